@@ -1,4 +1,6 @@
 use std::fmt::{self, Display, Formatter};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
 use clap::{ArgAction, Parser, Subcommand, ValueEnum};
@@ -21,7 +23,7 @@ pub struct CliArguments {
 #[derive(Debug, Clone, Subcommand)]
 #[command()]
 pub enum Command {
-    /// Compiles an input file into a PDF or PNG file
+    /// Compiles an input file into a PDF, PNG, SVG, or HTML file
     #[command(visible_alias = "c")]
     Compile(CompileCommand),
 
@@ -29,6 +31,12 @@ pub enum Command {
     #[command(visible_alias = "w")]
     Watch(CompileCommand),
 
+    /// Processes an input file to extract provided metadata
+    Query(QueryCommand),
+
+    /// Creates a new project from a template package
+    Init(InitCommand),
+
     /// Lists all discovered fonts in system and custom font paths
     Fonts(FontsCommand),
 }
@@ -36,40 +44,53 @@ pub enum Command {
 /// Compiles the input file into a PDF file
 #[derive(Debug, Clone, Parser)]
 pub struct CompileCommand {
-    /// Path to input Typst file
-    pub input: PathBuf,
+    /// Shared arguments
+    #[clap(flatten)]
+    pub common: SharedArgs,
 
-    /// Path to output PDF file or PNG file(s)
-    pub output: Option<PathBuf>,
-
-    /// Configures the project root
-    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
-    pub root: Option<PathBuf>,
+    /// Path to output PDF/HTML file or PNG/SVG file(s), or `-` for stdout.
+    /// For multiple images, `{p}`, `{0p}` and `{t}` are replaced by the page
+    /// number, the zero-padded page number and the page count
+    #[clap(value_parser = parse_output)]
+    pub output: Option<Output>,
 
-    /// Adds additional directories to search for fonts
-    #[clap(
-        long = "font-path",
-        env = "TYPST_FONT_PATHS",
-        value_name = "DIR",
-        action = ArgAction::Append,
-    )]
-    pub font_paths: Vec<PathBuf>,
+    /// Which pages to export, e.g. `1,3-5,10-`. Exports all pages if omitted
+    #[arg(long = "pages", value_delimiter = ',', value_parser = parse_page_range)]
+    pub pages: Option<Vec<PageRangeArgument>>,
 
     /// Opens the output file using the default viewer after compilation
     #[arg(long = "open")]
     pub open: Option<Option<String>>,
 
+    /// Serves a live preview on the given port of localhost, defaults to port
+    /// 3000 (only in watch mode)
+    #[arg(long = "serve", value_name = "PORT")]
+    pub serve: Option<Option<u16>>,
+
     /// The PPI (pixels per inch) to use for PNG export
     #[arg(long = "ppi", default_value_t = 144.0)]
     pub ppi: f32,
 
-    /// In which format to emit diagnostics
-    #[clap(
-        long,
-        default_value_t = DiagnosticFormat::Human,
-        value_parser = clap::value_parser!(DiagnosticFormat)
+    /// A PDF standard that the exported PDF must conform to
+    #[arg(long = "pdf-standard", value_enum)]
+    pub pdf_standard: Option<PdfStandard>,
+
+    /// The creation date written into the PDF's metadata, as a Unix timestamp.
+    /// Without it, no date is written
+    #[arg(
+        long = "creation-timestamp",
+        env = "SOURCE_DATE_EPOCH",
+        value_name = "UNIX_TIMESTAMP"
     )]
-    pub diagnostic_format: DiagnosticFormat,
+    pub creation_timestamp: Option<i64>,
+
+    /// Treats warnings as errors, failing the compilation if there are any
+    #[arg(long = "deny-warnings")]
+    pub deny_warnings: bool,
+
+    /// Prints the files written by the document after a successful compilation
+    #[arg(long = "manifest")]
+    pub manifest: bool,
 
     /// Produces a flamegraph of the compilation process
     #[arg(long = "flamegraph", value_name = "OUTPUT_SVG")]
@@ -78,16 +99,136 @@ pub struct CompileCommand {
 
 impl CompileCommand {
     /// The output path.
-    pub fn output(&self) -> PathBuf {
-        self.output
-            .clone()
-            .unwrap_or_else(|| self.input.with_extension("pdf"))
+    ///
+    /// Defaults to a PDF next to the input file or to stdout if the input is
+    /// read from stdin.
+    pub fn output(&self) -> Output {
+        self.output.clone().unwrap_or_else(|| match &self.common.input {
+            Input::Path(path) => Output::Path(path.with_extension("pdf")),
+            Input::Stdin => Output::Stdout,
+        })
     }
 }
 
-/// Lists all discovered fonts in system and custom font paths
+/// Creates a new project from a template package
 #[derive(Debug, Clone, Parser)]
-pub struct FontsCommand {
+pub struct InitCommand {
+    /// The template package to use, e.g. `@preview/charged-ieee:0.1.0`
+    pub template: String,
+
+    /// The directory of the new project, defaults to the template's name
+    pub dir: Option<PathBuf>,
+
+    /// Arguments related to package management
+    #[clap(flatten)]
+    pub package: PackageArgs,
+}
+
+/// Processes an input file to extract provided metadata
+#[derive(Debug, Clone, Parser)]
+pub struct QueryCommand {
+    /// Shared arguments
+    #[clap(flatten)]
+    pub common: SharedArgs,
+
+    /// Defines which elements to retrieve, e.g. `heading.where(level: 1)`
+    /// or `<intro>`
+    pub selector: String,
+
+    /// Extracts just one field from all retrieved elements
+    #[clap(long = "field")]
+    pub field: Option<String>,
+
+    /// Expects and retrieves exactly one element
+    #[clap(long = "one")]
+    pub one: bool,
+
+    /// The format to serialize in
+    #[clap(long = "format", default_value = "json")]
+    pub format: SerializationFormat,
+}
+
+/// Common arguments of compile, watch, and query.
+#[derive(Debug, Clone, Parser)]
+pub struct SharedArgs {
+    /// Path to input Typst file, or `-` to read from stdin
+    #[clap(value_parser = parse_input)]
+    pub input: Input,
+
+    /// Configures the project root
+    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Arguments related to fonts
+    #[clap(flatten)]
+    pub fonts: FontArgs,
+
+    /// Adds a string key-value pair, visible through `sys.inputs`
+    #[clap(
+        long = "input",
+        value_name = "key=value",
+        action = ArgAction::Append,
+        value_parser = parse_input_pair,
+    )]
+    pub inputs: Vec<(String, String)>,
+
+    /// Arguments related to package management
+    #[clap(flatten)]
+    pub package: PackageArgs,
+
+    /// Allows the document to write files into the given directory, which
+    /// must be contained in the project root
+    #[clap(long = "allow-write", value_name = "DIR")]
+    pub allow_write: Option<PathBuf>,
+
+    /// Allows a package that is not from `@local` to write files, e.g.
+    /// `@preview/example:0.1.0`
+    #[clap(
+        long = "allow-package-write",
+        value_name = "SPEC",
+        action = ArgAction::Append,
+    )]
+    pub allow_package_write: Vec<String>,
+
+    /// Records the hashes of packages that are not locked yet in the
+    /// project's `typst.lock`. Locked packages are always verified
+    #[clap(long = "lock")]
+    pub lock: bool,
+
+    /// In which format to emit diagnostics
+    #[clap(
+        long,
+        default_value_t = DiagnosticFormat::Human,
+        value_parser = clap::value_parser!(DiagnosticFormat)
+    )]
+    pub diagnostic_format: DiagnosticFormat,
+}
+
+/// Arguments related to package management.
+#[derive(Debug, Clone, Parser)]
+pub struct PackageArgs {
+    /// Custom directory for local packages, defaults to the system-dependent
+    /// data directory
+    #[clap(long = "package-path", env = "TYPST_PACKAGE_PATH", value_name = "DIR")]
+    pub package_path: Option<PathBuf>,
+
+    /// Base URL of the registry from which packages of all namespaces except
+    /// `@local` are downloaded, defaults to `https://packages.typst.org`
+    #[clap(
+        long = "package-registry",
+        env = "TYPST_PACKAGE_REGISTRY",
+        value_name = "URL"
+    )]
+    pub package_registry: Option<String>,
+
+    /// Never downloads packages, only uses local and cached ones
+    #[arg(long = "offline")]
+    pub offline: bool,
+}
+
+/// Arguments related to fonts.
+#[derive(Debug, Clone, Parser)]
+pub struct FontArgs {
     /// Adds additional directories to search for fonts
     #[clap(
         long = "font-path",
@@ -97,9 +238,166 @@ pub struct FontsCommand {
     )]
     pub font_paths: Vec<PathBuf>,
 
+    /// Ignores the fonts installed on the system
+    #[arg(long = "ignore-system-fonts")]
+    pub ignore_system_fonts: bool,
+
+    /// Ignores the fonts that are embedded in the binary
+    #[arg(long = "ignore-embedded-fonts")]
+    pub ignore_embedded_fonts: bool,
+}
+
+/// Lists all discovered fonts in system and custom font paths
+#[derive(Debug, Clone, Parser)]
+pub struct FontsCommand {
+    /// Arguments related to fonts
+    #[clap(flatten)]
+    pub fonts: FontArgs,
+
     /// Also lists style variants of each font family
     #[arg(long)]
     pub variants: bool,
+
+    /// Parses all font files again instead of using the font cache
+    #[arg(long)]
+    pub rebuild_cache: bool,
+
+    /// Only lists fonts that cover all characters of the given text
+    #[arg(long, value_name = "TEXT")]
+    pub covers: Option<String>,
+
+    /// In which format to list the fonts
+    #[arg(long = "format", value_enum, default_value_t = FontListFormat::Human)]
+    pub format: FontListFormat,
+}
+
+/// Which format to use for the font list.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum FontListFormat {
+    /// Font families and optionally their variants, for humans.
+    Human,
+    /// Details about each font as JSON, including its file, features and
+    /// coverage.
+    Json,
+}
+
+/// A PDF standard for exported documents.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum PdfStandard {
+    /// PDF/A-2b, for long-term archival.
+    #[value(name = "a-2b")]
+    A2b,
+}
+
+/// The input file, either stdin or a path.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Input {
+    /// Read the main source from stdin.
+    Stdin,
+    /// Read the main source from a file.
+    Path(PathBuf),
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdin => f.pad("stdin"),
+            Self::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+/// Parses an input path, where `-` stands for stdin.
+fn parse_input(raw: &str) -> Result<Input, String> {
+    Ok(match raw {
+        "-" => Input::Stdin,
+        path => Input::Path(path.into()),
+    })
+}
+
+/// The output file, either stdout or a path.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Output {
+    /// Write the result to stdout.
+    Stdout,
+    /// Write the result to a file.
+    Path(PathBuf),
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdout => f.pad("stdout"),
+            Self::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+/// Parses an output path, where `-` stands for stdout.
+fn parse_output(raw: &str) -> Result<Output, String> {
+    Ok(match raw {
+        "-" => Output::Stdout,
+        path => Output::Path(path.into()),
+    })
+}
+
+/// Parses a key-value pair of the form `key=value`.
+fn parse_input_pair(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or("input must be a key and a value separated by an equal sign")?;
+    let key = key.trim().to_owned();
+    if key.is_empty() {
+        return Err("the key was missing or empty".to_owned());
+    }
+    let value = value.trim().to_owned();
+    Ok((key, value))
+}
+
+/// A range of pages to export, e.g. `3-5`, `7` or `10-`.
+///
+/// Page numbers start at one. A missing start or end extends the range to
+/// the first or last page.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PageRangeArgument(pub RangeInclusive<Option<NonZeroUsize>>);
+
+/// Parses a single page number or a range of pages.
+fn parse_page_range(raw: &str) -> Result<PageRangeArgument, String> {
+    let parse = |number: &str| -> Result<Option<NonZeroUsize>, String> {
+        let number = number.trim();
+        if number.is_empty() {
+            return Ok(None);
+        }
+        match number.parse::<NonZeroUsize>() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(format!("`{number}` is not a valid page number")),
+        }
+    };
+
+    let range = match raw.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if let (Some(start), Some(end)) = (start, end) {
+                if start > end {
+                    return Err("page range must not end before it starts".into());
+                }
+            }
+            start..=end
+        }
+        None => {
+            let page = parse(raw)?.ok_or("page number must not be empty")?;
+            Some(page)..=Some(page)
+        }
+    };
+
+    Ok(PageRangeArgument(range))
+}
+
+/// Which format to use for serialized query output.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum SerializationFormat {
+    Json,
+    Yaml,
 }
 
 /// Which format to use for diagnostics.
@@ -107,6 +405,8 @@ pub struct FontsCommand {
 pub enum DiagnosticFormat {
     Human,
     Short,
+    /// One JSON object per line, for editors and other tools.
+    Json,
 }
 
 impl Display for DiagnosticFormat {
@@ -117,3 +417,62 @@ impl Display for DiagnosticFormat {
             .fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use typst::export::PageRanges;
+
+    use super::*;
+
+    /// A page range from optional start and end page numbers.
+    fn range(start: Option<usize>, end: Option<usize>) -> PageRangeArgument {
+        PageRangeArgument(PageRanges::range(start, end))
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range("3"), Ok(range(Some(3), Some(3))));
+        assert_eq!(parse_page_range("3-5"), Ok(range(Some(3), Some(5))));
+        assert_eq!(parse_page_range(" 3 - 5 "), Ok(range(Some(3), Some(5))));
+        assert_eq!(parse_page_range("4-4"), Ok(range(Some(4), Some(4))));
+        assert_eq!(parse_page_range("10-"), Ok(range(Some(10), None)));
+        assert_eq!(parse_page_range("-5"), Ok(range(None, Some(5))));
+        assert_eq!(parse_page_range("-"), Ok(range(None, None)));
+    }
+
+    #[test]
+    fn test_parse_invalid_page_range() {
+        assert_eq!(parse_page_range("").unwrap_err(), "page number must not be empty");
+        assert_eq!(parse_page_range("0").unwrap_err(), "`0` is not a valid page number");
+        assert_eq!(
+            parse_page_range("a-3").unwrap_err(),
+            "`a` is not a valid page number"
+        );
+        assert_eq!(
+            parse_page_range("1-2-3").unwrap_err(),
+            "`2-3` is not a valid page number"
+        );
+        assert_eq!(
+            parse_page_range("5-3").unwrap_err(),
+            "page range must not end before it starts",
+        );
+    }
+
+    #[test]
+    fn test_parse_pages_argument() {
+        let command =
+            CompileCommand::parse_from(["typst", "main.typ", "--pages", "1,3-5,10-"]);
+        assert_eq!(
+            command.pages,
+            Some(vec![
+                range(Some(1), Some(1)),
+                range(Some(3), Some(5)),
+                range(Some(10), None),
+            ]),
+        );
+
+        let result =
+            CompileCommand::try_parse_from(["typst", "main.typ", "--pages", "1,x"]);
+        assert!(result.is_err());
+    }
+}
@@ -1,41 +1,51 @@
+use std::ffi::OsString;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+use chrono::{Datelike, Timelike};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::term::{self, termcolor};
-use termcolor::{ColorChoice, StandardStream};
-use typst::diag::{bail, SourceError, StrResult};
-use typst::doc::Document;
-use typst::eval::eco_format;
+use serde::Serialize;
+use termcolor::{ColorChoice, StandardStream, WriteColor};
+use typst::diag::{bail, EcoString, Severity, SourceError, SourceResult, StrResult};
+use typst::doc::{Document, Frame};
+use typst::eval::{eco_format, Datetime, Tracer};
+use typst::export::{PageRanges, PdfOptions};
 use typst::file::FileId;
 use typst::geom::Color;
-use typst::syntax::Source;
+use typst::syntax::{Source, Span};
 use typst::World;
 
-use crate::args::{CompileCommand, DiagnosticFormat};
+use crate::args::{CompileCommand, DiagnosticFormat, Output, PdfStandard};
 use crate::watch::Status;
 use crate::world::SystemWorld;
-use crate::{color_stream, set_failed};
+use crate::{color_stream, print_warning, set_failed};
 
 type CodespanResult<T> = Result<T, CodespanError>;
 type CodespanError = codespan_reporting::files::Error;
 
 /// Execute a compilation command.
 pub fn compile(mut command: CompileCommand) -> StrResult<()> {
-    let mut world = SystemWorld::new(&command)?;
+    if command.serve.is_some() {
+        bail!("a live preview can only be served in watch mode");
+    }
+
+    let mut world = SystemWorld::new(&command.common)?;
     compile_once(&mut world, &mut command, false)?;
     Ok(())
 }
 
 /// Compile a single time.
 ///
-/// Returns whether it compiled without errors.
+/// Returns the document if it compiled and exported without errors.
 #[tracing::instrument(skip_all)]
 pub fn compile_once(
     world: &mut SystemWorld,
     command: &mut CompileCommand,
     watching: bool,
-) -> StrResult<()> {
+) -> StrResult<Option<Document>> {
     tracing::info!("Starting compilation");
 
     let start = std::time::Instant::now();
@@ -47,22 +57,61 @@ pub fn compile_once(
     world.reset();
     world.source(world.main()).map_err(|err| err.to_string())?;
 
-    let result = typst::compile(world);
+    // Export the PDF / PNG / SVG / HTML. Exporting may reject the document
+    // with further diagnostics, e.g. when it violates a PDF standard.
+    let mut tracer = Tracer::default();
+    let result = typst::compile(world, &mut tracer);
+    let mut warnings = tracer.warnings().to_vec();
+    let result = match result {
+        // Denied warnings fail the compilation and are reported as errors.
+        Ok(_) if command.deny_warnings && !warnings.is_empty() => Err(Box::new(
+            warnings
+                .drain(..)
+                .map(|warning| SourceError { severity: Severity::Error, ..warning })
+                .collect(),
+        )),
+        // The document's files are only written once nothing rejected it.
+        Ok(document) => match export(world, &mut tracer, &document, command) {
+            Ok(Ok(())) => tracer.flush_writes(world).map(|()| document),
+            Ok(Err(errors)) => Err(errors),
+            Err(message) => {
+                // Don't lose the warnings when the export itself fails.
+                print_diagnostics(world, warnings, command.common.diagnostic_format)
+                    .map_err(|_| "failed to print diagnostics")?;
+                return Err(message);
+            }
+        },
+        Err(errors) => Err(errors),
+    };
     let duration = start.elapsed();
 
     match result {
-        // Export the PDF / PNG.
         Ok(document) => {
-            export(&document, command)?;
-
             tracing::info!("Compilation succeeded in {duration:?}");
             if watching {
                 Status::Success(duration).print(command).unwrap();
             }
 
+            print_diagnostics(world, warnings, command.common.diagnostic_format)
+                .map_err(|_| "failed to print diagnostics")?;
+
+            // The document is fine even if its packages couldn't be recorded.
+            if let Err(err) = world.save_lockfile() {
+                print_warning(&format!("failed to update lockfile ({err})"))
+                    .map_err(|_| "failed to print warning")?;
+            }
+
+            if command.manifest {
+                print_manifest(world, &tracer).map_err(|_| "failed to print manifest")?;
+            }
+
             if let Some(open) = command.open.take() {
-                open_file(open.as_deref(), &command.output())?;
+                if let Output::Path(path) = command.output() {
+                    open_file(open.as_deref(), &path)?;
+                }
             }
+
+            Ok(Some(document))
         }
 
         // Print diagnostics.
@@ -74,60 +123,236 @@ pub fn compile_once(
                 Status::Error.print(command).unwrap();
             }
 
-            print_diagnostics(world, *errors, command.diagnostic_format)
+            warnings.extend(*errors);
+            print_diagnostics(world, warnings, command.common.diagnostic_format)
                 .map_err(|_| "failed to print diagnostics")?;
+
+            Ok(None)
         }
     }
-
-    Ok(())
 }
 
 /// Export into the target format.
-fn export(document: &Document, command: &CompileCommand) -> StrResult<()> {
-    match command.output().extension() {
-        Some(ext) if ext.eq_ignore_ascii_case("png") => export_png(document, command),
+///
+/// The outer result fails if the output could not be written, the inner one
+/// if the document cannot be represented in the target format.
+fn export(
+    world: &SystemWorld,
+    tracer: &mut Tracer,
+    document: &Document,
+    command: &CompileCommand,
+) -> StrResult<SourceResult<()>> {
+    let extension = match command.output() {
+        Output::Path(path) => path.extension().map(ToOwned::to_owned),
+        Output::Stdout => None,
+    };
+
+    match extension {
+        Some(ext) if ext.eq_ignore_ascii_case("png") => {
+            export_png(document, command).map(Ok)
+        }
+        Some(ext) if ext.eq_ignore_ascii_case("svg") => {
+            export_svg(document, command).map(Ok)
+        }
+        Some(ext) if ext.eq_ignore_ascii_case("html") => {
+            export_html(world, tracer, document, command)
+        }
         _ => export_pdf(document, command),
     }
 }
 
 /// Export to a PDF.
-fn export_pdf(document: &Document, command: &CompileCommand) -> StrResult<()> {
+fn export_pdf(
+    document: &Document,
+    command: &CompileCommand,
+) -> StrResult<SourceResult<()>> {
     let output = command.output();
-    let buffer = typst::export::pdf(document);
-    fs::write(output, buffer).map_err(|_| "failed to write PDF file")?;
-    Ok(())
+    let options = PdfOptions {
+        standard: match command.pdf_standard {
+            Some(PdfStandard::A2b) => typst::export::PdfStandard::A2b,
+            None => typst::export::PdfStandard::V1_7,
+        },
+        page_ranges: page_ranges(command, document)?,
+        timestamp: command.creation_timestamp.map(convert_timestamp).transpose()?,
+    };
+
+    // Stream the PDF into the output instead of building it in memory first.
+    let result = match &output {
+        Output::Path(path) => {
+            // Write into a temporary file that only replaces the output once
+            // the export succeeded. That way, a failed export doesn't destroy
+            // the last good PDF, e.g. in watch mode.
+            let temp = partial_path(path);
+            let file =
+                fs::File::create(&temp).map_err(|_| "failed to create PDF file")?;
+            let result =
+                typst::export::pdf_into(document, &options, io::BufWriter::new(file));
+            if let Ok(Ok(())) = result {
+                fs::rename(&temp, path).map_err(|_| "failed to write PDF file")?;
+            } else {
+                fs::remove_file(&temp).ok();
+            }
+            result
+        }
+        Output::Stdout => {
+            typst::export::pdf_into(document, &options, io::stdout().lock())
+        }
+    };
+
+    match result {
+        Ok(written) => written.map_err(|_| "failed to write PDF file")?,
+        Err(errors) => return Ok(Err(errors)),
+    }
+
+    Ok(Ok(()))
+}
+
+/// The path of the hidden file next to an output into which the output is
+/// written before it replaces the output.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Convert a Unix timestamp into a UTC datetime.
+fn convert_timestamp(timestamp: i64) -> StrResult<Datetime> {
+    let datetime = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .filter(|datetime| datetime.year() >= 0)
+        .ok_or("creation timestamp is out of range")?;
+    Ok(Datetime::from_ymd_hms(
+        datetime.year(),
+        datetime.month() as u8,
+        datetime.day() as u8,
+        datetime.hour() as u8,
+        datetime.minute() as u8,
+        datetime.second() as u8,
+    )
+    .ok_or("creation timestamp is out of range")?)
 }
 
 /// Export to one or multiple PNGs.
 fn export_png(document: &Document, command: &CompileCommand) -> StrResult<()> {
-    // Determine whether we have a `{n}` numbering.
+    export_image(document, command, "PNG", |frame| {
+        let pixmap = typst::export::render(frame, command.ppi / 72.0, Color::WHITE);
+        pixmap.encode_png().map_err(|_| "failed to encode PNG file".into())
+    })
+}
+
+/// Export to one or multiple SVGs.
+fn export_svg(document: &Document, command: &CompileCommand) -> StrResult<()> {
+    export_image(document, command, "SVG", |frame| {
+        Ok(typst::export::svg(frame).into_bytes())
+    })
+}
+
+/// Export the selected pages into one image file per page.
+fn export_image(
+    document: &Document,
+    command: &CompileCommand,
+    format: &str,
+    encode: impl Fn(&Frame) -> StrResult<Vec<u8>>,
+) -> StrResult<()> {
+    let ranges = page_ranges(command, document)?;
+    let pages: Vec<(usize, &Frame)> = document
+        .pages
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| ranges.as_ref().map_or(true, |r| r.includes_page_index(i)))
+        .collect();
+
+    // Determine whether we have a page number in the output path. `{n}` is
+    // the older spelling of `{0p}`.
     let output = command.output();
-    let string = output.to_str().unwrap_or_default();
-    let numbered = string.contains("{n}");
-    if !numbered && document.pages.len() > 1 {
-        bail!("cannot export multiple PNGs without `{{n}}` in output path");
+    let template = match &output {
+        Output::Path(path) => path.to_str().unwrap_or_default(),
+        Output::Stdout => "",
+    };
+    let numbered = ["{p}", "{0p}", "{n}"].iter().any(|p| template.contains(p));
+    if !numbered && pages.len() > 1 {
+        bail!(
+            "cannot export multiple {format}s without a page number \
+             (`{{p}}` or `{{0p}}`) in output path"
+        );
     }
 
-    // Find a number width that accommodates all pages. For instance, the
-    // first page should be numbered "001" if there are between 100 and
-    // 999 pages.
-    let width = 1 + document.pages.len().checked_ilog10().unwrap_or(0) as usize;
-    let mut storage;
-
-    for (i, frame) in document.pages.iter().enumerate() {
-        let pixmap = typst::export::render(frame, command.ppi / 72.0, Color::WHITE);
-        let path = if numbered {
-            storage = string.replace("{n}", &format!("{:0width$}", i + 1));
-            Path::new(&storage)
+    let total = document.pages.len();
+    for (i, frame) in pages {
+        let buffer = encode(frame)?;
+        let result = if numbered {
+            fs::write(expand_template(template, i + 1, total), buffer)
         } else {
-            output.as_path()
+            write_output(&output, &buffer)
         };
-        pixmap.save_png(path).map_err(|_| "failed to write PNG file")?;
+        result.map_err(|_| eco_format!("failed to write {format} file"))?;
     }
 
     Ok(())
 }
 
+/// The pages selected with `--pages`, if any.
+///
+/// Fails if the selection doesn't match any page of the document, as there
+/// would be nothing to export.
+fn page_ranges(
+    command: &CompileCommand,
+    document: &Document,
+) -> StrResult<Option<PageRanges>> {
+    let Some(pages) = &command.pages else { return Ok(None) };
+    let ranges = PageRanges::new(pages.iter().map(|range| range.0.clone()).collect());
+    if !(0..document.pages.len()).any(|i| ranges.includes_page_index(i)) {
+        bail!(
+            "no page matches the page selection (last page is {})",
+            document.pages.len()
+        );
+    }
+
+    Ok(Some(ranges))
+}
+
+/// Fill in the page number placeholders of an output path template.
+fn expand_template(template: &str, page: usize, total: usize) -> String {
+    // Find a number width that accommodates all pages. For instance, the
+    // first page should be numbered "001" if there are between 100 and
+    // 999 pages.
+    let width = 1 + total.checked_ilog10().unwrap_or(0) as usize;
+    let padded = format!("{page:0width$}");
+    template
+        .replace("{p}", &page.to_string())
+        .replace("{0p}", &padded)
+        .replace("{n}", &padded)
+        .replace("{t}", &total.to_string())
+}
+
+/// Export to an HTML file.
+fn export_html(
+    world: &SystemWorld,
+    tracer: &mut Tracer,
+    document: &Document,
+    command: &CompileCommand,
+) -> StrResult<SourceResult<()>> {
+    let html = match typst_library::html::html(world, tracer, document) {
+        Ok(html) => html,
+        Err(errors) => return Ok(Err(errors)),
+    };
+    write_output(&command.output(), html.as_bytes())
+        .map_err(|_| "failed to write HTML file")?;
+    Ok(Ok(()))
+}
+
+/// Write exported data to the output file or to stdout.
+fn write_output(output: &Output, buffer: &[u8]) -> io::Result<()> {
+    match output {
+        Output::Path(path) => fs::write(path, buffer),
+        Output::Stdout => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(buffer)?;
+            stdout.flush()
+        }
+    }
+}
+
 /// Opens the given file using:
 /// - The default file viewer if `open` is `None`.
 /// - The given viewer provided by `open` if it is `Some`.
@@ -141,15 +366,35 @@ fn open_file(open: Option<&str>, path: &Path) -> StrResult<()> {
     Ok(())
 }
 
+/// Print the files that were written by the document.
+fn print_manifest(world: &SystemWorld, tracer: &Tracer) -> io::Result<()> {
+    let mut w = color_stream();
+    let styles = term::Styles::default();
+
+    for (id, _) in tracer.writes() {
+        w.set_color(&styles.header_help)?;
+        write!(w, "wrote")?;
+
+        w.reset()?;
+        match world.write_path(id) {
+            Ok(path) => writeln!(w, " {}", path.display())?,
+            Err(_) => writeln!(w, " {id}")?,
+        }
+    }
+
+    Ok(())
+}
+
 /// Print diagnostic messages to the terminal.
-fn print_diagnostics(
+pub fn print_diagnostics(
     world: &SystemWorld,
-    errors: Vec<SourceError>,
+    diagnostics: Vec<SourceError>,
     diagnostic_format: DiagnosticFormat,
 ) -> Result<(), codespan_reporting::files::Error> {
     let mut w = match diagnostic_format {
         DiagnosticFormat::Human => color_stream(),
         DiagnosticFormat::Short => StandardStream::stderr(ColorChoice::Never),
+        DiagnosticFormat::Json => return print_json_diagnostics(world, diagnostics),
     };
 
     let mut config = term::Config { tab_width: 2, ..Default::default() };
@@ -157,27 +402,30 @@ fn print_diagnostics(
         config.display_style = term::DisplayStyle::Short;
     }
 
-    for error in errors {
+    for error in diagnostics {
         // The main diagnostic.
-        let diag = Diagnostic::error()
-            .with_message(error.message)
-            .with_notes(
-                error
-                    .hints
-                    .iter()
-                    .map(|e| (eco_format!("hint: {e}")).into())
-                    .collect(),
-            )
-            .with_labels(vec![Label::primary(error.span.id(), error.span.range(world))]);
+        let diag = match error.severity {
+            Severity::Error => Diagnostic::error(),
+            Severity::Warning => Diagnostic::warning(),
+        }
+        .with_message(error.message)
+        .with_notes(
+            error
+                .hints
+                .iter()
+                .map(|e| (eco_format!("hint: {e}")).into())
+                .collect(),
+        )
+        .with_labels(label(world, error.span).into_iter().collect());
 
         term::emit(&mut w, &config, world, &diag)?;
 
         // Stacktrace-like helper diagnostics.
         for point in error.trace {
             let message = point.v.to_string();
-            let help = Diagnostic::help().with_message(message).with_labels(vec![
-                Label::primary(point.span.id(), point.span.range(world)),
-            ]);
+            let help = Diagnostic::help()
+                .with_message(message)
+                .with_labels(label(world, point.span).into_iter().collect());
 
             term::emit(&mut w, &config, world, &help)?;
         }
@@ -186,6 +434,111 @@ fn print_diagnostics(
     Ok(())
 }
 
+/// Print diagnostic messages as JSON, one object per line.
+fn print_json_diagnostics(
+    world: &SystemWorld,
+    diagnostics: Vec<SourceError>,
+) -> Result<(), codespan_reporting::files::Error> {
+    let mut w = io::stderr().lock();
+    for error in diagnostics {
+        let diag = JsonDiagnostic {
+            severity: match error.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            message: error.message,
+            location: JsonLocation::new(world, error.span),
+            hints: error.hints,
+            trace: error
+                .trace
+                .into_iter()
+                .map(|point| JsonTracepoint {
+                    message: point.v.to_string(),
+                    location: JsonLocation::new(world, point.span),
+                })
+                .collect(),
+        };
+
+        serde_json::to_writer(&mut w, &diag).map_err(io::Error::from)?;
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// A diagnostic in the JSON diagnostic format.
+#[derive(Serialize)]
+struct JsonDiagnostic {
+    severity: &'static str,
+    message: EcoString,
+    #[serde(flatten)]
+    location: Option<JsonLocation>,
+    hints: Vec<EcoString>,
+    trace: Vec<JsonTracepoint>,
+}
+
+/// A tracepoint in the JSON diagnostic format.
+#[derive(Serialize)]
+struct JsonTracepoint {
+    message: String,
+    #[serde(flatten)]
+    location: Option<JsonLocation>,
+}
+
+/// The source location of a diagnostic or tracepoint.
+#[derive(Serialize)]
+struct JsonLocation {
+    /// The file's path within its project or package.
+    file: String,
+    /// The byte range in the file.
+    range: Range<usize>,
+    /// The one-based line and column where the range starts.
+    start: JsonPosition,
+    /// The one-based line and column where the range ends.
+    end: JsonPosition,
+}
+
+/// A line and column in a source file.
+#[derive(Serialize)]
+struct JsonPosition {
+    line: usize,
+    column: usize,
+}
+
+impl JsonLocation {
+    /// Locate a span, unless it is detached.
+    fn new(world: &SystemWorld, span: Span) -> Option<Self> {
+        if span.is_detached() {
+            return None;
+        }
+
+        let source = world.source(span.id()).ok()?;
+        let range = span.range_in(&source);
+        let position = |byte| {
+            Some(JsonPosition {
+                line: source.byte_to_line(byte)? + 1,
+                column: source.byte_to_column(byte)? + 1,
+            })
+        };
+
+        Some(Self {
+            file: span.id().to_string(),
+            start: position(range.start)?,
+            end: position(range.end)?,
+            range,
+        })
+    }
+}
+
+/// Create a label for a span, unless it is detached.
+fn label(world: &SystemWorld, span: Span) -> Option<Label<FileId>> {
+    if span.is_detached() {
+        return None;
+    }
+
+    Some(Label::primary(span.id(), span.range(world)))
+}
+
 impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
     type FileId = FileId;
     type Name = FileId;
@@ -237,3 +590,91 @@ impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use typst::geom::{Abs, Size};
+
+    use super::*;
+
+    #[test]
+    fn test_page_ranges() {
+        let document = Document {
+            pages: vec![Frame::new(Size::zero()); 3],
+            ..Default::default()
+        };
+
+        let ranges = |pages: &[&str]| {
+            let args = ["compile", "main.typ"].iter().chain(pages);
+            page_ranges(&CompileCommand::parse_from(args), &document)
+        };
+
+        assert_eq!(ranges(&[]), Ok(None));
+        assert!(ranges(&["--pages", "2"]).unwrap().is_some());
+        assert!(ranges(&["--pages", "3-"]).unwrap().is_some());
+        assert!(ranges(&["--pages", "5,1"]).unwrap().is_some());
+        assert_eq!(
+            ranges(&["--pages", "4"]).unwrap_err(),
+            "no page matches the page selection (last page is 3)",
+        );
+        assert!(ranges(&["--pages", "4-,10-12"]).is_err());
+    }
+
+    #[test]
+    fn test_failed_pdf_export_keeps_previous_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.pdf");
+        fs::write(&path, "previous").unwrap();
+
+        let output = path.to_str().unwrap();
+        let args = ["compile", "main.typ", output, "--pdf-standard", "a-2b"];
+        let command = CompileCommand::parse_from(args);
+
+        // A page that is too small for PDF/A.
+        let tiny = Document {
+            pages: vec![Frame::new(Size::splat(Abs::pt(1.0)))],
+            ..Default::default()
+        };
+        assert!(export_pdf(&tiny, &command).unwrap().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous");
+
+        let fine = Document {
+            pages: vec![Frame::new(Size::splat(Abs::pt(100.0)))],
+            ..Default::default()
+        };
+        assert!(export_pdf(&fine, &command).unwrap().is_ok());
+        assert!(fs::read(&path).unwrap().starts_with(b"%PDF-"));
+        assert!(!partial_path(&path).exists());
+    }
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(expand_template("page-{p}.png", 3, 12), "page-3.png");
+        assert_eq!(expand_template("page-{0p}.png", 3, 12), "page-03.png");
+        assert_eq!(expand_template("page-{n}.png", 3, 12), "page-03.png");
+        assert_eq!(expand_template("{p}-of-{t}.svg", 3, 12), "3-of-12.svg");
+        assert_eq!(expand_template("{0p}.png", 7, 100), "007.png");
+        assert_eq!(expand_template("{0p}.png", 100, 100), "100.png");
+        assert_eq!(expand_template("{0p}.png", 1, 1), "1.png");
+        assert_eq!(expand_template("out.png", 2, 5), "out.png");
+    }
+
+    #[test]
+    fn test_convert_timestamp() {
+        assert_eq!(
+            convert_timestamp(0),
+            Ok(Datetime::from_ymd_hms(1970, 1, 1, 0, 0, 0).unwrap()),
+        );
+        assert_eq!(
+            convert_timestamp(1_000_000_000),
+            Ok(Datetime::from_ymd_hms(2001, 9, 9, 1, 46, 40).unwrap()),
+        );
+
+        // Years above 9999 and below 0 can't be represented.
+        assert!(convert_timestamp(253_402_300_799).is_ok());
+        assert!(convert_timestamp(253_402_300_800).is_err());
+        assert!(convert_timestamp(-62_167_219_201).is_err());
+        assert!(convert_timestamp(i64::MAX).is_err());
+    }
+}
@@ -1,40 +1,167 @@
 use std::cell::OnceCell;
+use std::collections::HashMap;
 use std::env;
 use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
 use typst::diag::StrResult;
+use typst::eval::eco_format;
 use typst::font::{Font, FontBook, FontInfo, FontVariant};
 use typst::util::Bytes;
 use walkdir::WalkDir;
 
-use crate::args::FontsCommand;
+use crate::args::{FontArgs, FontListFormat, FontsCommand};
 
 /// Execute a font listing command.
 pub fn fonts(command: FontsCommand) -> StrResult<()> {
     let mut searcher = FontSearcher::new();
-    searcher.search(&command.font_paths);
-
-    for (name, infos) in searcher.book.families() {
-        println!("{name}");
-        if command.variants {
-            for info in infos {
-                let FontVariant { style, weight, stretch } = info.variant;
-                println!("- Style: {style:?}, Weight: {weight:?}, Stretch: {stretch:?}");
+    if command.rebuild_cache {
+        searcher.rebuild_cache();
+    }
+    searcher.search(&command.fonts);
+
+    // Whether a font covers all characters of the `--covers` text.
+    let covers = |info: &FontInfo| {
+        command.covers.as_ref().map_or(true, |text| {
+            text.chars()
+                .filter(|c| !c.is_whitespace())
+                .all(|c| info.coverage.contains(c as u32))
+        })
+    };
+
+    match command.format {
+        FontListFormat::Human => {
+            for (name, infos) in searcher.book.families() {
+                let infos: Vec<_> = infos.filter(|info| covers(info)).collect();
+                if infos.is_empty() {
+                    continue;
+                }
+
+                println!("{name}");
+                if command.variants {
+                    for info in infos {
+                        let FontVariant { style, weight, stretch } = info.variant;
+                        println!(
+                            "- Style: {style:?}, Weight: {weight:?}, Stretch: {stretch:?}"
+                        );
+                    }
+                }
             }
         }
+        FontListFormat::Json => {
+            let fonts: Vec<_> = searcher
+                .fonts
+                .iter()
+                .enumerate()
+                .filter_map(|(i, slot)| {
+                    let info = searcher.book.info(i)?;
+                    covers(info).then(|| JsonFont::new(info, slot))
+                })
+                .collect();
+
+            let json = serde_json::to_string_pretty(&fonts)
+                .map_err(|err| eco_format!("failed to serialize fonts ({err})"))?;
+            println!("{json}");
+        }
     }
 
     Ok(())
 }
 
+/// A font in the JSON font list.
+#[derive(Serialize)]
+struct JsonFont<'a> {
+    /// The font's family.
+    family: &'a str,
+    /// The path of the font file, `None` for embedded fonts.
+    path: Option<&'a Path>,
+    /// The index of the font in its collection.
+    index: u32,
+    /// The font's style, weight and stretch.
+    variant: FontVariant,
+    /// The tags of the OpenType features the font supports.
+    features: Vec<String>,
+    /// The ranges of codepoints the font covers, with inclusive bounds.
+    coverage: Vec<[u32; 2]>,
+}
+
+impl<'a> JsonFont<'a> {
+    fn new(info: &'a FontInfo, slot: &'a FontSlot) -> Self {
+        // The features are not part of the metadata, so the font must be
+        // loaded.
+        let mut features: Vec<String> = slot
+            .get()
+            .map(|font| {
+                let tables = font.ttf().tables();
+                tables
+                    .gsub
+                    .into_iter()
+                    .chain(tables.gpos)
+                    .flat_map(|table| table.features)
+                    .map(|feature| {
+                        String::from_utf8_lossy(&feature.tag.to_bytes()).into_owned()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        features.sort();
+        features.dedup();
+
+        Self {
+            family: &info.family,
+            path: Some(slot.path.as_path()).filter(|path| !path.as_os_str().is_empty()),
+            index: slot.index,
+            variant: info.variant,
+            features,
+            coverage: info
+                .coverage
+                .ranges()
+                .map(|range| [range.start, range.end - 1])
+                .collect(),
+        }
+    }
+}
+
 /// Searches for fonts.
 pub struct FontSearcher {
     /// Metadata about all discovered fonts.
     pub book: FontBook,
     /// Slots that the fonts are loaded into.
     pub fonts: Vec<FontSlot>,
+    /// Where the font cache is stored, if anywhere.
+    cache_path: Option<PathBuf>,
+    /// Metadata of font files from previous searches.
+    cache: HashMap<PathBuf, CachedFile>,
+    /// Metadata of the font files found in this search.
+    found: HashMap<PathBuf, CachedFile>,
+    /// Whether a font file had to be parsed because it was not cached.
+    parsed: bool,
+}
+
+/// The metadata of the fonts in one file, as stored in the font cache.
+#[derive(Serialize, Deserialize)]
+struct CachedFile {
+    /// The path of the font file.
+    path: PathBuf,
+    /// The file's modification time in seconds and nanoseconds since the
+    /// Unix epoch. The cached metadata is only used if it is unchanged.
+    modified: (u64, u32),
+    /// The metadata of each font in the file.
+    infos: Vec<FontInfo>,
+}
+
+/// The font cache on disk.
+#[derive(Serialize, Deserialize)]
+struct FontCache {
+    /// The version of Typst that wrote the cache. Caches from other versions
+    /// are discarded because the metadata format may have changed.
+    version: String,
+    /// The cached font files.
+    files: Vec<CachedFile>,
 }
 
 /// Holds details about the location of a font and lazily the font itself.
@@ -62,20 +189,79 @@ impl FontSlot {
 
 impl FontSearcher {
     /// Create a new, empty system searcher.
+    ///
+    /// Loads the metadata of previously found fonts from the font cache.
     pub fn new() -> Self {
-        Self { book: FontBook::new(), fonts: vec![] }
+        Self::with_cache(cache_path())
+    }
+
+    /// Create a new, empty system searcher that uses the font cache at the
+    /// given path.
+    fn with_cache(cache_path: Option<PathBuf>) -> Self {
+        let cache = cache_path
+            .as_deref()
+            .map(load_cache)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect();
+
+        Self {
+            book: FontBook::new(),
+            fonts: vec![],
+            cache_path,
+            cache,
+            found: HashMap::new(),
+            parsed: false,
+        }
     }
 
-    /// Search everything that is available.
-    pub fn search(&mut self, font_paths: &[PathBuf]) {
-        self.search_system();
+    /// Discard the cached metadata so that all font files are parsed again.
+    pub fn rebuild_cache(&mut self) {
+        self.cache.clear();
+        self.parsed = true;
+    }
+
+    /// Search everything that is available, except for the system or embedded
+    /// fonts if they are ignored.
+    pub fn search(&mut self, args: &FontArgs) {
+        if !args.ignore_system_fonts {
+            self.search_system();
+        }
 
         #[cfg(feature = "embed-fonts")]
-        self.add_embedded();
+        if !args.ignore_embedded_fonts {
+            self.add_embedded();
+        }
 
-        for path in font_paths {
+        for path in &args.font_paths {
             self.search_dir(path)
         }
+
+        // Merge the files found in this search into the cache. Files that were
+        // not searched this time, e.g. because system fonts are ignored, stay
+        // cached unless they were removed.
+        let mut files = std::mem::take(&mut self.found);
+        let mut pruned = false;
+        for (path, file) in std::mem::take(&mut self.cache) {
+            if files.contains_key(&path) {
+                continue;
+            }
+
+            if path.exists() {
+                files.insert(path, file);
+            } else {
+                pruned = true;
+            }
+        }
+
+        // Only write the cache if it is out of date, i.e. if a file was
+        // added, changed, or removed.
+        if let Some(path) = &self.cache_path {
+            if self.parsed || pruned {
+                save_cache(path, files.into_values().collect());
+            }
+        }
     }
 
     /// Add fonts that are embedded in the binary.
@@ -166,18 +352,172 @@ impl FontSearcher {
     }
 
     /// Index the fonts in the file at the given path.
+    ///
+    /// Uses the cached metadata if the file was not modified since it was
+    /// cached.
     fn search_file(&mut self, path: &Path) {
-        if let Ok(file) = File::open(path) {
-            if let Ok(mmap) = unsafe { Mmap::map(&file) } {
-                for (i, info) in FontInfo::iter(&mmap).enumerate() {
-                    self.book.push(info);
-                    self.fonts.push(FontSlot {
-                        path: path.into(),
-                        index: i as u32,
-                        font: OnceCell::new(),
-                    });
-                }
+        let Some(modified) = modification_time(path) else { return };
+        let cached = self
+            .found
+            .get(path)
+            .or_else(|| self.cache.get(path))
+            .filter(|file| file.modified == modified);
+
+        // Files without any valid font are cached, too, so that they aren't
+        // parsed again and again.
+        let infos = match cached {
+            Some(file) => file.infos.clone(),
+            None => {
+                self.parsed = true;
+                parse_file(path).unwrap_or_default()
             }
+        };
+
+        for (i, info) in infos.iter().enumerate() {
+            self.book.push(info.clone());
+            self.fonts.push(FontSlot {
+                path: path.into(),
+                index: i as u32,
+                font: OnceCell::new(),
+            });
         }
+
+        self.found
+            .insert(path.into(), CachedFile { path: path.into(), modified, infos });
+    }
+}
+
+/// Parse the metadata of the fonts in the file at the given path.
+fn parse_file(path: &Path) -> Option<Vec<FontInfo>> {
+    let file = File::open(path).ok()?;
+    let mmap = unsafe { Mmap::map(&file) }.ok()?;
+    Some(FontInfo::iter(&mmap).collect())
+}
+
+/// The modification time of a file in seconds and nanoseconds since the Unix
+/// epoch.
+fn modification_time(path: &Path) -> Option<(u64, u32)> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let duration = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some((duration.as_secs(), duration.subsec_nanos()))
+}
+
+/// The path of the font cache.
+fn cache_path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("typst/fonts.json"))
+}
+
+/// Load the cached font metadata, if there is a usable cache.
+fn load_cache(path: &Path) -> Vec<CachedFile> {
+    let Ok(data) = fs::read(path) else { return vec![] };
+
+    match serde_json::from_slice::<FontCache>(&data) {
+        Ok(cache) if cache.version == crate::typst_version() => cache.files,
+        _ => vec![],
+    }
+}
+
+/// Write the font metadata to the cache.
+///
+/// Failing to do so is not an error, the fonts will just be parsed again the
+/// next time.
+fn save_cache(path: &Path, mut files: Vec<CachedFile>) {
+    // Paths that are not valid UTF-8 cannot be stored.
+    files.retain(|file| file.path.to_str().is_some());
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let cache = FontCache { version: crate::typst_version().into(), files };
+    let Ok(data) = serde_json::to_vec(&cache) else { return };
+    let Some(parent) = path.parent() else { return };
+
+    // Write into a temporary file that is then moved into place, so that a
+    // concurrent search never reads a partially written cache.
+    let result = fs::create_dir_all(parent)
+        .and_then(|_| tempfile::NamedTempFile::new_in(parent))
+        .and_then(|mut file| {
+            file.write_all(&data)?;
+            file.persist(path).map_err(|err| err.error)?;
+            Ok(())
+        });
+
+    if let Err(err) = result {
+        tracing::info!("Failed to write font cache: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Copy a font from the repository's assets into a directory.
+    fn copy_font(name: &str, dir: &Path) -> PathBuf {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/fonts");
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::copy(assets.join(name), &path).unwrap();
+        path
+    }
+
+    /// Search the given directories, but not the embedded fonts.
+    fn search(cache: &Path, font_paths: &[&Path], ignore_system_fonts: bool) {
+        let mut searcher = FontSearcher::with_cache(Some(cache.into()));
+        searcher.search(&FontArgs {
+            font_paths: font_paths.iter().map(|&path| path.into()).collect(),
+            ignore_system_fonts,
+            ignore_embedded_fonts: true,
+        });
+    }
+
+    /// The paths of the files in the font cache.
+    fn cached(cache: &Path) -> Vec<PathBuf> {
+        load_cache(cache).into_iter().map(|file| file.path).collect()
+    }
+
+    #[test]
+    fn test_font_cache_is_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("fonts.json");
+        let a = copy_font("LinLibertine_R.ttf", &dir.path().join("a"));
+        let b = copy_font("DejaVuSansMono.ttf", &dir.path().join("b"));
+
+        search(&cache, &[&dir.path().join("a")], true);
+        assert_eq!(cached(&cache), [a.clone()]);
+
+        // Fonts that weren't searched this time stay cached.
+        search(&cache, &[&dir.path().join("b")], true);
+        assert_eq!(cached(&cache), [a.clone(), b.clone()]);
+
+        // Fonts whose files were removed are dropped.
+        fs::remove_file(&a).unwrap();
+        search(&cache, &[], true);
+        assert_eq!(cached(&cache), [b]);
+    }
+
+    #[test]
+    fn test_ignoring_system_fonts_keeps_them_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("fonts.json");
+        let a = copy_font("LinLibertine_R.ttf", &dir.path().join("a"));
+        let b = copy_font("DejaVuSansMono.ttf", &dir.path().join("b"));
+
+        // Whatever system fonts there are end up in the cache, next to ours.
+        search(&cache, &[&dir.path().join("a")], false);
+        let with_system = cached(&cache);
+        assert!(with_system.contains(&a));
+
+        // Ignoring them for one search doesn't drop them from the cache.
+        search(&cache, &[&dir.path().join("b")], true);
+        let without_system = cached(&cache);
+        assert!(without_system.contains(&b));
+        assert!(with_system.iter().all(|path| without_system.contains(path)));
+
+        // And they are used again from the cache once they are searched again.
+        let mut searcher = FontSearcher::with_cache(Some(cache.clone()));
+        searcher.search(&FontArgs {
+            font_paths: vec![],
+            ignore_system_fonts: false,
+            ignore_embedded_fonts: true,
+        });
+        assert!(!searcher.parsed);
     }
 }
@@ -0,0 +1,254 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use codespan_reporting::term::{self, termcolor};
+use termcolor::WriteColor;
+use typst::diag::{bail, FileError, StrResult};
+use typst::eval::eco_format;
+use typst::file::{FileId, PackageManifest, PackageSpec};
+use typst::syntax::{ast, LinkedNode, SyntaxKind};
+use typst::util::PathExt;
+use walkdir::WalkDir;
+
+use crate::args::InitCommand;
+use crate::color_stream;
+use crate::package::PackageStorage;
+
+/// Execute an initialization command.
+pub fn init(command: InitCommand) -> StrResult<()> {
+    let spec: PackageSpec = command.template.parse()?;
+
+    // Make the template package available and read its manifest.
+    let storage = PackageStorage::new(&command.package);
+    let package_dir = storage.prepare_package(&spec)?;
+    let manifest_path = package_dir.join("typst.toml");
+    let bytes = fs::read(&manifest_path)
+        .map_err(|err| FileError::from_io(err, &manifest_path))?;
+    let manifest = PackageManifest::parse(&bytes)?;
+    manifest.validate(&spec)?;
+
+    let Some(template) = &manifest.template else {
+        bail!("package {spec} does not provide a template");
+    };
+
+    let project_dir = command.dir.unwrap_or_else(|| PathBuf::from(spec.name.as_str()));
+    if project_dir.exists() {
+        bail!("project directory already exists (at {})", project_dir.display());
+    }
+
+    let template_dir = package_dir
+        .join_rooted(Path::new(template.path.as_str()))
+        .ok_or("template directory must be contained in the package")?;
+    if !template_dir.is_dir() {
+        bail!("template directory not found (searched at {})", template_dir.display());
+    }
+
+    // Files within the template are identified relative to the package, so
+    // that imports of the package's entrypoint can be recognized.
+    let template_root = Path::new("/").join(template.path.as_str());
+    let entrypoint_id =
+        FileId::new(None, &Path::new("/").join(manifest.package.entrypoint.as_str()));
+
+    copy_template(&template_dir, &project_dir, &template_root, entrypoint_id, &spec)
+        .map_err(|err| eco_format!("failed to create project ({err})"))?;
+
+    let main = project_dir.join(template.entrypoint.as_str());
+    print_created(&spec, &project_dir, &main).unwrap();
+
+    Ok(())
+}
+
+/// Copy the template directory into the project directory, rewriting imports
+/// of the package's entrypoint in Typst files into imports of the package.
+///
+/// Removes the project directory again if copying fails, so that no partial
+/// project is left behind.
+fn copy_template(
+    template_dir: &Path,
+    project_dir: &Path,
+    template_root: &Path,
+    entrypoint_id: FileId,
+    spec: &PackageSpec,
+) -> io::Result<()> {
+    let result =
+        copy_files(template_dir, project_dir, template_root, entrypoint_id, spec);
+    if result.is_err() {
+        fs::remove_dir_all(project_dir).ok();
+    }
+    result
+}
+
+/// Copy the files of the template, see [`copy_template`].
+///
+/// Symbolic links are rejected instead of followed, as they could point to
+/// files outside of the package.
+fn copy_files(
+    template_dir: &Path,
+    project_dir: &Path,
+    template_root: &Path,
+    entrypoint_id: FileId,
+    spec: &PackageSpec,
+) -> io::Result<()> {
+    for entry in WalkDir::new(template_dir) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(template_dir).unwrap();
+        let target = project_dir.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        if !entry.file_type().is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a regular file", relative.display()),
+            ));
+        }
+
+        let is_typst = relative.extension().map_or(false, |ext| ext == "typ");
+        let text = is_typst.then(|| fs::read_to_string(entry.path())).transpose();
+        match text {
+            Ok(Some(text)) => {
+                let id = FileId::new(None, &template_root.join(relative));
+                fs::write(&target, rewrite_imports(&text, id, entrypoint_id, spec))?;
+            }
+            // Other files and those that aren't valid UTF-8 are copied as is.
+            Ok(None) | Err(_) => {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrite imports and includes of the package's entrypoint into imports of
+/// the package itself, since the template is no longer part of the package
+/// once it is copied.
+fn rewrite_imports(
+    text: &str,
+    id: FileId,
+    entrypoint_id: FileId,
+    spec: &PackageSpec,
+) -> String {
+    let root = typst::syntax::parse(text);
+    let mut ranges = vec![];
+    collect_imports(&LinkedNode::new(&root), &mut |node| {
+        let Some(path) = node.cast::<ast::Str>() else { return };
+        if id.join(&path.get()).map_or(false, |target| target == entrypoint_id) {
+            ranges.push(node.range());
+        }
+    });
+
+    let mut rewritten = text.to_string();
+    for range in ranges.into_iter().rev() {
+        rewritten.replace_range(range, &format!("\"{spec}\""));
+    }
+
+    rewritten
+}
+
+/// Call `f` for the string literal sources of all imports and includes.
+fn collect_imports(node: &LinkedNode, f: &mut impl FnMut(&LinkedNode)) {
+    if matches!(node.kind(), SyntaxKind::ModuleImport | SyntaxKind::ModuleInclude) {
+        for child in node.children() {
+            if child.kind() == SyntaxKind::Str {
+                f(&child);
+            }
+        }
+    }
+
+    for child in node.children() {
+        collect_imports(&child, f);
+    }
+}
+
+/// Print that the project was created and how to compile it.
+fn print_created(spec: &PackageSpec, project_dir: &Path, main: &Path) -> io::Result<()> {
+    let mut w = color_stream();
+    let styles = term::Styles::default();
+
+    w.set_color(&styles.header_note)?;
+    write!(w, "created")?;
+
+    w.reset()?;
+    writeln!(w, " project from {spec} in {}", project_dir.display())?;
+    writeln!(w, "to compile it, run `typst compile {}`", main.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rewrite the imports of a file in the template directory of a package
+    /// whose entrypoint is `/lib.typ`.
+    fn rewrite(path: &str, text: &str) -> String {
+        let spec = "@preview/example:0.1.0".parse().unwrap();
+        let id = FileId::new(None, &Path::new("/template").join(path));
+        let entrypoint_id = FileId::new(None, Path::new("/lib.typ"));
+        rewrite_imports(text, id, entrypoint_id, &spec)
+    }
+
+    #[test]
+    fn test_rewrite_imports() {
+        assert_eq!(
+            rewrite("main.typ", "#import \"../lib.typ\": template\n#show: template"),
+            "#import \"@preview/example:0.1.0\": template\n#show: template",
+        );
+        assert_eq!(
+            rewrite("main.typ", "#import \"/lib.typ\"\n#include \"../lib.typ\""),
+            "#import \"@preview/example:0.1.0\"\n#include \"@preview/example:0.1.0\"",
+        );
+        assert_eq!(
+            rewrite("chapters/intro.typ", "#import \"../../lib.typ\": *"),
+            "#import \"@preview/example:0.1.0\": *",
+        );
+    }
+
+    #[test]
+    fn test_rewrite_imports_keeps_others() {
+        let text = "#import \"util.typ\": *\n\
+                    #import \"lib.typ\"\n\
+                    #include \"../other.typ\"\n\
+                    #import \"@preview/other:0.1.0\"\n\
+                    Text mentioning \"../lib.typ\".";
+        assert_eq!(rewrite("main.typ", text), text);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_copy_template_rejects_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let template_dir = dir.path().join("template");
+        let project_dir = dir.path().join("project");
+        fs::create_dir(&template_dir).unwrap();
+        fs::write(template_dir.join("a.typ"), "#import \"../lib.typ\"").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", template_dir.join("b.txt")).unwrap();
+
+        let spec = "@preview/example:0.1.0".parse().unwrap();
+        let entrypoint_id = FileId::new(None, Path::new("/lib.typ"));
+        let copy = || {
+            let template_root = Path::new("/template");
+            copy_template(
+                &template_dir,
+                &project_dir,
+                template_root,
+                entrypoint_id,
+                &spec,
+            )
+        };
+
+        // A failed copy doesn't leave a partial project behind.
+        assert!(copy().is_err());
+        assert!(!project_dir.exists());
+
+        fs::remove_file(template_dir.join("b.txt")).unwrap();
+        copy().unwrap();
+        assert_eq!(
+            fs::read_to_string(project_dir.join("a.typ")).unwrap(),
+            "#import \"@preview/example:0.1.0\"",
+        );
+    }
+}
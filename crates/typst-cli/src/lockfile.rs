@@ -0,0 +1,254 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use typst::diag::{bail, StrResult};
+use typst::eval::eco_format;
+use typst::file::PackageSpec;
+use walkdir::WalkDir;
+
+/// The name of the lockfile in the project root.
+pub const LOCKFILE_NAME: &str = "typst.lock";
+
+/// The prefix of the hashes of downloaded package archives.
+const ARCHIVE_PREFIX: &str = "sha256:";
+
+/// The prefix of the hashes of unpacked package directories.
+const FILES_PREFIX: &str = "files-sha256:";
+
+/// Records the hashes of all packages a project has used, so that later
+/// compilations notice when a package's contents change.
+#[derive(Debug, Clone)]
+pub struct Lockfile {
+    /// Where the lockfile is stored.
+    path: PathBuf,
+    /// The locked packages.
+    data: LockfileData,
+    /// Whether packages were locked since the lockfile was loaded.
+    changed: bool,
+}
+
+/// The serialized contents of a lockfile.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct LockfileData {
+    /// The locked packages, sorted by their specification.
+    #[serde(default, rename = "package")]
+    packages: Vec<LockedPackage>,
+}
+
+/// A package in the lockfile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// The package's specification.
+    pub spec: PackageSpec,
+    /// The hash of the package's archive, see [`hash_archive`].
+    ///
+    /// This is checked before a downloaded archive is unpacked. It is missing
+    /// for packages that were only ever found in the package path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+    /// The hash of the package's unpacked files, see [`hash_files`].
+    ///
+    /// This is checked for packages that are already on disk, where the
+    /// archive is no longer available.
+    pub files: String,
+}
+
+impl Lockfile {
+    /// Load the lockfile at the given path or start an empty one if there is
+    /// none yet.
+    pub fn load(path: PathBuf) -> StrResult<Self> {
+        let data: LockfileData = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|err| {
+                eco_format!("lockfile is malformed ({})", err.message())
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => LockfileData::default(),
+            Err(err) => {
+                bail!("failed to read lockfile at {} ({err})", path.display())
+            }
+        };
+
+        if let Some(package) = data.packages.iter().find(|package| {
+            !package.files.starts_with(FILES_PREFIX)
+                || package
+                    .archive
+                    .as_ref()
+                    .map_or(false, |hash| !hash.starts_with(ARCHIVE_PREFIX))
+        }) {
+            bail!("lockfile has a hash in an unknown format for {}", package.spec);
+        }
+
+        Ok(Self { path, data, changed: false })
+    }
+
+    /// The hashes that were recorded for a package.
+    pub fn get(&self, spec: &PackageSpec) -> Option<&LockedPackage> {
+        self.data.packages.iter().find(|package| &package.spec == spec)
+    }
+
+    /// Record the hashes of a package, replacing earlier ones.
+    ///
+    /// The lockfile is only written to disk by [`save`](Self::save).
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.data.packages.retain(|locked| locked.spec != package.spec);
+        self.data.packages.push(package);
+        self.data.packages.sort_by_key(|package| package.spec.to_string());
+        self.changed = true;
+    }
+
+    /// Write the lockfile to disk if packages were locked since it was loaded.
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let text = toml::to_string(&self.data)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        fs::write(&self.path, text)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// Hash the bytes of a downloaded package archive.
+pub fn hash_archive(data: &[u8]) -> String {
+    format!("{ARCHIVE_PREFIX}{:x}", Sha256::digest(data))
+}
+
+/// Hash the files of an unpacked package directory.
+///
+/// The hash covers the relative path and the contents of each file and the
+/// relative path and the target of each symbolic link, in a fixed order, so
+/// that it is independent of the file system and of whether the package was
+/// just downloaded or already cached. Packages with other kinds of files, like
+/// pipes or devices, are rejected.
+pub fn hash_files(dir: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        let file_type = entry.file_type();
+        let (kind, data) = if file_type.is_file() {
+            (b'f', fs::read(entry.path())?)
+        } else if file_type.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            (b'l', normalize(&target).into_bytes())
+        } else if file_type.is_dir() {
+            continue;
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "package contains a file that is neither regular nor a link",
+            ));
+        };
+
+        let relative = entry.path().strip_prefix(dir).unwrap();
+        hasher.update([kind]);
+        hasher.update(normalize(relative).as_bytes());
+        hasher.update([0]);
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(&data);
+    }
+
+    Ok(format!("{FILES_PREFIX}{:x}", hasher.finalize()))
+}
+
+/// Join the components of a path with forward slashes.
+fn normalize(path: &Path) -> String {
+    let components: Vec<_> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockfile_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCKFILE_NAME);
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let package = LockedPackage {
+            spec: spec.clone(),
+            archive: Some(hash_archive(b"archive")),
+            files: format!("{FILES_PREFIX}0123"),
+        };
+
+        // A missing lockfile is empty and not written without changes.
+        let mut lockfile = Lockfile::load(path.clone()).unwrap();
+        assert_eq!(lockfile.get(&spec), None);
+        lockfile.save().unwrap();
+        assert!(!path.exists());
+
+        lockfile.insert(package.clone());
+        lockfile.save().unwrap();
+
+        let lockfile = Lockfile::load(path).unwrap();
+        assert_eq!(lockfile.get(&spec), Some(&package));
+        assert_eq!(lockfile.get(&"@preview/example:0.2.0".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_lockfile_rejects_unknown_hash_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCKFILE_NAME);
+        for hashes in [
+            "files = \"md5:0123\"",
+            "archive = \"md5:0123\"\nfiles = \"files-sha256:0123\"",
+        ] {
+            fs::write(
+                &path,
+                format!("[[package]]\nspec = \"@preview/example:0.1.0\"\n{hashes}\n"),
+            )
+            .unwrap();
+            assert!(Lockfile::load(path.clone()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_hash_archive() {
+        let hash = hash_archive(b"");
+        assert_eq!(
+            hash,
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(hash_archive(b"archive"), hash);
+    }
+
+    #[test]
+    fn test_hash_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.typ"), "#let x = 1").unwrap();
+        fs::write(dir.path().join("typst.toml"), "[package]").unwrap();
+
+        let hash = hash_files(dir.path()).unwrap();
+        assert!(hash.starts_with(FILES_PREFIX));
+        assert_eq!(hash_files(dir.path()).unwrap(), hash);
+
+        fs::write(dir.path().join("src/lib.typ"), "#let x = 2").unwrap();
+        assert_ne!(hash_files(dir.path()).unwrap(), hash);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hash_files_covers_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.typ"), "a").unwrap();
+        fs::write(dir.path().join("b.typ"), "b").unwrap();
+        let without = hash_files(dir.path()).unwrap();
+
+        std::os::unix::fs::symlink("a.typ", dir.path().join("lib.typ")).unwrap();
+        let with_a = hash_files(dir.path()).unwrap();
+        assert_ne!(with_a, without);
+
+        fs::remove_file(dir.path().join("lib.typ")).unwrap();
+        std::os::unix::fs::symlink("b.typ", dir.path().join("lib.typ")).unwrap();
+        assert_ne!(hash_files(dir.path()).unwrap(), with_a);
+    }
+}
@@ -1,7 +1,11 @@
 mod args;
 mod compile;
 mod fonts;
+mod init;
+mod lockfile;
 mod package;
+mod query;
+mod serve;
 mod tracing;
 mod watch;
 mod world;
@@ -36,6 +40,8 @@ fn main() -> ExitCode {
     let res = match arguments.command {
         Command::Compile(command) => crate::compile::compile(command),
         Command::Watch(command) => crate::watch::watch(command),
+        Command::Query(command) => crate::query::query(command),
+        Command::Init(command) => crate::init::init(command),
         Command::Fonts(command) => crate::fonts::fonts(command),
     };
 
@@ -64,6 +70,18 @@ fn print_error(msg: &str) -> io::Result<()> {
     writeln!(w, ": {msg}.")
 }
 
+/// Print an application-level warning.
+fn print_warning(msg: &str) -> io::Result<()> {
+    let mut w = color_stream();
+    let styles = term::Styles::default();
+
+    w.set_color(&styles.header_warning)?;
+    write!(w, "warning")?;
+
+    w.reset()?;
+    writeln!(w, ": {msg}.")
+}
+
 /// Get stderr with color support if desirable.
 fn color_stream() -> termcolor::StandardStream {
     termcolor::StandardStream::stderr(if std::io::stderr().is_terminal() {
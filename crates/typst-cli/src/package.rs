@@ -1,67 +1,234 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
 use codespan_reporting::term::{self, termcolor};
 use termcolor::WriteColor;
 use typst::diag::{PackageError, PackageResult};
-use typst::file::PackageSpec;
+use typst::file::{PackageManifest, PackageSpec};
 
 use super::color_stream;
+use crate::args::PackageArgs;
+use crate::lockfile::{hash_archive, hash_files, LockedPackage, Lockfile};
 
-/// Make a package available in the on-disk cache.
-pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
-    let subdir =
-        format!("typst/packages/{}/{}-{}", spec.namespace, spec.name, spec.version);
+/// The registry from which packages are downloaded by default.
+const DEFAULT_REGISTRY: &str = "https://packages.typst.org";
 
-    if let Some(data_dir) = dirs::data_dir() {
-        let dir = data_dir.join(&subdir);
-        if dir.exists() {
-            return Ok(dir);
+/// The namespace for packages that are only available locally and never
+/// downloaded.
+pub const LOCAL_NAMESPACE: &str = "local";
+
+/// Where and how packages are looked up and downloaded.
+#[derive(Debug, Clone)]
+pub struct PackageStorage {
+    /// The directory for local packages.
+    package_path: Option<PathBuf>,
+    /// The directory into which downloaded packages are cached.
+    cache_path: Option<PathBuf>,
+    /// The base URL of the registry, without a trailing slash.
+    registry: String,
+    /// Whether downloading packages is disabled.
+    offline: bool,
+    /// The project's lockfile, against which packages are verified.
+    lockfile: Option<RefCell<Lockfile>>,
+    /// Whether packages that are not locked yet are recorded in the lockfile.
+    record: bool,
+    /// Packages that were already verified against the lockfile.
+    verified: RefCell<HashSet<PackageSpec>>,
+}
+
+impl PackageStorage {
+    /// Configure package storage from the command line arguments.
+    pub fn new(args: &PackageArgs) -> Self {
+        let registry = args.package_registry.as_deref().unwrap_or(DEFAULT_REGISTRY);
+        Self {
+            package_path: args
+                .package_path
+                .clone()
+                .or_else(|| Some(dirs::data_dir()?.join("typst/packages"))),
+            cache_path: dirs::cache_dir().map(|dir| dir.join("typst/packages")),
+            registry: registry.trim_end_matches('/').into(),
+            offline: args.offline,
+            lockfile: None,
+            record: false,
+            verified: RefCell::default(),
         }
     }
 
-    if let Some(cache_dir) = dirs::cache_dir() {
-        let dir = cache_dir.join(&subdir);
+    /// Verify packages against a lockfile and, if `record` is set, record the
+    /// hashes of packages that are not locked yet.
+    pub fn with_lockfile(mut self, lockfile: Lockfile, record: bool) -> Self {
+        self.lockfile = Some(RefCell::new(lockfile));
+        self.record = record;
+        self
+    }
 
-        // Download from network if it doesn't exist yet.
-        if spec.namespace == "preview" && !dir.exists() {
-            download_package(spec, &dir)?;
+    /// Write the hashes of newly recorded packages into the lockfile.
+    pub fn save_lockfile(&self) -> io::Result<()> {
+        match &self.lockfile {
+            Some(lockfile) => lockfile.borrow_mut().save(),
+            None => Ok(()),
         }
+    }
 
-        if dir.exists() {
-            return Ok(dir);
+    /// Make a package available in the on-disk cache.
+    ///
+    /// Packages are first looked up in the package path. Packages from any
+    /// namespace but `@local` are then looked up in the cache and downloaded
+    /// from the registry if they aren't cached yet.
+    pub fn prepare_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        let subdir = format!("{}/{}-{}", spec.namespace, spec.name, spec.version);
+
+        if let Some(package_path) = &self.package_path {
+            let dir = package_path.join(&subdir);
+            if dir.exists() {
+                self.verify_package(spec, &dir, None)?;
+                return Ok(dir);
+            }
         }
+
+        if let Some(cache_path) = &self.cache_path {
+            let dir = cache_path.join(&subdir);
+            if dir.exists() {
+                self.verify_package(spec, &dir, None)?;
+                return Ok(dir);
+            }
+
+            // Download from network if it doesn't exist yet.
+            if spec.namespace != LOCAL_NAMESPACE {
+                if self.offline {
+                    return Err(PackageError::Offline(spec.clone()));
+                }
+
+                let archive = self.download_package(spec, &dir)?;
+
+                // Don't keep a download that doesn't match the lockfile around.
+                if let Err(err) = self.verify_package(spec, &dir, Some(archive)) {
+                    fs::remove_dir_all(&dir).ok();
+                    return Err(err);
+                }
+
+                self.prefetch_dependencies(&dir)?;
+                return Ok(dir);
+            }
+        }
+
+        Err(PackageError::NotFound(spec.clone()))
     }
 
-    Err(PackageError::NotFound(spec.clone()))
-}
+    /// Check a package's files against their hash in the lockfile or record
+    /// them if the package is not locked yet and recording is enabled.
+    ///
+    /// The hash of the archive is given for packages that were just
+    /// downloaded. It was already checked before unpacking and is recorded
+    /// alongside the files' hash.
+    ///
+    /// Packages in the local namespace are under development and thus never
+    /// locked.
+    fn verify_package(
+        &self,
+        spec: &PackageSpec,
+        dir: &Path,
+        archive: Option<String>,
+    ) -> PackageResult<()> {
+        let Some(lockfile) = &self.lockfile else { return Ok(()) };
+        if spec.namespace == LOCAL_NAMESPACE || self.verified.borrow().contains(spec) {
+            return Ok(());
+        }
+
+        let mut lockfile = lockfile.borrow_mut();
+        let locked = lockfile.get(spec).cloned();
+        if locked.is_none() && !self.record {
+            return Ok(());
+        }
+
+        let files = hash_files(dir).map_err(|_| PackageError::Other)?;
+        match locked {
+            Some(locked) if locked.files != files => {
+                return Err(PackageError::IntegrityMismatch(spec.clone()));
+            }
+            // Complete packages that were first locked from the package path.
+            Some(locked) if locked.archive.is_none() && archive.is_some() => {
+                if self.record {
+                    lockfile.insert(LockedPackage { archive, ..locked });
+                }
+            }
+            Some(_) => {}
+            None => lockfile.insert(LockedPackage { spec: spec.clone(), archive, files }),
+        }
 
-/// Download a package over the network.
-fn download_package(spec: &PackageSpec, package_dir: &Path) -> PackageResult<()> {
-    // The `@preview` namespace is the only namespace that supports on-demand
-    // fetching.
-    assert_eq!(spec.namespace, "preview");
-
-    let url = format!(
-        "https://packages.typst.org/preview/{}-{}.tar.gz",
-        spec.name, spec.version
-    );
-
-    print_downloading(spec).unwrap();
-    let reader = match ureq::get(&url).call() {
-        Ok(response) => response.into_reader(),
-        Err(ureq::Error::Status(404, _)) => {
-            return Err(PackageError::NotFound(spec.clone()))
+        self.verified.borrow_mut().insert(spec.clone());
+        Ok(())
+    }
+
+    /// Download a package over the network.
+    ///
+    /// The archive is checked against the lockfile before it is unpacked.
+    /// Returns its hash.
+    fn download_package(
+        &self,
+        spec: &PackageSpec,
+        package_dir: &Path,
+    ) -> PackageResult<String> {
+        let url = format!(
+            "{}/{}/{}-{}.tar.gz",
+            self.registry, spec.namespace, spec.name, spec.version
+        );
+
+        print_downloading(spec).unwrap();
+        let mut data = vec![];
+        match ureq::get(&url).call() {
+            Ok(response) => response
+                .into_reader()
+                .read_to_end(&mut data)
+                .map_err(|_| PackageError::NetworkFailed)?,
+            Err(ureq::Error::Status(404, _)) => {
+                return Err(PackageError::NotFound(spec.clone()))
+            }
+            Err(_) => return Err(PackageError::NetworkFailed),
+        };
+
+        let hash = hash_archive(&data);
+        if let Some(lockfile) = &self.lockfile {
+            let lockfile = lockfile.borrow();
+            let locked = lockfile.get(spec).and_then(|locked| locked.archive.as_ref());
+            if locked.map_or(false, |locked| *locked != hash) {
+                return Err(PackageError::IntegrityMismatch(spec.clone()));
+            }
         }
-        Err(_) => return Err(PackageError::NetworkFailed),
-    };
-
-    let decompressed = flate2::read::GzDecoder::new(reader);
-    tar::Archive::new(decompressed).unpack(package_dir).map_err(|_| {
-        fs::remove_dir_all(package_dir).ok();
-        PackageError::MalformedArchive
-    })
+
+        let decompressed = flate2::read::GzDecoder::new(data.as_slice());
+        tar::Archive::new(decompressed).unpack(package_dir).map_err(|_| {
+            fs::remove_dir_all(package_dir).ok();
+            PackageError::MalformedArchive
+        })?;
+
+        Ok(hash)
+    }
+
+    /// Make the dependencies declared in a freshly downloaded package's
+    /// manifest available, so that they don't have to be downloaded one by
+    /// one as evaluation encounters them.
+    ///
+    /// A missing or malformed manifest is not reported here, but when the
+    /// package is imported.
+    fn prefetch_dependencies(&self, package_dir: &Path) -> PackageResult<()> {
+        let Ok(bytes) = fs::read(package_dir.join("typst.toml")) else {
+            return Ok(());
+        };
+
+        let Ok(manifest) = PackageManifest::parse(&bytes) else {
+            return Ok(());
+        };
+
+        for dependency in &manifest.package.dependencies {
+            self.prepare_package(dependency)?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Print that a package downloading is happening.
@@ -75,3 +242,251 @@ fn print_downloading(spec: &PackageSpec) -> io::Result<()> {
     w.reset()?;
     writeln!(w, " {spec}")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::lockfile::LOCKFILE_NAME;
+
+    /// Package storage that looks up local packages in `dir/packages` and
+    /// caches downloads from the registry in `dir/cache`.
+    fn storage(dir: &Path, registry: &str) -> PackageStorage {
+        let mut storage = PackageStorage::new(&PackageArgs {
+            package_path: Some(dir.join("packages")),
+            package_registry: Some(registry.into()),
+            offline: false,
+        });
+        storage.cache_path = Some(dir.join("cache"));
+        storage
+    }
+
+    /// Build a package archive with the given files.
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, text) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(text.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, text.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Serve files over HTTP on a local port, responding with a 404 for all
+    /// other paths.
+    ///
+    /// Returns the server's base URL.
+    fn serve(files: Vec<(String, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).ok();
+
+                // Skip the headers until the empty line.
+                let mut header = String::new();
+                while reader.read_line(&mut header).map_or(false, |n| n > 2) {
+                    header.clear();
+                }
+
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match files.iter().find(|(file, _)| file == path) {
+                    Some((_, body)) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", [].as_slice()),
+                };
+
+                let head = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len(),
+                );
+                stream.write_all(head.as_bytes()).ok();
+                stream.write_all(body).ok();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_verify_package_against_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock_path = dir.path().join(LOCKFILE_NAME);
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let package_dir = dir.path().join("packages/preview/example-0.1.0");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(package_dir.join("lib.typ"), "#let x = 1").unwrap();
+
+        // Without recording, unlocked packages are used without being locked.
+        let lockfile = Lockfile::load(lock_path.clone()).unwrap();
+        let packages = storage(dir.path(), "").with_lockfile(lockfile, false);
+        assert_eq!(packages.prepare_package(&spec), Ok(package_dir.clone()));
+        packages.save_lockfile().unwrap();
+        assert!(!lock_path.exists());
+
+        // With recording, they are locked.
+        let lockfile = Lockfile::load(lock_path.clone()).unwrap();
+        let packages = storage(dir.path(), "").with_lockfile(lockfile, true);
+        assert_eq!(packages.prepare_package(&spec), Ok(package_dir.clone()));
+        packages.save_lockfile().unwrap();
+        let lockfile = Lockfile::load(lock_path.clone()).unwrap();
+        let locked = lockfile.get(&spec).unwrap();
+        assert_eq!(locked.archive, None);
+        assert_eq!(locked.files, hash_files(&package_dir).unwrap());
+
+        // A changed package doesn't match the lock anymore.
+        fs::write(package_dir.join("lib.typ"), "#let x = 2").unwrap();
+        let packages = storage(dir.path(), "").with_lockfile(lockfile, true);
+        assert_eq!(
+            packages.prepare_package(&spec),
+            Err(PackageError::IntegrityMismatch(spec)),
+        );
+    }
+
+    #[test]
+    fn test_download_is_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock_path = dir.path().join(LOCKFILE_NAME);
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let archive = archive(&[("lib.typ", "#let x = 1")]);
+        let registry =
+            serve(vec![("/preview/example-0.1.0.tar.gz".into(), archive.clone())]);
+
+        let lockfile = Lockfile::load(lock_path.clone()).unwrap();
+        let packages = storage(dir.path(), &registry).with_lockfile(lockfile, true);
+        let package_dir = dir.path().join("cache/preview/example-0.1.0");
+        assert_eq!(packages.prepare_package(&spec), Ok(package_dir.clone()));
+        packages.save_lockfile().unwrap();
+
+        // Both the archive and the unpacked files are locked.
+        let lockfile = Lockfile::load(lock_path).unwrap();
+        let locked = lockfile.get(&spec).unwrap();
+        assert_eq!(locked.archive, Some(hash_archive(&archive)));
+        assert_eq!(locked.files, hash_files(&package_dir).unwrap());
+
+        // Edits to the cached package are noticed.
+        fs::write(package_dir.join("lib.typ"), "#let x = 2").unwrap();
+        let packages = storage(dir.path(), &registry).with_lockfile(lockfile, true);
+        assert_eq!(
+            packages.prepare_package(&spec),
+            Err(PackageError::IntegrityMismatch(spec)),
+        );
+    }
+
+    #[test]
+    fn test_mismatched_archive_is_not_unpacked() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let registry = serve(vec![(
+            "/preview/example-0.1.0.tar.gz".into(),
+            archive(&[("lib.typ", "#let x = 1")]),
+        )]);
+
+        let mut lockfile = Lockfile::load(dir.path().join(LOCKFILE_NAME)).unwrap();
+        lockfile.insert(LockedPackage {
+            spec: spec.clone(),
+            archive: Some(hash_archive(b"other")),
+            files: "files-sha256:0".into(),
+        });
+
+        let packages = storage(dir.path(), &registry).with_lockfile(lockfile, true);
+        assert_eq!(
+            packages.prepare_package(&spec),
+            Err(PackageError::IntegrityMismatch(spec)),
+        );
+        assert!(!dir.path().join("cache/preview/example-0.1.0").exists());
+    }
+
+    #[test]
+    fn test_mismatched_download_is_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let registry = serve(vec![(
+            "/preview/example-0.1.0.tar.gz".into(),
+            archive(&[("lib.typ", "#let x = 1")]),
+        )]);
+
+        // Packages locked from the package path only have a files' hash.
+        let mut lockfile = Lockfile::load(dir.path().join(LOCKFILE_NAME)).unwrap();
+        lockfile.insert(LockedPackage {
+            spec: spec.clone(),
+            archive: None,
+            files: "files-sha256:0".into(),
+        });
+
+        let packages = storage(dir.path(), &registry).with_lockfile(lockfile, true);
+        assert_eq!(
+            packages.prepare_package(&spec),
+            Err(PackageError::IntegrityMismatch(spec)),
+        );
+        assert!(!dir.path().join("cache/preview/example-0.1.0").exists());
+    }
+
+    #[test]
+    fn test_download_from_custom_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = serve(vec![(
+            "/acme/example-0.1.0.tar.gz".into(),
+            archive(&[("typst.toml", "[package]"), ("lib.typ", "#let x = 1")]),
+        )]);
+
+        // Any namespace but `@local` is downloaded from the registry.
+        let packages = storage(dir.path(), &format!("{registry}/"));
+        let spec: PackageSpec = "@acme/example:0.1.0".parse().unwrap();
+        let package_dir = dir.path().join("cache/acme/example-0.1.0");
+        assert_eq!(packages.prepare_package(&spec), Ok(package_dir.clone()));
+        assert_eq!(
+            fs::read_to_string(package_dir.join("lib.typ")).unwrap(),
+            "#let x = 1"
+        );
+
+        let missing: PackageSpec = "@acme/example:0.2.0".parse().unwrap();
+        assert_eq!(
+            packages.prepare_package(&missing),
+            Err(PackageError::NotFound(missing)),
+        );
+
+        let local: PackageSpec = "@local/example:0.1.0".parse().unwrap();
+        assert_eq!(packages.prepare_package(&local), Err(PackageError::NotFound(local)));
+    }
+
+    #[test]
+    fn test_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut packages = storage(dir.path(), "http://127.0.0.1:1");
+        packages.offline = true;
+
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        assert_eq!(
+            packages.prepare_package(&spec),
+            Err(PackageError::Offline(spec.clone()))
+        );
+
+        // Cached packages are still available.
+        let package_dir = dir.path().join("cache/preview/example-0.1.0");
+        fs::create_dir_all(&package_dir).unwrap();
+        assert_eq!(packages.prepare_package(&spec), Ok(package_dir));
+    }
+
+    #[test]
+    fn test_package_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let packages = storage(dir.path(), "http://127.0.0.1:1");
+
+        // Packages in the package path take precedence over cached ones and
+        // are never downloaded, whatever their namespace.
+        for spec in ["@local/example:0.1.0", "@preview/example:0.1.0"] {
+            let spec: PackageSpec = spec.parse().unwrap();
+            let subdir = format!("{}/{}-{}", spec.namespace, spec.name, spec.version);
+            let package_dir = dir.path().join("packages").join(subdir);
+            fs::create_dir_all(&package_dir).unwrap();
+            assert_eq!(packages.prepare_package(&spec), Ok(package_dir));
+        }
+    }
+}
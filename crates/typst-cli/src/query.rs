@@ -0,0 +1,137 @@
+use comemo::Track;
+use typst::diag::{bail, At, SourceResult, StrResult};
+use typst::doc::Document;
+use typst::eval::{eco_format, eval_string, Tracer, Value};
+use typst::model::{Content, Introspector, LocatableSelector};
+use typst::syntax::Span;
+use typst::World;
+
+use crate::args::{QueryCommand, SerializationFormat};
+use crate::compile::print_diagnostics;
+use crate::world::SystemWorld;
+use crate::{print_warning, set_failed};
+
+/// Execute a query command.
+pub fn query(command: QueryCommand) -> StrResult<()> {
+    let mut world = SystemWorld::new(&command.common)?;
+    tracing::info!("Starting querying");
+
+    let start = std::time::Instant::now();
+
+    // Reset everything and ensure that the main file is still present.
+    world.reset();
+    world.source(world.main()).map_err(|err| err.to_string())?;
+
+    let mut tracer = Tracer::default();
+    let result = typst::compile(&world, &mut tracer)
+        .and_then(|document| retrieve(&world, &command, &document))
+        .and_then(|elements| tracer.flush_writes(&world).map(|()| elements));
+    let mut warnings = tracer.warnings().to_vec();
+    let duration = start.elapsed();
+
+    match result {
+        Ok(elements) => {
+            tracing::info!("Querying succeeded in {duration:?}");
+            let serialized = format(elements, &command)?;
+            println!("{serialized}");
+            print_diagnostics(&world, warnings, command.common.diagnostic_format)
+                .map_err(|_| "failed to print diagnostics")?;
+
+            // The query is fine even if its packages couldn't be recorded.
+            if let Err(err) = world.save_lockfile() {
+                print_warning(&format!("failed to update lockfile ({err})"))
+                    .map_err(|_| "failed to print warning")?;
+            }
+        }
+
+        // Print diagnostics.
+        Err(errors) => {
+            set_failed();
+            tracing::info!("Querying failed");
+            warnings.extend(*errors);
+            print_diagnostics(&world, warnings, command.common.diagnostic_format)
+                .map_err(|_| "failed to print diagnostics")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Retrieve the matches for the selector.
+fn retrieve(
+    world: &dyn World,
+    command: &QueryCommand,
+    document: &Document,
+) -> SourceResult<Vec<Content>> {
+    let selector = eval_string(world.track(), &command.selector, Span::detached())?
+        .cast::<LocatableSelector>()
+        .at(Span::detached())?;
+
+    let introspector = Introspector::new(&document.pages);
+    Ok(introspector
+        .query(&selector.0)
+        .into_iter()
+        .map(|elem| elem.into_inner())
+        .collect())
+}
+
+/// Format the query result in the output format.
+fn format(elements: Vec<Content>, command: &QueryCommand) -> StrResult<String> {
+    if command.one && elements.len() != 1 {
+        bail!("expected exactly one element, found {}", elements.len());
+    }
+
+    let mapped: Vec<Value> = elements
+        .into_iter()
+        .filter_map(|elem| match &command.field {
+            Some(field) => elem.field(field),
+            None => Some(Value::Content(elem)),
+        })
+        .collect();
+
+    if command.one {
+        let Some(value) = mapped.first() else {
+            bail!("no such field found for element");
+        };
+        serialize(value, command.format)
+    } else {
+        serialize(&mapped, command.format)
+    }
+}
+
+/// Serialize data to the output format.
+fn serialize(
+    data: &impl serde::Serialize,
+    format: SerializationFormat,
+) -> StrResult<String> {
+    match format {
+        SerializationFormat::Json => {
+            serde_json::to_string_pretty(data).map_err(|e| eco_format!("{e}"))
+        }
+        SerializationFormat::Yaml => {
+            serde_yaml::to_string(&data).map_err(|e| eco_format!("{e}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typst::eval::{array, dict};
+    use typst::model::Styles;
+
+    #[test]
+    fn test_serialize_values() {
+        let value = Value::Dict(dict!["a" => 1, "b" => array![true, "hi"]]);
+        assert_eq!(
+            serialize(&value, SerializationFormat::Json).unwrap(),
+            "{\n  \"a\": 1,\n  \"b\": [\n    true,\n    \"hi\"\n  ]\n}"
+        );
+
+        let value = Value::Array(array![Value::Styles(Styles::new())]);
+        assert_eq!(
+            serialize(&value, SerializationFormat::Json).unwrap_err(),
+            "cannot serialize styles"
+        );
+    }
+}
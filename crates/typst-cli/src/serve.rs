@@ -0,0 +1,391 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+use typst::diag::StrResult;
+use typst::doc::{Document, Frame};
+use typst::eval::eco_format;
+use typst::geom::{Abs, Color, Point};
+use typst::ide::{jump_from_click, Jump};
+use typst::World;
+
+use crate::watch::Message;
+use crate::world::SystemWorld;
+
+/// The port on which the preview is served by default.
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// The page that displays the preview.
+const INDEX_HTML: &str = include_str!("preview.html");
+
+/// How long a click waits for its jump target before giving up.
+const JUMP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A local HTTP server that shows a live preview of the document in the
+/// browser.
+///
+/// The pages are rendered on demand. Browsers are notified about new
+/// compilations through server-sent events, after which they reload the
+/// pages. Clicks into the pages are forwarded to the watch loop through the
+/// [`Message::Click`] message, since resolving them requires the world.
+pub struct Server {
+    /// The state that is shared with the connection threads.
+    state: Arc<Mutex<State>>,
+    /// The port the server listens on.
+    port: u16,
+}
+
+/// The state of the preview.
+#[derive(Default)]
+struct State {
+    /// Incremented with each compilation.
+    version: u64,
+    /// Whether the last compilation succeeded.
+    ok: bool,
+    /// The last successfully compiled document.
+    document: Option<Document>,
+    /// The event streams that are notified about new compilations. Each
+    /// stream is written by its own thread, so that a slow browser doesn't
+    /// hold up the others or the lock.
+    listeners: Vec<Sender<String>>,
+}
+
+/// A click into a page of the preview that waits for its jump target.
+pub struct Click {
+    /// The page, starting at 1.
+    page: usize,
+    /// The clicked point on the page.
+    point: Point,
+    /// Where to send the jump target, as JSON.
+    reply: Sender<String>,
+}
+
+impl Server {
+    /// Start listening on the given port on localhost.
+    ///
+    /// Clicks into the preview are sent to `messages`.
+    pub fn start(port: u16, ppi: f32, messages: Sender<Message>) -> StrResult<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|err| {
+            eco_format!("failed to serve preview on port {port} ({err})")
+        })?;
+        let port = listener.local_addr().map_or(port, |addr| addr.port());
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                let messages = messages.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle(stream, &state, &messages, ppi) {
+                        tracing::info!("Preview connection failed: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self { state, port })
+    }
+
+    /// The port the server listens on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Publish the result of a compilation and notify all browsers.
+    ///
+    /// If the compilation failed, the previous document stays visible.
+    pub fn update(&self, document: Option<&Document>) {
+        let mut state = self.state.lock().unwrap();
+        state.version += 1;
+        state.ok = document.is_some();
+        if let Some(document) = document {
+            state.document = Some(document.clone());
+        }
+
+        let event = event(&status(&state));
+        state
+            .listeners
+            .retain(|listener| listener.send(event.clone()).is_ok());
+    }
+}
+
+impl Click {
+    /// Determine the jump target of the click and send it to the browser.
+    pub fn answer(self, world: &SystemWorld, document: &Document) {
+        let jump = document
+            .pages
+            .get(self.page.wrapping_sub(1))
+            .and_then(|frame| jump_from_click(world, &document.pages, frame, self.point));
+
+        let target = match jump {
+            Some(Jump::Source(id, offset)) => world
+                .source(id)
+                .ok()
+                .and_then(|source| {
+                    Some(json!({
+                        "kind": "source",
+                        "file": id.to_string(),
+                        "line": source.byte_to_line(offset)? + 1,
+                        "column": source.byte_to_column(offset)? + 1,
+                    }))
+                })
+                .unwrap_or_else(|| json!({ "kind": "none" })),
+            Some(Jump::Url(url)) => json!({ "kind": "url", "url": url.as_str() }),
+            Some(Jump::Position(position)) => json!({
+                "kind": "position",
+                "page": position.page.get(),
+                "x": position.point.x.to_pt(),
+                "y": position.point.y.to_pt(),
+            }),
+            None => json!({ "kind": "none" }),
+        };
+
+        self.reply.send(target.to_string()).ok();
+    }
+}
+
+/// Respond to a single request.
+fn handle(
+    mut stream: TcpStream,
+    state: &Mutex<State>,
+    messages: &Sender<Message>,
+    ppi: f32,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    // Skip the headers, we don't need any of them.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", b"bad request");
+    };
+
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+
+    match route(target) {
+        Route::Index => {
+            respond(&mut stream, "200 OK", "text/html", INDEX_HTML.as_bytes())
+        }
+        Route::Events => {
+            // Register as a listener, then keep the connection open and
+            // forward the events without holding the lock.
+            let (sender, events) = mpsc::channel();
+            let first = {
+                let mut state = state.lock().unwrap();
+                state.listeners.push(sender);
+                event(&status(&state))
+            };
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/event-stream\r\n\
+                 Cache-Control: no-cache\r\n\r\n\
+                 {first}",
+            )?;
+            stream.flush()?;
+
+            // Once writing fails, the receiver is dropped and the next update
+            // removes the listener.
+            for event in events {
+                stream.write_all(event.as_bytes())?;
+                stream.flush()?;
+            }
+
+            Ok(())
+        }
+        Route::Jump(query) => {
+            let Some((page, point)) = parse_jump(query) else {
+                return respond(&mut stream, "400 Bad Request", "text/plain", b"");
+            };
+
+            let (reply, receive) = mpsc::channel();
+            let click = Click { page, point, reply };
+            if messages.send(Message::Click(click)).is_err() {
+                return respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "text/plain",
+                    b"",
+                );
+            }
+
+            // The watch loop might be busy compiling, so don't wait forever.
+            let target = receive
+                .recv_timeout(JUMP_TIMEOUT)
+                .unwrap_or_else(|_| r#"{"kind":"none"}"#.into());
+            respond(&mut stream, "200 OK", "application/json", target.as_bytes())
+        }
+        Route::Page(page, ext) => {
+            let frame: Option<Frame> = state
+                .lock()
+                .unwrap()
+                .document
+                .as_ref()
+                .and_then(|document| document.pages.get(page.wrapping_sub(1)))
+                .cloned();
+
+            match (frame, ext) {
+                (Some(frame), "svg") => {
+                    let svg = typst::export::svg(&frame);
+                    respond(&mut stream, "200 OK", "image/svg+xml", svg.as_bytes())
+                }
+                (Some(frame), "png") => {
+                    let pixmap = typst::export::render(&frame, ppi / 72.0, Color::WHITE);
+                    let png = pixmap.encode_png().unwrap_or_default();
+                    respond(&mut stream, "200 OK", "image/png", &png)
+                }
+                _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+            }
+        }
+        Route::NotFound => {
+            respond(&mut stream, "404 Not Found", "text/plain", b"not found")
+        }
+    }
+}
+
+/// What a request asks for.
+#[derive(Debug, PartialEq)]
+enum Route<'a> {
+    /// The page that displays the preview.
+    Index,
+    /// The stream of server-sent events about new compilations.
+    Events,
+    /// The jump target of a click, with the query string of the request.
+    Jump(&'a str),
+    /// A rendered page, starting at 1, and the requested image format.
+    Page(usize, &'a str),
+    /// Anything else.
+    NotFound,
+}
+
+/// Determine what the request target asks for.
+fn route(target: &str) -> Route<'_> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path {
+        "/" => Route::Index,
+        "/events" => Route::Events,
+        "/jump" => Route::Jump(query),
+        _ => path
+            .strip_prefix("/page/")
+            .and_then(|rest| rest.split_once('.'))
+            .and_then(|(page, ext)| Some(Route::Page(page.parse().ok()?, ext)))
+            .unwrap_or(Route::NotFound),
+    }
+}
+
+/// Parse the page and point of a click from the query string of a jump
+/// request, e.g. `page=1&x=10.5&y=20`.
+fn parse_jump(query: &str) -> Option<(usize, Point)> {
+    let param = |key: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|&(k, _)| k == key)
+            .map(|(_, v)| v)
+    };
+
+    let page = param("page")?.parse::<usize>().ok().filter(|&page| page >= 1)?;
+    let coordinate =
+        |key: &str| param(key)?.parse::<f64>().ok().filter(|v| v.is_finite());
+    let point = Point::new(Abs::pt(coordinate("x")?), Abs::pt(coordinate("y")?));
+    Some((page, point))
+}
+
+/// Format a server-sent event with the given data.
+fn event(data: &str) -> String {
+    format!("data: {data}\n\n")
+}
+
+/// The current status of the preview as JSON.
+fn status(state: &State) -> String {
+    let pages: Vec<_> = state
+        .document
+        .iter()
+        .flat_map(|document| &document.pages)
+        .map(|frame| {
+            json!({
+                "width": frame.width().to_pt(),
+                "height": frame.height().to_pt(),
+            })
+        })
+        .collect();
+
+    json!({ "version": state.version, "ok": state.ok, "pages": pages }).to_string()
+}
+
+/// Write a complete response and close the connection.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("/"), Route::Index);
+        assert_eq!(route("/?v=2"), Route::Index);
+        assert_eq!(route("/events"), Route::Events);
+        assert_eq!(route("/jump?page=1&x=2&y=3"), Route::Jump("page=1&x=2&y=3"));
+        assert_eq!(route("/page/2.svg"), Route::Page(2, "svg"));
+        assert_eq!(route("/page/10.png?v=7"), Route::Page(10, "png"));
+        assert_eq!(route("/page/two.svg"), Route::NotFound);
+        assert_eq!(route("/page/2"), Route::NotFound);
+        assert_eq!(route("/favicon.ico"), Route::NotFound);
+    }
+
+    #[test]
+    fn test_event_format() {
+        let state = State { version: 3, ok: true, ..State::default() };
+        assert_eq!(
+            event(&status(&state)),
+            "data: {\"ok\":true,\"pages\":[],\"version\":3}\n\n",
+        );
+    }
+
+    #[test]
+    fn test_parse_jump() {
+        let point = |x, y| Point::new(Abs::pt(x), Abs::pt(y));
+        assert_eq!(parse_jump("page=2&x=10.5&y=20"), Some((2, point(10.5, 20.0))));
+        assert_eq!(parse_jump("y=1&x=0&page=1&extra"), Some((1, point(0.0, 1.0))));
+        assert_eq!(parse_jump("page=1&x=10"), None);
+        assert_eq!(parse_jump("page=0&x=1&y=1"), None);
+        assert_eq!(parse_jump("page=1.5&x=1&y=1"), None);
+        assert_eq!(parse_jump("page=-1&x=1&y=1"), None);
+        assert_eq!(parse_jump("page=1e30&x=1&y=1"), None);
+        assert_eq!(parse_jump("page=1&x=NaN&y=1"), None);
+        assert_eq!(parse_jump("page=1&x=1&y=inf"), None);
+    }
+}
@@ -6,42 +6,80 @@ use codespan_reporting::term::{self, termcolor};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use same_file::is_same_file;
 use termcolor::WriteColor;
-use typst::diag::StrResult;
+use typst::diag::{bail, StrResult};
 use typst::eval::eco_format;
 
-use crate::args::CompileCommand;
+use crate::args::{CompileCommand, Input, Output};
 use crate::color_stream;
 use crate::compile::compile_once;
+use crate::serve::{Click, Server, DEFAULT_PORT};
 use crate::world::SystemWorld;
 
 /// Execute a watching compilation command.
 pub fn watch(mut command: CompileCommand) -> StrResult<()> {
+    if command.common.input == Input::Stdin {
+        bail!("cannot watch input from stdin");
+    }
+
+    let Output::Path(output) = command.output() else {
+        bail!("cannot write output to stdout in watch mode");
+    };
+
     // Create the world that serves sources, files, and fonts.
-    let mut world = SystemWorld::new(&command)?;
+    let mut world = SystemWorld::new(&command.common)?;
+
+    // Both file system events and clicks into the preview are handled here.
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    // Start serving the preview, if requested.
+    let server = match command.serve {
+        Some(port) => {
+            let port = port.unwrap_or(DEFAULT_PORT);
+            let server = Server::start(port, command.ppi, tx.clone())?;
+            command.serve = Some(Some(server.port()));
+            Some(server)
+        }
+        None => None,
+    };
 
     // Perform initial compilation.
-    compile_once(&mut world, &mut command, true)?;
+    let mut document = compile_once(&mut world, &mut command, true)?;
+    if let Some(server) = &server {
+        server.update(document.as_ref());
+    }
 
     // Setup file watching.
-    let (tx, rx) = std::sync::mpsc::channel();
-    let mut watcher = RecommendedWatcher::new(tx, notify::Config::default())
-        .map_err(|_| "failed to setup file watching")?;
+    let fs_tx = tx.clone();
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<notify::Event>| {
+            fs_tx.send(Message::Fs(event)).ok();
+        },
+        notify::Config::default(),
+    )
+    .map_err(|_| "failed to setup file watching")?;
 
     // Watch all the files that are used by the input file and its dependencies.
     watch_dependencies(&mut world, &mut watcher, HashSet::new())?;
 
     // Handle events.
     let timeout = std::time::Duration::from_millis(100);
-    let output = command.output();
     loop {
         let mut removed = HashSet::new();
         let mut recompile = false;
-        for event in rx
+        for message in rx
             .recv()
             .into_iter()
             .chain(std::iter::from_fn(|| rx.recv_timeout(timeout).ok()))
         {
-            let event = event.map_err(|_| "failed to watch directory")?;
+            let event = match message {
+                Message::Fs(event) => event.map_err(|_| "failed to watch directory")?,
+                Message::Click(click) => {
+                    if let Some(document) = &document {
+                        click.answer(&world, document);
+                    }
+                    continue;
+                }
+            };
 
             // Workaround for notify-rs' implicit unwatch on remove/rename
             // (triggered by some editors when saving files) with the inotify
@@ -69,8 +107,13 @@ pub fn watch(mut command: CompileCommand) -> StrResult<()> {
                 .map(ToOwned::to_owned)
                 .collect();
 
-            // Recompile.
-            compile_once(&mut world, &mut command, true)?;
+            // Recompile. The preview keeps showing the last document that
+            // compiled successfully.
+            let compiled = compile_once(&mut world, &mut command, true)?;
+            if let Some(server) = &server {
+                server.update(compiled.as_ref());
+            }
+            document = compiled.or(document);
             comemo::evict(10);
 
             // Adjust the watching.
@@ -79,6 +122,14 @@ pub fn watch(mut command: CompileCommand) -> StrResult<()> {
     }
 }
 
+/// A message to the watch loop.
+pub enum Message {
+    /// A file system event.
+    Fs(notify::Result<notify::Event>),
+    /// A click into the preview.
+    Click(Click),
+}
+
 /// Adjust the file watching. Watches all new dependencies and unwatches
 /// all `previous` dependencies that are not relevant anymore.
 #[tracing::instrument(skip_all)]
@@ -158,12 +209,19 @@ impl Status {
         w.set_color(&color)?;
         write!(w, "watching")?;
         w.reset()?;
-        writeln!(w, " {}", command.input.display())?;
+        writeln!(w, " {}", command.common.input)?;
 
         w.set_color(&color)?;
         write!(w, "writing to")?;
         w.reset()?;
-        writeln!(w, " {}", output.display())?;
+        writeln!(w, " {output}")?;
+
+        if let Some(Some(port)) = command.serve {
+            w.set_color(&color)?;
+            write!(w, "serving preview at")?;
+            w.reset()?;
+            writeln!(w, " http://127.0.0.1:{port}")?;
+        }
 
         writeln!(w)?;
         writeln!(w, "[{timestamp}] {}", self.message())?;
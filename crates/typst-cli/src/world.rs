@@ -2,23 +2,28 @@ use std::cell::{OnceCell, RefCell, RefMut};
 use std::collections::HashMap;
 use std::fs;
 use std::hash::Hash;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
 
 use chrono::Datelike;
 use comemo::Prehashed;
 use same_file::Handle;
 use siphasher::sip128::{Hasher128, SipHasher13};
-use typst::diag::{FileError, FileResult, StrResult};
-use typst::eval::{eco_format, Datetime, Library};
+use typst::diag::{bail, EcoString, FileError, FileResult, StrResult};
+use typst::eval::{eco_format, Datetime, Dict, Library, Value};
 use typst::file::FileId;
 use typst::font::{Font, FontBook};
 use typst::syntax::Source;
 use typst::util::{Bytes, PathExt};
 use typst::World;
 
-use crate::args::CompileCommand;
+use crate::args::{Input, SharedArgs};
 use crate::fonts::{FontSearcher, FontSlot};
-use crate::package::prepare_package;
+use crate::lockfile::{Lockfile, LOCKFILE_NAME};
+use crate::package::{PackageStorage, LOCAL_NAMESPACE};
+
+/// The virtual path of a main source file that is read from stdin.
+const STDIN_PATH: &str = "/<stdin>";
 
 /// A world that provides access to the operating system.
 pub struct SystemWorld {
@@ -26,12 +31,18 @@ pub struct SystemWorld {
     root: PathBuf,
     /// The input path.
     main: FileId,
+    /// The main source file if it was read from stdin.
+    stdin: Option<Source>,
     /// Typst's standard library.
     library: Prehashed<Library>,
     /// Metadata about discovered fonts.
     book: Prehashed<FontBook>,
     /// Locations of and storage for lazily loaded fonts.
     fonts: Vec<FontSlot>,
+    /// Where and how packages are looked up and downloaded.
+    packages: PackageStorage,
+    /// Which files the document may write.
+    writes: WritePermission,
     /// Maps package-path combinations to canonical hashes. All package-path
     /// combinations that point to the same file are mapped to the same hash. To
     /// be used in conjunction with `paths`.
@@ -45,39 +56,78 @@ pub struct SystemWorld {
 
 impl SystemWorld {
     /// Create a new system world.
-    pub fn new(command: &CompileCommand) -> StrResult<Self> {
+    pub fn new(command: &SharedArgs) -> StrResult<Self> {
         let mut searcher = FontSearcher::new();
-        searcher.search(&command.font_paths);
-
-        // Resolve the system-global input path.
-        let system_input = command.input.canonicalize().map_err(|_| {
-            eco_format!("input file not found (searched at {})", command.input.display())
-        })?;
+        searcher.search(&command.fonts);
+
+        // Resolve the system-global input path, unless reading from stdin.
+        let system_input = match &command.input {
+            Input::Path(path) => Some(path.canonicalize().map_err(|_| {
+                eco_format!("input file not found (searched at {})", path.display())
+            })?),
+            Input::Stdin => None,
+        };
 
         // Resolve the system-global root directory.
         let root = {
             let path = command
                 .root
                 .as_deref()
-                .or_else(|| system_input.parent())
+                .or_else(|| system_input.as_deref()?.parent())
                 .unwrap_or(Path::new("."));
             path.canonicalize().map_err(|_| {
                 eco_format!("root directory not found (searched at {})", path.display())
             })?
         };
 
-        // Resolve the input path within the project.
-        let project_input = system_input
-            .strip_prefix(&root)
-            .map(|path| Path::new("/").join(path))
-            .map_err(|_| "input file must be contained in project root")?;
+        // Resolve the input path within the project. Source from stdin gets a
+        // virtual path at the root so that relative imports work from there.
+        let (main, stdin) = match system_input {
+            Some(system_input) => {
+                let project_input = system_input
+                    .strip_prefix(&root)
+                    .map(|path| Path::new("/").join(path))
+                    .map_err(|_| "input file must be contained in project root")?;
+                (FileId::new(None, &project_input), None)
+            }
+            None => {
+                let id = FileId::new(None, Path::new(STDIN_PATH));
+                (id, Some(Source::new(id, read_stdin()?)))
+            }
+        };
+
+        // Resolve the directory into which the document may write files.
+        let write_dir = command
+            .allow_write
+            .as_deref()
+            .map(|dir| resolve_write_dir(dir, &root))
+            .transpose()?;
+
+        // Make the inputs from the command line available as `sys.inputs`.
+        let inputs: Dict = command
+            .inputs
+            .iter()
+            .map(|(key, value)| (key.as_str().into(), Value::Str(value.as_str().into())))
+            .collect();
+
+        // Packages are verified against the project's lockfile.
+        let lockfile = Lockfile::load(root.join(LOCKFILE_NAME))?;
+        let packages =
+            PackageStorage::new(&command.package).with_lockfile(lockfile, command.lock);
 
         Ok(Self {
             root,
-            main: FileId::new(None, &project_input),
-            library: Prehashed::new(typst_library::build()),
+            main,
+            stdin,
+            library: Prehashed::new(typst_library::build_with_inputs(inputs)),
             book: Prehashed::new(searcher.book),
             fonts: searcher.fonts,
+            packages,
+            writes: WritePermission {
+                root: root.clone(),
+                dir: write_dir,
+                packages: command.allow_package_write.clone(),
+            },
             hashes: RefCell::default(),
             paths: RefCell::default(),
             today: OnceCell::new(),
@@ -94,6 +144,27 @@ impl SystemWorld {
         self.paths.get_mut().values().map(|slot| slot.system_path.as_path())
     }
 
+    /// The path on disk at which a file written by the document is stored.
+    ///
+    /// Files written by a package end up in the `<namespace>/<name>`
+    /// subdirectory of the write directory, so that packages can neither
+    /// overwrite the project's files nor each other's.
+    pub fn write_path(&self, id: FileId) -> FileResult<PathBuf> {
+        let base = match (id.package(), &self.writes.dir) {
+            (Some(spec), Some(dir)) => {
+                dir.join(spec.namespace.as_str()).join(spec.name.as_str())
+            }
+            _ => self.root.clone(),
+        };
+        base.join_rooted(id.path()).ok_or(FileError::AccessDenied(None))
+    }
+
+    /// Record the hashes of packages that were locked by the last compilation
+    /// in the project's lockfile, if `--lock` was given.
+    pub fn save_lockfile(&self) -> io::Result<()> {
+        self.packages.save_lockfile()
+    }
+
     /// Reset the compilation state in preparation of a new compilation.
     pub fn reset(&mut self) {
         self.hashes.borrow_mut().clear();
@@ -122,16 +193,47 @@ impl World for SystemWorld {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.slot(id)?.source()
+        match &self.stdin {
+            Some(source) if id == self.main => Ok(source.clone()),
+            _ => self.slot(id)?.source(),
+        }
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.slot(id)?.file()
+        match &self.stdin {
+            Some(source) if id == self.main => Ok(source.text().as_bytes().into()),
+            _ => self.slot(id)?.file(),
+        }
     }
 
     fn write(&self, id: FileId, data: &[u8]) -> FileResult<()> {
-        let p = self.root.join_rooted(id.path()).ok_or(FileError::AccessDenied)?;
-        fs::write(&p, data).map_err(|f| FileError::from_io(f, &p))
+        let deny = |rule| FileError::AccessDenied(Some(rule));
+        let path = self.write_path(id)?;
+        self.writes.check(id, &path).map_err(deny)?;
+
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(FileError::AccessDenied(None));
+        };
+
+        // Symbolic links in the existing part of the path could lead out of
+        // the write directory, so the real path is checked before creating any
+        // directories and again afterwards.
+        let real = canonicalize_existing(parent)
+            .map_err(|err| FileError::from_io(err, parent))?
+            .join(name);
+        self.writes.check(id, &real).map_err(deny)?;
+        fs::create_dir_all(parent).map_err(|err| FileError::from_io(err, parent))?;
+
+        let real = parent
+            .canonicalize()
+            .map_err(|err| FileError::from_io(err, parent))?
+            .join(name);
+        self.writes.check(id, &real).map_err(deny)?;
+        if real.is_symlink() {
+            return Err(deny("files may not be written through symbolic links".into()));
+        }
+
+        fs::write(&real, data).map_err(|err| FileError::from_io(err, &real))
     }
 
     fn font(&self, index: usize) -> Option<Font> {
@@ -167,14 +269,14 @@ impl SystemWorld {
                 // Determine the root path relative to which the file path
                 // will be resolved.
                 let root = match id.package() {
-                    Some(spec) => prepare_package(spec)?,
+                    Some(spec) => self.packages.prepare_package(spec)?,
                     None => self.root.clone(),
                 };
 
                 // Join the path to the root. If it tries to escape, deny
                 // access. Note: It can still escape via symlinks.
                 system_path =
-                    root.join_rooted(id.path()).ok_or(FileError::AccessDenied)?;
+                    root.join_rooted(id.path()).ok_or(FileError::AccessDenied(None))?;
 
                 PathHash::new(&system_path)
             })
@@ -249,6 +351,92 @@ fn read(path: &Path) -> FileResult<Vec<u8>> {
     }
 }
 
+/// Read the main source file from stdin.
+fn read_stdin() -> StrResult<String> {
+    let mut buf = vec![];
+    io::stdin()
+        .read_to_end(&mut buf)
+        .map_err(|_| "failed to read input from stdin")?;
+    decode_utf8(buf).map_err(|_| "input from stdin is not valid UTF-8".into())
+}
+
+/// Which files a document may write.
+struct WritePermission {
+    /// The project root.
+    root: PathBuf,
+    /// The directory into which files may be written, if writing is allowed.
+    dir: Option<PathBuf>,
+    /// The packages outside of the `@local` namespace that may write files.
+    packages: Vec<String>,
+}
+
+impl WritePermission {
+    /// Check whether a file may be written to the given path, naming the rule
+    /// that forbids it otherwise.
+    ///
+    /// Local packages are under the user's control and are treated like the
+    /// project itself, while all other packages need an explicit grant.
+    fn check(&self, id: FileId, path: &Path) -> Result<(), EcoString> {
+        let Some(dir) = &self.dir else {
+            return Err("writing files is disabled, see --allow-write".into());
+        };
+
+        if let Some(spec) = id.package() {
+            if spec.namespace != LOCAL_NAMESPACE
+                && !self.packages.iter().any(|granted| *granted == spec.to_string())
+            {
+                return Err(eco_format!(
+                    "{spec} may not write files, see --allow-package-write"
+                ));
+            }
+        }
+
+        if !path.starts_with(dir) {
+            let project_dir = Path::new("/").join(dir.strip_prefix(&self.root).unwrap());
+            return Err(eco_format!(
+                "files may only be written into {}",
+                project_dir.display()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the directory into which the document may write files.
+///
+/// The directory is only created once a file is written into it. Until then,
+/// its existing ancestors are canonicalized just like the root and the missing
+/// rest is appended.
+fn resolve_write_dir(dir: &Path, root: &Path) -> StrResult<PathBuf> {
+    let failed = |err: io::Error| {
+        eco_format!("failed to resolve write directory at {} ({err})", dir.display())
+    };
+
+    let absolute = std::env::current_dir().map_err(failed)?.join(dir);
+    let existing = absolute.ancestors().find(|path| path.exists()).unwrap();
+    let missing = absolute.strip_prefix(existing).unwrap();
+    let normal = |component: Component| matches!(component, Component::Normal(_));
+    if !missing.components().all(normal) {
+        bail!("failed to resolve write directory at {}", dir.display());
+    }
+
+    let resolved = canonicalize_existing(&absolute).map_err(failed)?;
+    if !resolved.starts_with(root) {
+        bail!("write directory must be contained in project root");
+    }
+
+    Ok(resolved)
+}
+
+/// Canonicalize the longest existing ancestor of an absolute path and append
+/// the missing rest unchanged.
+fn canonicalize_existing(path: &Path) -> io::Result<PathBuf> {
+    let existing = path.ancestors().find(|path| path.exists()).unwrap();
+    let missing = path.strip_prefix(existing).unwrap();
+    Ok(existing.canonicalize()?.join(missing))
+}
+
 /// Decode UTF-8 with an optional BOM.
 fn decode_utf8(buf: Vec<u8>) -> FileResult<String> {
     Ok(if buf.starts_with(b"\xef\xbb\xbf") {
@@ -259,3 +447,103 @@ fn decode_utf8(buf: Vec<u8>) -> FileResult<String> {
         String::from_utf8(buf)?
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Permission to write into `/project/out`.
+    fn permission(packages: &[&str]) -> WritePermission {
+        WritePermission {
+            root: PathBuf::from("/project"),
+            dir: Some(PathBuf::from("/project/out")),
+            packages: packages.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// A file written by the project or by the given package.
+    fn id(package: Option<&str>) -> FileId {
+        let spec = package.map(|spec| spec.parse().unwrap());
+        FileId::new(spec, Path::new("/out/data.json"))
+    }
+
+    #[test]
+    fn test_write_permission_disabled() {
+        let permission = WritePermission { dir: None, ..permission(&[]) };
+        let path = Path::new("/project/out/data.json");
+        assert_eq!(
+            permission.check(id(None), path).unwrap_err(),
+            "writing files is disabled, see --allow-write",
+        );
+    }
+
+    #[test]
+    fn test_write_permission_outside_of_directory() {
+        let permission = permission(&[]);
+        let check = |path: &str| permission.check(id(None), Path::new(path));
+        assert!(check("/project/out/data.json").is_ok());
+        assert!(check("/project/out/a/b.txt").is_ok());
+        assert_eq!(
+            check("/project/data.json").unwrap_err(),
+            "files may only be written into /out",
+        );
+        assert!(check("/project/output/x").is_err());
+    }
+
+    #[test]
+    fn test_write_permission_for_packages() {
+        let path = Path::new("/project/out/data.json");
+        let preview = "@preview/example:0.1.0";
+        let other = "@acme/example:0.1.0";
+
+        // Local packages may write like the project itself.
+        let ungranted = permission(&[]);
+        assert!(ungranted.check(id(Some("@local/example:0.1.0")), path).is_ok());
+
+        // All other packages must be granted, whatever their namespace.
+        assert_eq!(
+            ungranted.check(id(Some(preview)), path).unwrap_err(),
+            "@preview/example:0.1.0 may not write files, see --allow-package-write",
+        );
+        assert!(ungranted.check(id(Some(other)), path).is_err());
+
+        let granted = permission(&[preview, other]);
+        assert!(granted.check(id(Some(preview)), path).is_ok());
+        assert!(granted.check(id(Some(other)), path).is_ok());
+        assert!(granted.check(id(Some("@preview/example:0.2.0")), path).is_err());
+    }
+
+    #[test]
+    fn test_resolve_write_dir_does_not_create_it() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let dir = root.join("out/nested");
+        assert_eq!(resolve_write_dir(&dir, &root), Ok(dir.clone()));
+        assert!(!root.join("out").exists());
+        assert!(resolve_write_dir(&root.join(".."), &root).is_err());
+        assert!(resolve_write_dir(&root.join("missing/../.."), &root).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_symlink_out_of_write_dir_is_denied() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let outside = outside.path().canonicalize().unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+        let real = canonicalize_existing(&root.join("link/a/data.json")).unwrap();
+        assert_eq!(real, outside.join("a/data.json"));
+
+        let permission = WritePermission {
+            root: root.clone(),
+            dir: Some(root),
+            packages: vec![],
+        };
+        assert_eq!(
+            permission.check(id(None), &real).unwrap_err(),
+            "files may only be written into /",
+        );
+    }
+}
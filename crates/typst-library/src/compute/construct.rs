@@ -4,6 +4,7 @@ use std::str::FromStr;
 use time::{Month, PrimitiveDateTime};
 
 use typst::eval::{Datetime, Module, Regex};
+use typst::util::Bytes;
 
 use crate::prelude::*;
 
@@ -486,6 +487,7 @@ cast! {
 ///   optional `base` parameter.
 /// - Floats are formatted in base 10 and never in exponential notation.
 /// - From labels the name is extracted.
+/// - Bytes are decoded as UTF-8.
 ///
 /// If you wish to convert from and to Unicode code points, see
 /// [`str.to-unicode`]($func/str.to-unicode) and
@@ -546,6 +548,9 @@ cast! {
     v: f64 => Self::Str(format_str!("{}", v)),
     v: Label => Self::Str(v.0.into()),
     v: Str => Self::Str(v),
+    v: Bytes => Self::Str(
+        std::str::from_utf8(&v).map_err(|_| "bytes are not valid utf-8")?.into()
+    ),
 }
 
 /// Format an integer in a base.
@@ -633,6 +638,85 @@ cast! {
     },
 }
 
+/// Converts a value to bytes.
+///
+/// - Strings are encoded in UTF-8.
+/// - Arrays of integers between `{0}` and `{255}` are converted directly.
+/// - Bytes are returned as is.
+///
+/// Bytes can be concatenated with `+`, indexed with the `at` method and
+/// encoded as base64 or hexadecimal strings with the `base64` and `hex`
+/// methods.
+///
+/// ## Example { #example }
+/// ```example
+/// #bytes("Hello 😃") \
+/// #bytes((123, 160, 22, 0)) \
+/// #bytes("Typst").hex()
+/// ```
+///
+/// Display: Bytes
+/// Category: construct
+#[func]
+#[scope(
+    scope.define("from-base64", bytes_from_base64_func());
+    scope.define("from-hex", bytes_from_hex_func());
+    scope
+)]
+pub fn bytes(
+    /// The value that should be converted to bytes.
+    value: ToBytes,
+) -> Bytes {
+    value.0
+}
+
+/// A value that can be cast to bytes.
+pub struct ToBytes(Bytes);
+
+cast! {
+    ToBytes,
+    v: Str => Self(v.as_bytes().into()),
+    v: Array => {
+        let bytes = v.into_iter().map(u8::from_value).collect::<StrResult<Vec<_>>>()?;
+        Self(bytes.into())
+    },
+    v: Bytes => Self(v),
+}
+
+/// Decodes bytes from a base64 string.
+///
+/// ## Example { #example }
+/// ```example
+/// #str(bytes.from-base64("VHlwc3Q="))
+/// ```
+///
+/// Display: Bytes From Base64
+/// Category: construct
+#[func]
+pub fn bytes_from_base64(
+    /// The base64 string that should be decoded.
+    text: Str,
+) -> StrResult<Bytes> {
+    Bytes::from_base64(&text)
+}
+
+/// Decodes bytes from a string of hexadecimal digits, two per byte.
+///
+/// ## Example { #example }
+/// ```example
+/// #str(bytes.from-hex("5479707374"))
+/// ```
+///
+/// Display: Bytes From Hex
+/// Category: construct
+#[func]
+pub fn bytes_from_hex(
+    /// The hexadecimal string that should be decoded.
+    text: Str,
+) -> StrResult<Bytes> {
+    Bytes::from_hex(&text)
+}
+
 /// Creates a label from a string.
 ///
 /// Inserting a label into content attaches it to the closest previous element
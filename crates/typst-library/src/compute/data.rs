@@ -1,11 +1,15 @@
-use typst::diag::{format_xml_like_error, FileError};
+use typst::diag::format_xml_like_error;
 use typst::eval::Datetime;
+use typst::util::Bytes;
 
 use crate::prelude::*;
 
-/// Reads plain text from a file.
+/// Reads plain text or data from a file.
 ///
-/// The file will be read and returned as a string.
+/// By default, the file will be read as UTF-8 and returned as a string.
+///
+/// If you specify `{encoding: none}`, this returns raw [bytes]($func/bytes)
+/// instead.
 ///
 /// ## Example { #example }
 /// ```example
@@ -21,20 +25,58 @@ use crate::prelude::*;
 pub fn read(
     /// Path to a file.
     path: Spanned<EcoString>,
+    /// The encoding to read the file with.
+    ///
+    /// If set to `{none}`, this function returns raw bytes.
+    #[named]
+    #[default(Some(Encoding::Utf8))]
+    encoding: Option<Encoding>,
     /// The virtual machine.
     vm: &mut Vm,
-) -> SourceResult<Str> {
+) -> SourceResult<Readable> {
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
-    let text = std::str::from_utf8(&data)
-        .map_err(|_| "file is not valid utf-8")
-        .at(span)?;
-    Ok(text.into())
+    Ok(match encoding {
+        None => Readable::Bytes(data),
+        Some(Encoding::Utf8) => {
+            let text = std::str::from_utf8(&data)
+                .map_err(|_| "file is not valid utf-8")
+                .at(span)?;
+            Readable::Str(text.into())
+        }
+    })
 }
 
-/// Writes plain text to a file.
+/// An encoding of a file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Cast)]
+pub enum Encoding {
+    /// The Unicode UTF-8 encoding.
+    #[string("utf8")]
+    Utf8,
+}
+
+/// Writes text or bytes to a file.
+///
+/// The file is only written once the document has compiled successfully.
+/// Writing different data to the same file more than once is an error.
+///
+/// Where files may be written is up to the environment. The command line
+/// interface doesn't allow writing at all unless a directory is granted with
+/// `--allow-write`. Packages that are not from `@local` additionally need to be
+/// granted with `--allow-package-write`. A package's files are written into the
+/// `<namespace>/<name>` subdirectory of the granted directory.
 ///
+/// ## Example { #example }
+/// ```typ
+/// #let people = (
+///   (name: "Alice", age: 32),
+///   (name: "Bob", age: 27),
+/// )
+///
+/// // Needs `--allow-write out` on the command line.
+/// #write("out/people.json", json.encode(people))
+/// ```
 ///
 /// Display: Write
 /// Category: data-loading
@@ -42,17 +84,15 @@ pub fn read(
 pub fn write(
     /// Path to a file.
     path: Spanned<EcoString>,
-    /// Text to write.
-    text: Spanned<EcoString>,
+    /// The text or bytes to write.
+    data: Readable,
     /// The virtual machine.
     vm: &mut Vm,
 ) -> SourceResult<()> {
     let Spanned { v: path, span } = path;
-    let Spanned { v: text, span: _ } = text;
-    let complete_pth = "dest/".to_owned() + &path.trim_start_matches('/');
-    let id = vm.location()
-        .join(&complete_pth).at(span)?;
-    vm.world().write(id, text.as_bytes()).at(span)
+    let id = vm.location().join(&path).at(span)?;
+    vm.vt.tracer.write(id, data.into(), span);
+    Ok(())
 }
 
 /// Reads structured data from a CSV file.
@@ -76,6 +116,11 @@ pub fn write(
 /// Display: CSV
 /// Category: data-loading
 #[func]
+#[scope(
+    scope.define("decode", csv_decode_func());
+    scope.define("encode", csv_encode_func());
+    scope
+)]
 pub fn csv(
     /// Path to a CSV file.
     path: Spanned<EcoString>,
@@ -90,12 +135,41 @@ pub fn csv(
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
+    parse_csv(&data, delimiter, Origin::File).at(span)
+}
+
+/// Reads structured data from a CSV string or bytes.
+///
+/// The data is parsed in the same way as by the [`csv`]($func/csv) function.
+///
+/// ## Example { #example }
+/// ```example
+/// #csv.decode("Name;Weight\nDebby;150", delimiter: ";")
+/// ```
+///
+/// Display: Decode CSV
+/// Category: data-loading
+#[func]
+pub fn csv_decode(
+    /// CSV data.
+    data: Spanned<Readable>,
+    /// The delimiter that separates columns in the CSV data.
+    /// Must be a single ASCII character.
+    #[named]
+    #[default]
+    delimiter: Delimiter,
+) -> SourceResult<Array> {
+    let Spanned { v: data, span } = data;
+    parse_csv(data.as_slice(), delimiter, Origin::Data).at(span)
+}
 
+/// Parse CSV data into a 2-dimensional array of strings.
+fn parse_csv(data: &[u8], delimiter: Delimiter, origin: Origin) -> StrResult<Array> {
     let mut builder = csv::ReaderBuilder::new();
     builder.has_headers(false);
     builder.delimiter(delimiter.0 as u8);
 
-    let mut reader = builder.from_reader(data.as_slice());
+    let mut reader = builder.from_reader(data);
     let mut array = Array::new();
 
     for (line, result) in reader.records().enumerate() {
@@ -103,7 +177,7 @@ pub fn csv(
         // `has_headers` set to `false`. See issue:
         // https://github.com/BurntSushi/rust-csv/issues/184
         let line = line + 1; // Counting lines from 1
-        let row = result.map_err(|err| format_csv_error(err, line)).at(span)?;
+        let row = result.map_err(|err| format_csv_error(err, line, origin))?;
         let sub = row.into_iter().map(|field| field.into_value()).collect();
         array.push(Value::Array(sub))
     }
@@ -138,16 +212,170 @@ cast! {
     },
 }
 
+/// Encodes an array of rows into a CSV string.
+///
+/// Each row must be an array of strings, numbers, booleans, datetimes or
+/// `{none}`, which is encoded as an empty field. All rows must have the same
+/// number of fields.
+///
+/// ## Example { #example }
+/// ```example
+/// #csv.encode((
+///   ("Name", "Weight"),
+///   ("Debby", 150),
+/// ))
+/// ```
+///
+/// Display: Encode CSV
+/// Category: data-loading
+#[func]
+pub fn csv_encode(
+    /// The rows to encode.
+    rows: Spanned<Array>,
+    /// The delimiter that separates columns in the CSV string.
+    /// Must be a single ASCII character.
+    #[named]
+    #[default]
+    delimiter: Delimiter,
+) -> SourceResult<Str> {
+    let Spanned { v: rows, span } = rows;
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter.0 as u8)
+        .from_writer(vec![]);
+
+    // The rows and fields have no spans of their own, so errors name their
+    // position, counting from 1.
+    for (y, row) in rows.into_iter().enumerate() {
+        let y = y + 1;
+        let row = match row {
+            Value::Array(row) => row,
+            v => bail!(span, "expected array for row {y}, found {}", v.type_name()),
+        };
+
+        let fields = row
+            .iter()
+            .enumerate()
+            .map(|(x, field)| {
+                encode_csv_field(field)
+                    .map_err(|err| eco_format!("{err} in row {y}, column {}", x + 1))
+            })
+            .collect::<StrResult<Vec<_>>>()
+            .at(span)?;
+        writer
+            .write_record(&fields)
+            .map_err(|err| format_csv_encode_error(err, y))
+            .at(span)?;
+    }
+
+    let data = writer
+        .into_inner()
+        .map_err(|_| "failed to encode value as csv")
+        .at(span)?;
+    Ok(String::from_utf8(data).unwrap_or_default().into())
+}
+
+/// Convert a Typst value to a CSV field.
+fn encode_csv_field(value: &Value) -> StrResult<EcoString> {
+    Ok(match value {
+        Value::None => EcoString::new(),
+        Value::Bool(v) => eco_format!("{v}"),
+        Value::Int(v) => eco_format!("{v}"),
+        Value::Float(v) => eco_format!("{v}"),
+        Value::Str(v) => v.as_str().into(),
+        v => match v.downcast::<Datetime>() {
+            Some(datetime) => format_datetime(datetime),
+            None => bail!("cannot encode {} as csv", v.type_name()),
+        },
+    })
+}
+
+/// Format the user-facing CSV encoding error message.
+fn format_csv_encode_error(error: csv::Error, line: usize) -> EcoString {
+    match error.kind() {
+        csv::ErrorKind::UnequalLengths { expected_len, len, .. } => {
+            eco_format!(
+                "failed to encode csv: found {len} instead of {expected_len} fields in row {line}"
+            )
+        }
+        _ => "failed to encode value as csv".into(),
+    }
+}
+
+/// Data that can be decoded: A string or raw bytes.
+pub enum Readable {
+    /// A decoded string.
+    Str(Str),
+    /// Raw bytes.
+    Bytes(Bytes),
+}
+
+impl Readable {
+    /// The raw bytes of the data.
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Str(v) => v.as_bytes(),
+            Self::Bytes(v) => v.as_slice(),
+        }
+    }
+}
+
+impl From<Readable> for Bytes {
+    fn from(value: Readable) -> Self {
+        match value {
+            Readable::Str(v) => v.as_bytes().into(),
+            Readable::Bytes(v) => v,
+        }
+    }
+}
+
+cast! {
+    Readable,
+    self => match self {
+        Self::Str(v) => v.into_value(),
+        Self::Bytes(v) => v.into_value(),
+    },
+    v: Str => Self::Str(v),
+    v: Bytes => Self::Bytes(v),
+}
+
+/// Where parsed data comes from, which error messages refer to.
+#[derive(Copy, Clone)]
+enum Origin {
+    /// A file that was loaded from a path.
+    File,
+    /// A string or bytes that were passed to a `decode` function.
+    Data,
+}
+
+impl Origin {
+    /// Name the data in the given format, e.g. `json file`.
+    fn name(self, format: &str) -> EcoString {
+        match self {
+            Self::File => eco_format!("{format} file"),
+            Self::Data => eco_format!("{format} data"),
+        }
+    }
+
+    /// The error message for data that is not valid UTF-8.
+    fn invalid_utf8(self) -> EcoString {
+        match self {
+            Self::File => "file is not valid utf-8".into(),
+            Self::Data => "data is not valid utf-8".into(),
+        }
+    }
+}
+
 /// Format the user-facing CSV error message.
-fn format_csv_error(error: csv::Error, line: usize) -> EcoString {
+fn format_csv_error(error: csv::Error, line: usize, origin: Origin) -> EcoString {
     match error.kind() {
-        csv::ErrorKind::Utf8 { .. } => "file is not valid utf-8".into(),
+        csv::ErrorKind::Utf8 { .. } => origin.invalid_utf8(),
         csv::ErrorKind::UnequalLengths { expected_len, len, .. } => {
             eco_format!(
-                "failed to parse csv file: found {len} instead of {expected_len} fields in line {line}"
+                "failed to parse {}: found {len} instead of {expected_len} fields in line {line}",
+                origin.name("csv"),
             )
         }
-        _ => "failed to parse csv file".into(),
+        _ => eco_format!("failed to parse {}", origin.name("csv")),
     }
 }
 
@@ -192,6 +420,11 @@ fn format_csv_error(error: csv::Error, line: usize) -> EcoString {
 /// Display: JSON
 /// Category: data-loading
 #[func]
+#[scope(
+    scope.define("decode", json_decode_func());
+    scope.define("encode", json_encode_func());
+    scope
+)]
 pub fn json(
     /// Path to a JSON file.
     path: Spanned<EcoString>,
@@ -201,8 +434,35 @@ pub fn json(
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
+    parse_json(&data, Origin::File).at(span)
+}
+
+/// Reads structured data from a JSON string or bytes.
+///
+/// The data is converted in the same way as by the [`json`]($func/json)
+/// function.
+///
+/// ## Example { #example }
+/// ```example
+/// #let data = json.decode("{\"name\": \"Debby\"}")
+/// #data.name
+/// ```
+///
+/// Display: Decode JSON
+/// Category: data-loading
+#[func]
+pub fn json_decode(
+    /// JSON data.
+    data: Spanned<Readable>,
+) -> SourceResult<Value> {
+    let Spanned { v: data, span } = data;
+    parse_json(data.as_slice(), Origin::Data).at(span)
+}
+
+/// Parse JSON data into a Typst value.
+fn parse_json(data: &[u8], origin: Origin) -> StrResult<Value> {
     let value: serde_json::Value =
-        serde_json::from_slice(&data).map_err(format_json_error).at(span)?;
+        serde_json::from_slice(data).map_err(|err| format_json_error(err, origin))?;
     Ok(convert_json(value))
 }
 
@@ -227,10 +487,71 @@ fn convert_json(value: serde_json::Value) -> Value {
     }
 }
 
+/// Encodes structured data into a JSON string.
+///
+/// Dictionaries, arrays, strings, numbers, booleans and `{none}` are encoded
+/// as their JSON counterparts. Datetimes are encoded as ISO 8601 strings.
+///
+/// ## Example { #example }
+/// ```example
+/// #json.encode((name: "Debby", weight: 150))
+/// ```
+///
+/// Display: Encode JSON
+/// Category: data-loading
+#[func]
+pub fn json_encode(
+    /// The value to encode.
+    value: Spanned<Value>,
+    /// Whether to pretty print the JSON with newlines and indentation.
+    #[named]
+    #[default(true)]
+    pretty: bool,
+) -> SourceResult<Str> {
+    let Spanned { v: value, span } = value;
+    let json = encode_json(&value).at(span)?;
+    let text = if pretty {
+        serde_json::to_string_pretty(&json)
+    } else {
+        serde_json::to_string(&json)
+    };
+
+    Ok(text.map_err(|_| "failed to encode value as json").at(span)?.into())
+}
+
+/// Convert a Typst value to a JSON value.
+fn encode_json(value: &Value) -> StrResult<serde_json::Value> {
+    Ok(match value {
+        Value::None => serde_json::Value::Null,
+        Value::Bool(v) => serde_json::Value::Bool(*v),
+        Value::Int(v) => serde_json::Value::Number((*v).into()),
+        Value::Float(v) => serde_json::Number::from_f64(*v)
+            .map(serde_json::Value::Number)
+            .ok_or("cannot encode infinite or NaN float as json")?,
+        Value::Str(v) => serde_json::Value::String(v.as_str().into()),
+        Value::Array(v) => {
+            serde_json::Value::Array(v.iter().map(encode_json).collect::<StrResult<_>>()?)
+        }
+        Value::Dict(v) => serde_json::Value::Object(
+            v.iter()
+                .map(|(key, value)| Ok((String::from(key.as_str()), encode_json(value)?)))
+                .collect::<StrResult<_>>()?,
+        ),
+        v => match v.downcast::<Datetime>() {
+            Some(datetime) => serde_json::Value::String(format_datetime(datetime).into()),
+            None => bail!("cannot encode {} as json", v.type_name()),
+        },
+    })
+}
+
 /// Format the user-facing JSON error message.
-fn format_json_error(error: serde_json::Error) -> EcoString {
+fn format_json_error(error: serde_json::Error, origin: Origin) -> EcoString {
     assert!(error.is_syntax() || error.is_eof());
-    eco_format!("failed to parse json file: syntax error in line {}", error.line())
+    eco_format!(
+        "failed to parse {}: syntax error in line {}",
+        origin.name("json"),
+        error.line()
+    )
 }
 
 /// Reads structured data from a TOML file.
@@ -258,6 +579,11 @@ fn format_json_error(error: serde_json::Error) -> EcoString {
 /// Display: TOML
 /// Category: data-loading
 #[func]
+#[scope(
+    scope.define("decode", toml_decode_func());
+    scope.define("encode", toml_encode_func());
+    scope
+)]
 pub fn toml(
     /// Path to a TOML file.
     path: Spanned<EcoString>,
@@ -267,12 +593,36 @@ pub fn toml(
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
+    parse_toml(&data, Origin::File).at(span)
+}
 
-    let raw = std::str::from_utf8(&data)
-        .map_err(|_| "file is not valid utf-8")
-        .at(span)?;
+/// Reads structured data from a TOML string or bytes.
+///
+/// The data is converted in the same way as by the [`toml`]($func/toml)
+/// function.
+///
+/// ## Example { #example }
+/// ```example
+/// #let details = toml.decode("title = \"Zoo\"")
+/// #details.title
+/// ```
+///
+/// Display: Decode TOML
+/// Category: data-loading
+#[func]
+pub fn toml_decode(
+    /// TOML data.
+    data: Spanned<Readable>,
+) -> SourceResult<Value> {
+    let Spanned { v: data, span } = data;
+    parse_toml(data.as_slice(), Origin::Data).at(span)
+}
 
-    let value: toml::Value = toml::from_str(raw).map_err(format_toml_error).at(span)?;
+/// Parse TOML data into a Typst value.
+fn parse_toml(data: &[u8], origin: Origin) -> StrResult<Value> {
+    let raw = std::str::from_utf8(data).map_err(|_| origin.invalid_utf8())?;
+    let value: toml::Value =
+        toml::from_str(raw).map_err(|err| format_toml_error(err, origin))?;
     Ok(convert_toml(value))
 }
 
@@ -312,17 +662,99 @@ fn convert_toml(value: toml::Value) -> Value {
     }
 }
 
+/// Encodes a dictionary into a TOML string.
+///
+/// Since TOML has no null value, `{none}` cannot be encoded. Datetimes are
+/// encoded as TOML datetimes.
+///
+/// ## Example { #example }
+/// ```example
+/// #toml.encode((title: "Zoo", version: "0.1.0"))
+/// ```
+///
+/// Display: Encode TOML
+/// Category: data-loading
+#[func]
+pub fn toml_encode(
+    /// The dictionary to encode.
+    value: Spanned<Value>,
+    /// Whether to pretty print the TOML, with arrays split across lines.
+    #[named]
+    #[default(true)]
+    pretty: bool,
+) -> SourceResult<Str> {
+    let Spanned { v: value, span } = value;
+    let Value::Dict(_) = value else {
+        bail!(span, "expected dictionary, found {}", value.type_name());
+    };
+
+    let toml = encode_toml(&value).at(span)?;
+    let text =
+        if pretty { toml::to_string_pretty(&toml) } else { toml::to_string(&toml) };
+    Ok(text
+        .map_err(|err| eco_format!("failed to encode value as toml ({err})"))
+        .at(span)?
+        .into())
+}
+
+/// Convert a Typst value to a TOML value.
+fn encode_toml(value: &Value) -> StrResult<toml::Value> {
+    Ok(match value {
+        Value::Bool(v) => toml::Value::Boolean(*v),
+        Value::Int(v) => toml::Value::Integer(*v),
+        Value::Float(v) => toml::Value::Float(*v),
+        Value::Str(v) => toml::Value::String(v.as_str().into()),
+        Value::Array(v) => {
+            toml::Value::Array(v.iter().map(encode_toml).collect::<StrResult<_>>()?)
+        }
+        Value::Dict(v) => toml::Value::Table(
+            v.iter()
+                .map(|(key, value)| Ok((String::from(key.as_str()), encode_toml(value)?)))
+                .collect::<StrResult<_>>()?,
+        ),
+        v => match v.downcast::<Datetime>() {
+            Some(datetime) => toml::Value::Datetime(encode_toml_datetime(datetime)?),
+            None => bail!("cannot encode {} as toml", v.type_name()),
+        },
+    })
+}
+
+/// Convert a Typst datetime to a TOML datetime.
+fn encode_toml_datetime(datetime: &Datetime) -> StrResult<toml::value::Datetime> {
+    let date = match (datetime.year(), datetime.month(), datetime.day()) {
+        (Some(year), Some(month), Some(day)) => Some(toml::value::Date {
+            year: u16::try_from(year)
+                .ok()
+                .filter(|&year| year <= 9999)
+                .ok_or("cannot encode year outside of 0-9999 as toml")?,
+            month,
+            day,
+        }),
+        _ => None,
+    };
+
+    let time = match (datetime.hour(), datetime.minute(), datetime.second()) {
+        (Some(hour), Some(minute), Some(second)) => {
+            Some(toml::value::Time { hour, minute, second, nanosecond: 0 })
+        }
+        _ => None,
+    };
+
+    Ok(toml::value::Datetime { date, time, offset: None })
+}
+
 /// Format the user-facing TOML error message.
-fn format_toml_error(error: toml::de::Error) -> EcoString {
+fn format_toml_error(error: toml::de::Error, origin: Origin) -> EcoString {
     if let Some(range) = error.span() {
         eco_format!(
-            "failed to parse toml file: {}, index {}-{}",
+            "failed to parse {}: {}, index {}-{}",
+            origin.name("toml"),
             error.message(),
             range.start,
             range.end
         )
     } else {
-        eco_format!("failed to parse toml file: {}", error.message())
+        eco_format!("failed to parse {}: {}", origin.name("toml"), error.message())
     }
 }
 
@@ -367,6 +799,11 @@ fn format_toml_error(error: toml::de::Error) -> EcoString {
 /// Display: YAML
 /// Category: data-loading
 #[func]
+#[scope(
+    scope.define("decode", yaml_decode_func());
+    scope.define("encode", yaml_encode_func());
+    scope
+)]
 pub fn yaml(
     /// Path to a YAML file.
     path: Spanned<EcoString>,
@@ -376,8 +813,35 @@ pub fn yaml(
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
+    parse_yaml(&data, Origin::File).at(span)
+}
+
+/// Reads structured data from a YAML string or bytes.
+///
+/// The data is converted in the same way as by the [`yaml`]($func/yaml)
+/// function.
+///
+/// ## Example { #example }
+/// ```example
+/// #let data = yaml.decode("name: Debby\nweight: 150")
+/// #data.name
+/// ```
+///
+/// Display: Decode YAML
+/// Category: data-loading
+#[func]
+pub fn yaml_decode(
+    /// YAML data.
+    data: Spanned<Readable>,
+) -> SourceResult<Value> {
+    let Spanned { v: data, span } = data;
+    parse_yaml(data.as_slice(), Origin::Data).at(span)
+}
+
+/// Parse YAML data into a Typst value.
+fn parse_yaml(data: &[u8], origin: Origin) -> StrResult<Value> {
     let value: serde_yaml::Value =
-        serde_yaml::from_slice(&data).map_err(format_yaml_error).at(span)?;
+        serde_yaml::from_slice(data).map_err(|err| format_yaml_error(err, origin))?;
     Ok(convert_yaml(value))
 }
 
@@ -413,9 +877,74 @@ fn convert_yaml_key(key: serde_yaml::Value) -> Option<Str> {
     }
 }
 
+/// Encodes structured data into a YAML string.
+///
+/// Dictionaries, arrays, strings, numbers, booleans and `{none}` are encoded
+/// as their YAML counterparts. Datetimes are encoded as ISO 8601 strings.
+///
+/// ## Example { #example }
+/// ```example
+/// #yaml.encode((name: "Debby", tags: ("zebra", "herbivore")))
+/// ```
+///
+/// Display: Encode YAML
+/// Category: data-loading
+#[func]
+pub fn yaml_encode(
+    /// The value to encode.
+    value: Spanned<Value>,
+) -> SourceResult<Str> {
+    let Spanned { v: value, span } = value;
+    let yaml = encode_yaml(&value).at(span)?;
+    Ok(serde_yaml::to_string(&yaml)
+        .map_err(|_| "failed to encode value as yaml")
+        .at(span)?
+        .into())
+}
+
+/// Convert a Typst value to a YAML value.
+fn encode_yaml(value: &Value) -> StrResult<serde_yaml::Value> {
+    Ok(match value {
+        Value::None => serde_yaml::Value::Null,
+        Value::Bool(v) => serde_yaml::Value::Bool(*v),
+        Value::Int(v) => serde_yaml::Value::Number((*v).into()),
+        Value::Float(v) => serde_yaml::Value::Number((*v).into()),
+        Value::Str(v) => serde_yaml::Value::String(v.as_str().into()),
+        Value::Array(v) => serde_yaml::Value::Sequence(
+            v.iter().map(encode_yaml).collect::<StrResult<_>>()?,
+        ),
+        Value::Dict(v) => serde_yaml::Value::Mapping(
+            v.iter()
+                .map(|(key, value)| {
+                    Ok((
+                        serde_yaml::Value::String(key.as_str().into()),
+                        encode_yaml(value)?,
+                    ))
+                })
+                .collect::<StrResult<_>>()?,
+        ),
+        v => match v.downcast::<Datetime>() {
+            Some(datetime) => serde_yaml::Value::String(format_datetime(datetime).into()),
+            None => bail!("cannot encode {} as yaml", v.type_name()),
+        },
+    })
+}
+
+/// Format a datetime as an ISO 8601 string, for formats that have no datetime
+/// type of their own.
+fn format_datetime(datetime: &Datetime) -> EcoString {
+    let pattern = match datetime {
+        Datetime::Date(_) => "[year]-[month]-[day]",
+        Datetime::Time(_) => "[hour]:[minute]:[second]",
+        Datetime::Datetime(_) => "[year]-[month]-[day]T[hour]:[minute]:[second]",
+    };
+
+    datetime.display(Some(pattern.into())).unwrap_or_default()
+}
+
 /// Format the user-facing YAML error message.
-fn format_yaml_error(error: serde_yaml::Error) -> EcoString {
-    eco_format!("failed to parse yaml file: {}", error.to_string().trim())
+fn format_yaml_error(error: serde_yaml::Error, origin: Origin) -> EcoString {
+    eco_format!("failed to parse {}: {}", origin.name("yaml"), error.to_string().trim())
 }
 
 /// Reads structured data from an XML file.
@@ -470,6 +999,10 @@ fn format_yaml_error(error: serde_yaml::Error) -> EcoString {
 /// Display: XML
 /// Category: data-loading
 #[func]
+#[scope(
+    scope.define("decode", xml_decode_func());
+    scope
+)]
 pub fn xml(
     /// Path to an XML file.
     path: Spanned<EcoString>,
@@ -479,8 +1012,36 @@ pub fn xml(
     let Spanned { v: path, span } = path;
     let id = vm.location().join(&path).at(span)?;
     let data = vm.world().file(id).at(span)?;
-    let text = std::str::from_utf8(&data).map_err(FileError::from).at(span)?;
-    let document = roxmltree::Document::parse(text).map_err(format_xml_error).at(span)?;
+    parse_xml(&data, Origin::File).at(span)
+}
+
+/// Reads structured data from an XML string or bytes.
+///
+/// The data is converted in the same way as by the [`xml`]($func/xml)
+/// function.
+///
+/// ## Example { #example }
+/// ```example
+/// #let data = xml.decode("<zoo><animal>Debby</animal></zoo>")
+/// #data.first().children.first().tag
+/// ```
+///
+/// Display: Decode XML
+/// Category: data-loading
+#[func]
+pub fn xml_decode(
+    /// XML data.
+    data: Spanned<Readable>,
+) -> SourceResult<Value> {
+    let Spanned { v: data, span } = data;
+    parse_xml(data.as_slice(), Origin::Data).at(span)
+}
+
+/// Parse XML data into a Typst value.
+fn parse_xml(data: &[u8], origin: Origin) -> StrResult<Value> {
+    let text = std::str::from_utf8(data).map_err(|_| origin.invalid_utf8())?;
+    let document =
+        roxmltree::Document::parse(text).map_err(|err| format_xml_error(err, origin))?;
     Ok(convert_xml(document.root()))
 }
 
@@ -509,6 +1070,6 @@ fn convert_xml(node: roxmltree::Node) -> Value {
 }
 
 /// Format the user-facing XML error message.
-fn format_xml_error(error: roxmltree::Error) -> EcoString {
-    format_xml_like_error("xml file", error)
+fn format_xml_error(error: roxmltree::Error, origin: Origin) -> EcoString {
+    format_xml_like_error(&origin.name("xml"), error)
 }
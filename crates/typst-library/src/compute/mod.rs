@@ -4,6 +4,7 @@ pub mod calc;
 mod construct;
 mod data;
 mod foundations;
+pub mod sys;
 
 pub use self::construct::*;
 pub use self::data::*;
@@ -27,6 +28,7 @@ pub(super) fn define(global: &mut Scope) {
     global.define("datetime", datetime_func());
     global.define("symbol", symbol_func());
     global.define("str", str_func());
+    global.define("bytes", bytes_func());
     global.define("label", label_func());
     global.define("regex", regex_func());
     global.define("range", range_func());
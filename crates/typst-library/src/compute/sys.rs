@@ -0,0 +1,21 @@
+//! System-related things.
+
+use typst::eval::{Module, Scope};
+
+use crate::prelude::*;
+
+/// A module with system-related things.
+///
+/// The `inputs` are key-value pairs that were passed to the compiler, for
+/// instance with `--input key=value` on the command line. They allow one
+/// template to produce different documents without changing its source.
+///
+/// ```example
+/// #let draft = sys.inputs.at("draft", default: "false") == "true"
+/// #if draft [*Draft*]
+/// ```
+pub fn module(inputs: Dict) -> Module {
+    let mut scope = Scope::new();
+    scope.define("inputs", inputs);
+    Module::new("sys").with_scope(scope)
+}
@@ -0,0 +1,604 @@
+//! Exporting the content tree into HTML.
+//!
+//! Other than the PDF, PNG and SVG exporters, which work on the laid out
+//! frames, the HTML exporter works on the realized content of a document. This
+//! keeps the semantic structure (headings, paragraphs, lists, ...) intact so
+//! that browsers can reflow the text. Elements that have no HTML equivalent are
+//! laid out and embedded as inline SVG.
+
+use std::fmt::Write;
+use std::mem;
+
+use base64::Engine;
+use typst::eval::{eval, Route, Tracer};
+use typst::image::{ImageFormat, RasterFormat, VectorFormat};
+use typst::model::{realize, DelayedErrors, Guard};
+use typst::util::hash128;
+
+use crate::layout::{
+    BlockElem, BoxElem, ColumnsElem, EnumElem, EnumItem, ListElem, ListItem, PadElem,
+    PageElem, ParbreakElem, TableElem, TermItem, TermsElem,
+};
+use crate::math::{EquationElem, LayoutMath};
+use crate::meta::{Counter, FigureElem, FootnoteElem, HeadingElem};
+use crate::prelude::*;
+use crate::text::{
+    EmphElem, LinebreakElem, Quoter, Quotes, RawElem, SmartQuoteElem, SpaceElem,
+    StrongElem, TextElem,
+};
+use crate::visualize::ImageElem;
+
+/// Export the main source file of a world into an HTML document.
+///
+/// The `document` must be the result of compiling the same world with the
+/// same `tracer`. It provides the introspection data (counters, references,
+/// footnote numbers) the content is realized with.
+#[tracing::instrument(skip_all)]
+pub fn html(
+    world: &dyn World,
+    tracer: &mut Tracer,
+    document: &Document,
+) -> SourceResult<String> {
+    let route = Route::default();
+
+    // Call `track` just once to keep comemo's ID stable.
+    let world = world.track();
+    let mut tracer = tracer.track_mut();
+
+    // Evaluate the source file into a module. This is cached from the
+    // preceding compilation, whose warnings and writes are thereby replayed
+    // into the same tracer instead of getting lost.
+    let module =
+        eval(world, route.track(), TrackedMut::reborrow_mut(&mut tracer), &world.main())?;
+
+    let introspector = Introspector::new(&document.pages);
+    let mut locator = Locator::new();
+    let mut delayed = DelayedErrors::default();
+    let mut vt = Vt {
+        world,
+        tracer,
+        locator: &mut locator,
+        introspector: introspector.track(),
+        delayed: delayed.track_mut(),
+    };
+
+    let library = world.library();
+    let styles = StyleChain::new(&library.styles);
+    let width = document.pages.first().map_or(Abs::pt(595.0), Frame::width);
+
+    let mut writer = HtmlWriter::new(&mut vt, width);
+    writer.accept(&module.content(), styles)?;
+    writer.flush();
+    let body = writer.finish();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    if let Some(title) = &document.title {
+        writeln!(html, "<title>{}</title>", escape(title)).unwrap();
+    }
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&body);
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Writes realized content as HTML.
+struct HtmlWriter<'a, 'v> {
+    /// The virtual typesetter.
+    vt: &'a mut Vt<'v>,
+    /// The width available for content that is embedded as SVG.
+    width: Abs,
+    /// The finished block-level HTML.
+    html: String,
+    /// The inline HTML of the current paragraph.
+    par: String,
+    /// Whether paragraphs should be wrapped in `<p>` tags. This is disabled
+    /// within elements like headings and list items.
+    wrap: bool,
+    /// The rendered footnotes, which are appended to the end of the document.
+    footnotes: Vec<(EcoString, String)>,
+    /// The tag of the list that is currently open. Markup lists are not
+    /// collected into list elements before realization, so consecutive items
+    /// are grouped here.
+    list: Option<&'static str>,
+    /// Substitutes smart quotes within the current paragraph.
+    quoter: Quoter,
+}
+
+impl<'a, 'v> HtmlWriter<'a, 'v> {
+    fn new(vt: &'a mut Vt<'v>, width: Abs) -> Self {
+        Self {
+            vt,
+            width,
+            html: String::new(),
+            par: String::new(),
+            wrap: true,
+            footnotes: vec![],
+            list: None,
+            quoter: Quoter::new(),
+        }
+    }
+
+    /// Finish the document and return the body's HTML.
+    fn finish(mut self) -> String {
+        self.close_list();
+        if !self.footnotes.is_empty() {
+            self.html.push_str("<section class=\"footnotes\">\n<ol>\n");
+            for (id, note) in mem::take(&mut self.footnotes) {
+                writeln!(self.html, "<li id=\"{id}\">{note}</li>").unwrap();
+            }
+            self.html.push_str("</ol>\n</section>\n");
+        }
+        self.html
+    }
+
+    /// Write content.
+    fn accept(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        if content.can::<dyn LayoutMath>() && !content.is::<EquationElem>() {
+            let equation = EquationElem::new(content.clone()).pack();
+            return self.accept(&equation, styles);
+        }
+
+        if let Some((elem, local)) = content.to_styled() {
+            return self.styled(elem, local, styles);
+        }
+
+        if let Some(children) = content.to_sequence() {
+            for elem in children {
+                self.accept(elem, styles)?;
+            }
+            return Ok(());
+        }
+
+        // Elements with a direct HTML equivalent are still subject to user
+        // show rules, but not to their built-in ones, which would destroy the
+        // semantic structure. They are only guarded once they are prepared
+        // because the guard is part of the hash that locates them, which must
+        // match the layout pass.
+        let mut target = content.clone();
+        let base = Guard::Base(target.func());
+        if is_semantic(&target) && !target.needs_preparation() && !target.is_guarded(base)
+        {
+            target = target.guarded(base);
+        }
+
+        if let Some(realized) = realize(self.vt, &target, styles)? {
+            return self.accept(&realized, styles);
+        }
+
+        self.elem(&target, styles)
+    }
+
+    /// Write styled content.
+    fn styled(
+        &mut self,
+        elem: &Content,
+        local: &Styles,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let chained = styles.chain(local);
+        let mut link = None;
+        for meta in MetaElem::data_in(StyleChain::new(local)) {
+            match meta {
+                Meta::Hide => return Ok(()),
+                Meta::Link(dest) => link = Some(dest),
+                _ => {}
+            }
+        }
+
+        let Some(dest) = link else {
+            return self.accept(elem, chained);
+        };
+
+        let href = match dest {
+            Destination::Url(url) => url.clone(),
+            Destination::Location(loc) => eco_format!("#{}", anchor(&loc)),
+            Destination::Position(pos) => eco_format!("#page-{}", pos.page),
+        };
+
+        write!(self.par, "<a href=\"{}\">", escape(&href)).unwrap();
+        self.accept(elem, chained)?;
+        self.par.push_str("</a>");
+        Ok(())
+    }
+
+    /// Write a realized element.
+    fn elem(&mut self, elem: &Content, styles: StyleChain) -> SourceResult<()> {
+        // Spaces and paragraph breaks may appear between the items of a list.
+        let item =
+            elem.is::<ListItem>() || elem.is::<EnumItem>() || elem.is::<TermItem>();
+        if !item && !elem.is::<SpaceElem>() && !elem.is::<ParbreakElem>() {
+            self.close_list();
+        }
+
+        if let Some(item) = elem.to::<ListItem>() {
+            self.open_list("ul");
+            self.block("li", None, &item.body(), styles, false)?;
+        } else if let Some(item) = elem.to::<EnumItem>() {
+            self.open_list("ol");
+            let value = item.number(styles).map(|n| eco_format!("value=\"{n}\""));
+            self.block("li", value, &item.body(), styles, false)?;
+        } else if let Some(item) = elem.to::<TermItem>() {
+            self.open_list("dl");
+            self.block("dt", None, &item.term(), styles, false)?;
+            self.block("dd", None, &item.description(), styles, false)?;
+        } else if self.list.is_some() {
+            // Nothing to do for spaces and breaks between items.
+        } else if let Some(text) = elem.to::<TextElem>() {
+            let text = text.text();
+            self.par.push_str(&escape(&text));
+            if let Some(c) = text.chars().last() {
+                self.quoter.last(c);
+            }
+        } else if elem.is::<SpaceElem>() {
+            self.par.push(' ');
+            self.quoter.last(' ');
+        } else if elem.is::<LinebreakElem>() {
+            self.par.push_str("<br>");
+            self.quoter.last('\n');
+        } else if let Some(quote) = elem.to::<SmartQuoteElem>() {
+            self.smart_quote(quote, styles);
+        } else if elem.is::<ParbreakElem>() {
+            self.flush();
+        } else if let Some(strong) = elem.to::<StrongElem>() {
+            self.inline("strong", &strong.body(), styles)?;
+        } else if let Some(emph) = elem.to::<EmphElem>() {
+            self.inline("em", &emph.body(), styles)?;
+        } else if let Some(raw) = elem.to::<RawElem>() {
+            self.raw(raw, styles);
+        } else if let Some(heading) = elem.to::<HeadingElem>() {
+            self.heading(heading, styles)?;
+        } else if let Some(list) = elem.to::<ListElem>() {
+            self.flush();
+            self.html.push_str("<ul>\n");
+            for item in list.children() {
+                self.block("li", None, &item.body(), styles, false)?;
+            }
+            self.html.push_str("</ul>\n");
+        } else if let Some(enum_) = elem.to::<EnumElem>() {
+            self.flush();
+            writeln!(self.html, "<ol start=\"{}\">", enum_.start(styles)).unwrap();
+            for item in enum_.children() {
+                let value = item.number(styles).map(|n| eco_format!("value=\"{n}\""));
+                self.block("li", value, &item.body(), styles, false)?;
+            }
+            self.html.push_str("</ol>\n");
+        } else if let Some(terms) = elem.to::<TermsElem>() {
+            self.flush();
+            self.html.push_str("<dl>\n");
+            for item in terms.children() {
+                self.block("dt", None, &item.term(), styles, false)?;
+                self.block("dd", None, &item.description(), styles, false)?;
+            }
+            self.html.push_str("</dl>\n");
+        } else if let Some(table) = elem.to::<TableElem>() {
+            self.table(table, styles)?;
+        } else if let Some(figure) = elem.to::<FigureElem>() {
+            self.figure(elem, figure, styles)?;
+        } else if let Some(footnote) = elem.to::<FootnoteElem>() {
+            self.footnote(footnote, styles)?;
+        } else if let Some(equation) = elem.to::<EquationElem>() {
+            self.equation(elem, equation, styles)?;
+        } else if let Some(image) = elem.to::<ImageElem>() {
+            self.image(image, styles);
+        } else if let Some(block) = elem.to::<BlockElem>() {
+            if let Some(body) = block.body(styles) {
+                self.flush();
+                self.accept(&body, styles)?;
+                self.flush();
+            }
+        } else if let Some(boxed) = elem.to::<BoxElem>() {
+            if let Some(body) = boxed.body(styles) {
+                self.accept(&body, styles)?;
+            }
+        } else if let Some(page) = elem.to::<PageElem>() {
+            self.accept(&page.body(), styles)?;
+        } else if let Some(pad) = elem.to::<PadElem>() {
+            self.accept(&pad.body(), styles)?;
+        } else if let Some(columns) = elem.to::<ColumnsElem>() {
+            self.accept(&columns.body(), styles)?;
+        } else if elem.can::<dyn Layout>() {
+            self.frame(elem, styles)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write inline content wrapped in a tag.
+    fn inline(
+        &mut self,
+        tag: &str,
+        body: &Content,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        write!(self.par, "<{tag}>").unwrap();
+        self.accept(body, styles)?;
+        write!(self.par, "</{tag}>").unwrap();
+        Ok(())
+    }
+
+    /// Write block-level content wrapped in a tag.
+    fn block(
+        &mut self,
+        tag: &str,
+        attrs: Option<EcoString>,
+        body: &Content,
+        styles: StyleChain,
+        wrap: bool,
+    ) -> SourceResult<()> {
+        self.flush();
+        match attrs {
+            Some(attrs) => write!(self.html, "<{tag} {attrs}>").unwrap(),
+            None => write!(self.html, "<{tag}>").unwrap(),
+        }
+
+        let prev = mem::replace(&mut self.wrap, wrap);
+        let list = self.list.take();
+        self.accept(body, styles)?;
+        self.close_list();
+        self.flush();
+        self.list = list;
+        self.wrap = prev;
+
+        writeln!(self.html, "</{tag}>").unwrap();
+        Ok(())
+    }
+
+    /// Write content into a separate buffer and return the resulting HTML.
+    fn capture(&mut self, body: &Content, styles: StyleChain) -> SourceResult<String> {
+        let html = mem::take(&mut self.html);
+        let par = mem::take(&mut self.par);
+        let prev = mem::replace(&mut self.wrap, false);
+        let list = self.list.take();
+        self.accept(body, styles)?;
+        self.close_list();
+        self.flush();
+        self.list = list;
+        self.wrap = prev;
+        self.par = par;
+        Ok(mem::replace(&mut self.html, html))
+    }
+
+    /// Open a list with the given tag unless it is already open.
+    fn open_list(&mut self, tag: &'static str) {
+        if self.list != Some(tag) {
+            self.close_list();
+            self.flush();
+            writeln!(self.html, "<{tag}>").unwrap();
+            self.list = Some(tag);
+        }
+    }
+
+    /// Close the currently open list, if any.
+    fn close_list(&mut self) {
+        if let Some(tag) = self.list.take() {
+            writeln!(self.html, "</{tag}>").unwrap();
+        }
+    }
+
+    /// Write a smart quote like it is laid out in a paragraph, in the quotes
+    /// of the text's language and region.
+    fn smart_quote(&mut self, quote: &SmartQuoteElem, styles: StyleChain) {
+        let double = quote.double(styles);
+        if !quote.enabled(styles) {
+            self.par.push_str(if double { "&quot;" } else { "'" });
+            self.quoter.last(if double { '"' } else { '\'' });
+            return;
+        }
+
+        // The following text isn't known yet, so the quote is assumed to be
+        // followed by a space.
+        let quotes = Quotes::from_lang(
+            TextElem::lang_in(styles),
+            TextElem::region_in(styles),
+            quote.alternative(styles),
+        );
+        let substituted = self.quoter.quote(&quotes, double, None);
+        self.par.push_str(&escape(substituted));
+        if let Some(c) = substituted.chars().last() {
+            self.quoter.last(c);
+        }
+    }
+
+    /// Finish the current paragraph.
+    fn flush(&mut self) {
+        self.quoter = Quoter::new();
+        let par = mem::take(&mut self.par);
+        let trimmed = par.trim();
+        if trimmed.is_empty() {
+            return;
+        }
+
+        if self.wrap {
+            writeln!(self.html, "<p>{trimmed}</p>").unwrap();
+        } else {
+            self.html.push_str(trimmed);
+        }
+    }
+
+    /// Write a heading.
+    fn heading(&mut self, heading: &HeadingElem, styles: StyleChain) -> SourceResult<()> {
+        let level = heading.level(styles).get().min(6);
+        let tag = eco_format!("h{level}");
+        let attrs =
+            heading.0.location().map(|loc| eco_format!("id=\"{}\"", anchor(&loc)));
+
+        let mut body = heading.body();
+        if let Some(numbering) = heading.numbering(styles) {
+            body = Counter::of(HeadingElem::func())
+                .display(Some(numbering), false)
+                .spanned(heading.span())
+                + SpaceElem::new().pack()
+                + body;
+        }
+
+        self.block(&tag, attrs, &body, styles, false)
+    }
+
+    /// Write raw text.
+    fn raw(&mut self, raw: &RawElem, styles: StyleChain) {
+        let text = escape(&raw.text());
+        let class = raw
+            .lang(styles)
+            .map(|lang| eco_format!(" class=\"language-{}\"", escape(&lang)))
+            .unwrap_or_default();
+
+        if raw.block(styles) {
+            self.flush();
+            writeln!(self.html, "<pre><code{class}>{text}</code></pre>").unwrap();
+        } else {
+            write!(self.par, "<code{class}>{text}</code>").unwrap();
+        }
+    }
+
+    /// Write a table, splitting its cells into rows.
+    fn table(&mut self, table: &TableElem, styles: StyleChain) -> SourceResult<()> {
+        self.flush();
+        let columns = table.columns(styles).0.len().max(1);
+        self.html.push_str("<table>\n");
+        for row in table.children().chunks(columns) {
+            self.html.push_str("<tr>");
+            for cell in row {
+                self.block("td", None, cell, styles, false)?;
+            }
+            self.html.push_str("</tr>\n");
+        }
+        self.html.push_str("</table>\n");
+        Ok(())
+    }
+
+    /// Write a figure with its caption.
+    fn figure(
+        &mut self,
+        elem: &Content,
+        figure: &FigureElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        self.flush();
+        match elem.location() {
+            Some(loc) => writeln!(self.html, "<figure id=\"{}\">", anchor(&loc)).unwrap(),
+            None => self.html.push_str("<figure>\n"),
+        }
+
+        let prev = mem::replace(&mut self.wrap, true);
+        self.accept(&figure.body(), styles)?;
+        self.flush();
+        self.wrap = prev;
+
+        if let Some(caption) = figure.full_caption(self.vt)? {
+            self.block("figcaption", None, &caption, styles, false)?;
+        }
+
+        self.html.push_str("</figure>\n");
+        Ok(())
+    }
+
+    /// Write a footnote marker and remember the footnote's body.
+    fn footnote(
+        &mut self,
+        footnote: &FootnoteElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let loc = footnote.declaration_location(self.vt).at(footnote.span())?;
+        let numbering = footnote.numbering(styles);
+        let counter = Counter::of(FootnoteElem::func());
+        let num = counter.at(self.vt, loc)?.display(self.vt, &numbering)?;
+        let number = self.capture(&num, styles)?;
+
+        let id = eco_format!("fn-{}", anchor(&loc));
+        write!(self.par, "<sup><a href=\"#{id}\">{number}</a></sup>").unwrap();
+
+        if let Some(body) = footnote.body_content() {
+            let note = self.capture(&body, styles)?;
+            self.footnotes.push((id, note));
+        }
+
+        Ok(())
+    }
+
+    /// Write an equation as inline SVG.
+    fn equation(
+        &mut self,
+        elem: &Content,
+        equation: &EquationElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let regions = Regions::one(Size::new(self.width, Abs::inf()), Axes::splat(false));
+        let frame = equation.layout(self.vt, styles, regions)?.into_frame();
+        let svg = typst::export::svg(&frame);
+
+        if equation.block(styles) {
+            self.flush();
+            match elem.location() {
+                Some(loc) => write!(self.html, "<div id=\"{}\"", anchor(&loc)).unwrap(),
+                None => self.html.push_str("<div"),
+            }
+            writeln!(self.html, " class=\"equation\">{svg}</div>").unwrap();
+        } else {
+            write!(self.par, "<span class=\"equation\">{svg}</span>").unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Write an image with its alternative text.
+    fn image(&mut self, image: &ImageElem, styles: StyleChain) {
+        let data = image.data();
+        let mime = match image.determine_format(styles) {
+            Some(ImageFormat::Raster(RasterFormat::Png)) => "image/png",
+            Some(ImageFormat::Raster(RasterFormat::Jpg)) => "image/jpeg",
+            Some(ImageFormat::Raster(RasterFormat::Gif)) => "image/gif",
+            Some(ImageFormat::Vector(VectorFormat::Svg)) => "image/svg+xml",
+            None => "application/octet-stream",
+        };
+
+        let data = base64::engine::general_purpose::STANDARD.encode(data);
+        write!(self.par, "<img src=\"data:{mime};base64,{data}\"").unwrap();
+        if let Some(alt) = image.alt(styles) {
+            write!(self.par, " alt=\"{}\"", escape(&alt)).unwrap();
+        }
+        self.par.push('>');
+    }
+
+    /// Lay out an element that has no HTML equivalent and embed it as SVG.
+    fn frame(&mut self, elem: &Content, styles: StyleChain) -> SourceResult<()> {
+        let Some(layoutable) = elem.with::<dyn Layout>() else { return Ok(()) };
+        let regions = Regions::one(Size::new(self.width, Abs::inf()), Axes::splat(false));
+        let frame = layoutable.layout(self.vt, styles, regions)?.into_frame();
+        self.flush();
+        writeln!(self.html, "<div class=\"frame\">{}</div>", typst::export::svg(&frame))
+            .unwrap();
+        Ok(())
+    }
+}
+
+/// Whether an element is written as a dedicated HTML element instead of
+/// being realized with its built-in show rule.
+fn is_semantic(elem: &Content) -> bool {
+    elem.is::<HeadingElem>()
+        || elem.is::<FigureElem>()
+        || elem.is::<FootnoteElem>()
+        || elem.is::<RawElem>()
+        || elem.is::<StrongElem>()
+        || elem.is::<EmphElem>()
+}
+
+/// The HTML id of the element at a location.
+fn anchor(loc: &Location) -> EcoString {
+    eco_format!("loc-{:x}", hash128(loc) as u64)
+}
+
+/// Escape text for use in HTML content and attributes.
+fn escape(text: &str) -> EcoString {
+    let mut escaped = EcoString::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -251,7 +251,8 @@ impl Layout for EnumElem {
             cells.push(Content::empty());
             cells.push(resolved);
             cells.push(Content::empty());
-            cells.push(item.body().styled(Self::set_parents(Parent(number))));
+            let body = item.body().styled(Self::set_parents(Parent(number)));
+            cells.push(body.tagged(vt, Role::ListItem, &item.0));
             number = number.saturating_add(1);
         }
 
@@ -268,7 +269,15 @@ impl Layout for EnumElem {
             styles,
         );
 
-        Ok(layouter.layout(vt)?.fragment)
+        // Tag the frames with the list. The markers, which are not part of any
+        // item, thereby become artifacts.
+        let tag = structure_tag(vt, Role::List, &self.0);
+        let mut fragment = layouter.layout(vt)?.fragment;
+        for frame in fragment.iter_mut() {
+            frame.meta_iter([tag.clone()]);
+        }
+
+        Ok(fragment)
     }
 }
 
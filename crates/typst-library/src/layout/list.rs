@@ -147,7 +147,8 @@ impl Layout for ListElem {
             cells.push(Content::empty());
             cells.push(marker.clone());
             cells.push(Content::empty());
-            cells.push(item.body().styled(Self::set_depth(Depth)));
+            let body = item.body().styled(Self::set_depth(Depth));
+            cells.push(body.tagged(vt, Role::ListItem, &item.0));
         }
 
         let layouter = GridLayouter::new(
@@ -163,7 +164,15 @@ impl Layout for ListElem {
             styles,
         );
 
-        Ok(layouter.layout(vt)?.fragment)
+        // Tag the frames with the list. The markers, which are not part of any
+        // item, thereby become artifacts.
+        let tag = structure_tag(vt, Role::List, &self.0);
+        let mut fragment = layouter.layout(vt)?.fragment;
+        for frame in fragment.iter_mut() {
+            frame.meta_iter([tag.clone()]);
+        }
+
+        Ok(fragment)
     }
 }
 
@@ -180,7 +180,16 @@ impl ParElem {
             let lines = linebreak(&vt, &p, region.x - p.hang);
 
             // Stack the lines into one frame per region.
-            finalize(&mut vt, &p, &lines, region, expand)
+            let mut fragment = finalize(&mut vt, &p, &lines, region, expand)?;
+
+            // Tag the frames with the paragraph so that exporters can recover
+            // the document's structure.
+            let tag = structure_tag(&mut vt, Role::Paragraph, &par.0);
+            for frame in fragment.iter_mut() {
+                frame.meta_iter([tag.clone()]);
+            }
+
+            Ok(fragment)
         }
 
         let fragment = cached(
@@ -145,10 +145,14 @@ impl Layout for TableElem {
             .into_iter()
             .enumerate()
             .map(|(i, child)| {
-                let mut child = child.padded(Sides::splat(inset));
-
                 let x = i % cols;
                 let y = i / cols;
+                let mut child = child.clone().padded(Sides::splat(inset)).tagged(
+                    vt,
+                    Role::TableCell(y),
+                    &child,
+                );
+
                 if let Smart::Custom(alignment) = align.resolve(vt, x, y)? {
                     child = child.styled(AlignElem::set_alignment(alignment));
                 }
@@ -221,6 +225,13 @@ impl Layout for TableElem {
             }
         }
 
+        // Tag the frames with the table. Its lines and backgrounds, which are
+        // not part of any cell, thereby become artifacts.
+        let tag = structure_tag(vt, Role::Table, &self.0);
+        for frame in layout.fragment.iter_mut() {
+            frame.meta_iter([tag.clone()]);
+        }
+
         Ok(layout.fragment)
     }
 }
@@ -126,13 +126,13 @@ impl Layout for TermsElem {
             if !indent.is_zero() {
                 seq.push(HElem::new(indent.into()).pack());
             }
-            seq.push(child.term().strong());
-            seq.push(separator.clone());
-            seq.push(child.description());
+            let body = child.term().strong() + separator.clone() + child.description();
+            seq.push(body.tagged(vt, Role::ListItem, &child.0));
         }
 
         Content::sequence(seq)
             .styled(ParElem::set_hanging_indent(hanging_indent + indent))
+            .tagged(vt, Role::List, &self.0)
             .layout(vt, styles, regions)
     }
 }
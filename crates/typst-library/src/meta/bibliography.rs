@@ -63,7 +63,10 @@ pub struct BibliographyElem {
             .collect::<SourceResult<Vec<Bytes>>>()?;
 
         // Check that parsing works.
-        let _ = load(&paths, &data).at(span)?;
+        let entries = load(&paths, &data).at(span)?;
+        if entries.is_empty() {
+            vm.vt.tracer.warn(warning!(span, "bibliography contains no entries"));
+        }
 
         paths
     )]
@@ -302,6 +302,7 @@ impl Show for FigureElem {
         realized = BlockElem::new()
             .with_body(Some(realized))
             .pack()
+            .tagged(vt, Role::Figure, &self.0)
             .aligned(Axes::with_x(Some(Align::Center.into())));
 
         // Wrap in a float.
@@ -146,7 +146,7 @@ impl Synthesize for HeadingElem {
 
 impl Show for HeadingElem {
     #[tracing::instrument(name = "HeadingElem::show", skip_all)]
-    fn show(&self, _: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
+    fn show(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
         let mut realized = self.body();
         if let Some(numbering) = self.numbering(styles) {
             realized = Counter::of(Self::func())
@@ -155,7 +155,11 @@ impl Show for HeadingElem {
                 + HElem::new(Em::new(0.3).into()).with_weak(true).pack()
                 + realized;
         }
-        Ok(BlockElem::new().with_body(Some(realized)).pack())
+        let role = Role::Heading(self.level(styles));
+        Ok(BlockElem::new()
+            .with_body(Some(realized))
+            .pack()
+            .tagged(vt, role, &self.0))
     }
 }
 
@@ -100,7 +100,9 @@ impl Show for LinkElem {
                 .unwrap_or(body),
         };
 
-        Ok(linked.styled(TextElem::set_hyphenate(Hyphenate(Smart::Custom(false)))))
+        Ok(linked
+            .tagged(vt, Role::Link, &self.0)
+            .styled(TextElem::set_hyphenate(Hyphenate(Smart::Custom(false)))))
     }
 }
 
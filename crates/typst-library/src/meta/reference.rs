@@ -152,9 +152,18 @@ impl Show for RefElem {
             let elem = vt.introspector.query_label(&self.target());
             let span = self.span();
 
+            // Like in synthesis, the bibliography takes precedence.
             if BibliographyElem::has(vt, &target.0) {
                 if elem.is_ok() {
-                    bail!(span, "label occurs in the document and its bibliography");
+                    vt.tracer.warn(
+                        warning!(
+                            span,
+                            "label occurs in the document and its bibliography"
+                        )
+                        .with_hints([
+                            "the reference cites the bibliography entry".into(),
+                        ]),
+                    );
                 }
 
                 return Ok(self.to_citation(vt, styles)?.pack().spanned(span));
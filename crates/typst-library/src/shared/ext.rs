@@ -1,5 +1,7 @@
 //! Extension traits.
 
+use typst::util::hash128;
+
 use crate::layout::{AlignElem, MoveElem, PadElem};
 use crate::prelude::*;
 use crate::text::{EmphElem, FontFamily, FontList, StrongElem, TextElem, UnderlineElem};
@@ -23,6 +25,11 @@ pub trait ContentExt {
     /// Should be used in combination with [`Location::variant`].
     fn backlinked(self, loc: Location) -> Self;
 
+    /// Tag this content as an element of the document's logical structure,
+    /// so that exporters can recover which parts of the output belong to the
+    /// element. See [`structure_tag`].
+    fn tagged(self, vt: &mut Vt, role: Role, elem: &Content) -> Self;
+
     /// Set alignments for this content.
     fn aligned(self, aligns: Axes<Option<GenAlign>>) -> Self;
 
@@ -56,6 +63,10 @@ impl ContentExt for Content {
         self.styled(MetaElem::set_data(vec![Meta::Elem(backlink)]))
     }
 
+    fn tagged(self, vt: &mut Vt, role: Role, elem: &Content) -> Self {
+        self.styled(MetaElem::set_data(vec![structure_tag(vt, role, elem)]))
+    }
+
     fn aligned(self, aligns: Axes<Option<GenAlign>>) -> Self {
         self.styled(AlignElem::set_alignment(aligns))
     }
@@ -90,3 +101,14 @@ impl StylesExt for Styles {
         )));
     }
 }
+
+/// Create the metadata that ties output to an element of the document's
+/// logical structure.
+///
+/// The element is located right here, but this doesn't make it visible to
+/// introspection: the tag is only read by exporters and the locator replays
+/// it across memoized layouts.
+pub fn structure_tag(vt: &mut Vt, role: Role, elem: &Content) -> Meta {
+    let location = vt.locator.locate(hash128(&(role, elem)));
+    Meta::Tag(Tag { role, location })
+}
@@ -76,6 +76,22 @@ pub struct TextElem {
     /// هذا عربي.
     ///
     /// ```
+    #[parse({
+        let font_list: Option<Spanned<FontList>> = args.named("font")?;
+        if let Some(Spanned { v: FontList(families), span }) = &font_list {
+            let world = vm.world();
+            for family in families {
+                if world.book().select_family(family.as_str()).next().is_none() {
+                    vm.vt.tracer.warn(warning!(
+                        *span,
+                        "unknown font family: {}",
+                        family.as_str(),
+                    ));
+                }
+            }
+        }
+        font_list.map(|font_list| font_list.v)
+    })]
     #[default(FontList(vec![FontFamily::new("Linux Libertine")]))]
     pub font: FontList,
 
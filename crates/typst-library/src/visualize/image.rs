@@ -4,6 +4,7 @@ use std::path::Path;
 use typst::image::{Image, ImageFormat, RasterFormat, VectorFormat};
 use typst::util::Bytes;
 
+use crate::compute::Readable;
 use crate::meta::{Figurable, LocalName};
 use crate::prelude::*;
 use crate::text::families;
@@ -32,6 +33,10 @@ use crate::text::families;
 /// Display: Image
 /// Category: visualize
 #[element(Layout, LocalName, Figurable)]
+#[scope(
+    scope.define("decode", image_decode_func());
+    scope
+)]
 pub struct ImageElem {
     /// Path to an image file.
     #[required]
@@ -50,6 +55,10 @@ pub struct ImageElem {
     #[parse(data)]
     pub data: Bytes,
 
+    /// The image's format. Detected automatically by default, from the file
+    /// extension or, failing that, from the data itself.
+    pub format: Smart<ImageFormat>,
+
     /// The width of the image.
     pub width: Smart<Rel<Length>>,
 
@@ -72,22 +81,13 @@ impl Layout for ImageElem {
         styles: StyleChain,
         regions: Regions,
     ) -> SourceResult<Fragment> {
-        let ext = Path::new(self.path().as_str())
-            .extension()
-            .and_then(OsStr::to_str)
-            .unwrap_or_default()
-            .to_lowercase();
-
-        let format = match ext.as_str() {
-            "png" => ImageFormat::Raster(RasterFormat::Png),
-            "jpg" | "jpeg" => ImageFormat::Raster(RasterFormat::Jpg),
-            "gif" => ImageFormat::Raster(RasterFormat::Gif),
-            "svg" | "svgz" => ImageFormat::Vector(VectorFormat::Svg),
-            _ => bail!(self.span(), "unknown image format"),
+        let Some(format) = self.determine_format(styles) else {
+            bail!(self.span(), "unknown image format");
         };
 
+        let data = self.data();
         let image = Image::with_fonts(
-            self.data(),
+            data,
             format,
             vt.world,
             families(styles).next().as_ref().map(|f| f.as_str()),
@@ -153,6 +153,86 @@ impl Layout for ImageElem {
     }
 }
 
+impl ImageElem {
+    /// The image's format: The explicitly given one, or else the one its
+    /// file extension or data indicates.
+    pub fn determine_format(&self, styles: StyleChain) -> Option<ImageFormat> {
+        if let Smart::Custom(format) = self.format(styles) {
+            return Some(format);
+        }
+
+        let ext = Path::new(self.path().as_str())
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_lowercase();
+
+        Some(match ext.as_str() {
+            "png" => ImageFormat::Raster(RasterFormat::Png),
+            "jpg" | "jpeg" => ImageFormat::Raster(RasterFormat::Jpg),
+            "gif" => ImageFormat::Raster(RasterFormat::Gif),
+            "svg" | "svgz" => ImageFormat::Vector(VectorFormat::Svg),
+            _ => return ImageFormat::detect(&self.data()),
+        })
+    }
+}
+
+/// Decodes a raster or vector graphic from bytes or a string.
+///
+/// This is useful for images that are generated by the document itself or
+/// come from data instead of a file.
+///
+/// ## Example { #example }
+/// ```example
+/// #let svg = `<svg xmlns="http://www.w3.org/2000/svg" width="60" height="30">
+///   <rect width="60" height="30" rx="6" fill="teal" />
+/// </svg>`.text
+///
+/// #image.decode(svg)
+/// #image.decode(svg.replace("teal", "orange"), width: 40pt)
+/// ```
+///
+/// Display: Decode Image
+/// Category: visualize
+#[func]
+pub fn image_decode(
+    /// The data to decode as an image. Can be a string for SVGs.
+    data: Readable,
+    /// The image's format. Detected automatically by default.
+    #[named]
+    format: Option<Smart<ImageFormat>>,
+    /// The width of the image.
+    #[named]
+    width: Option<Smart<Rel<Length>>>,
+    /// The height of the image.
+    #[named]
+    height: Option<Smart<Rel<Length>>>,
+    /// A text describing the image.
+    #[named]
+    alt: Option<Option<EcoString>>,
+    /// How the image should adjust itself to a given area.
+    #[named]
+    fit: Option<ImageFit>,
+) -> Content {
+    let mut elem = ImageElem::new(EcoString::new(), data.into());
+    if let Some(format) = format {
+        elem.push_format(format);
+    }
+    if let Some(width) = width {
+        elem.push_width(width);
+    }
+    if let Some(height) = height {
+        elem.push_height(height);
+    }
+    if let Some(alt) = alt {
+        elem.push_alt(alt);
+    }
+    if let Some(fit) = fit {
+        elem.push_fit(fit);
+    }
+    elem.pack()
+}
+
 impl LocalName for ImageElem {
     fn local_name(&self, lang: Lang, _: Option<Region>) -> &'static str {
         match lang {
@@ -60,20 +60,40 @@ macro_rules! __error {
     };
 }
 
+/// Construct a [`SourceError`] with severity [`Warning`](Severity::Warning).
+///
+/// ```
+/// warning!(span, "warning with a {}", "source result");
+/// ```
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __warning {
+    ($span:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::diag::SourceError::warning(
+            $span,
+            $crate::diag::eco_format!($fmt, $($arg),*),
+        )
+    };
+}
+
 #[doc(inline)]
 pub use crate::__error as error;
+#[doc(inline)]
+pub use crate::__warning as warning;
 #[doc(hidden)]
 pub use ecow::{eco_format, EcoString};
 
 /// A result that can carry multiple source errors.
 pub type SourceResult<T> = Result<T, Box<Vec<SourceError>>>;
 
-/// An error in a source file.
+/// An error or warning in a source file.
 ///
 /// The contained spans will only be detached if any of the input source files
 /// were detached.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct SourceError {
+    /// Whether the diagnostic is an error or a warning.
+    pub severity: Severity,
     /// The span of the erroneous node in the source code.
     pub span: Span,
     /// A diagnostic message describing the problem.
@@ -89,6 +109,7 @@ impl SourceError {
     /// Create a new, bare error.
     pub fn new(span: Span, message: impl Into<EcoString>) -> Self {
         Self {
+            severity: Severity::Error,
             span,
             trace: vec![],
             message: message.into(),
@@ -96,6 +117,14 @@ impl SourceError {
         }
     }
 
+    /// Create a new, bare warning.
+    pub fn warning(span: Span, message: impl Into<EcoString>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(span, message)
+        }
+    }
+
     /// Adds user-facing hints to the error.
     pub fn with_hints(mut self, hints: impl IntoIterator<Item = EcoString>) -> Self {
         self.hints.extend(hints);
@@ -103,6 +132,16 @@ impl SourceError {
     }
 }
 
+/// The severity of a [`SourceError`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Severity {
+    /// A fatal error that prevents compilation.
+    Error,
+    /// A problem that does not prevent compilation, but likely leads to an
+    /// unintended result.
+    Warning,
+}
+
 /// A part of an error's [trace](SourceError::trace).
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Tracepoint {
@@ -244,8 +283,8 @@ pub type FileResult<T> = Result<T, FileError>;
 pub enum FileError {
     /// A file was not found at this path.
     NotFound(PathBuf),
-    /// A file could not be accessed.
-    AccessDenied,
+    /// A file could not be accessed, possibly because of the named rule.
+    AccessDenied(Option<EcoString>),
     /// A directory was found, but a file was expected.
     IsDirectory,
     /// The file is not a Typst source file, but should have been.
@@ -263,7 +302,7 @@ impl FileError {
     pub fn from_io(error: io::Error, path: &Path) -> Self {
         match error.kind() {
             io::ErrorKind::NotFound => Self::NotFound(path.into()),
-            io::ErrorKind::PermissionDenied => Self::AccessDenied,
+            io::ErrorKind::PermissionDenied => Self::AccessDenied(None),
             io::ErrorKind::InvalidData
                 if error.to_string().contains("stream did not contain valid UTF-8") =>
             {
@@ -282,7 +321,8 @@ impl Display for FileError {
             Self::NotFound(path) => {
                 write!(f, "file not found (searched at {})", path.display())
             }
-            Self::AccessDenied => f.pad("failed to load file (access denied)"),
+            Self::AccessDenied(None) => f.pad("failed to load file (access denied)"),
+            Self::AccessDenied(Some(rule)) => write!(f, "access denied ({rule})"),
             Self::IsDirectory => f.pad("failed to load file (is a directory)"),
             Self::NotSource => f.pad("not a typst source file"),
             Self::InvalidUtf8 => f.pad("file is not valid utf-8"),
@@ -328,6 +368,10 @@ pub enum PackageError {
     NetworkFailed,
     /// The package archive was malformed.
     MalformedArchive,
+    /// The package is not available locally and downloading it is disabled.
+    Offline(PackageSpec),
+    /// The package's contents do not match the hash recorded for it.
+    IntegrityMismatch(PackageSpec),
     /// Another error.
     Other,
 }
@@ -342,6 +386,12 @@ impl Display for PackageError {
             }
             Self::NetworkFailed => f.pad("failed to load package (network failed)"),
             Self::MalformedArchive => f.pad("failed to load package (archive malformed)"),
+            Self::Offline(spec) => {
+                write!(f, "package not available offline (searched for {spec})")
+            }
+            Self::IntegrityMismatch(spec) => {
+                write!(f, "package does not match its locked hash (searched for {spec})")
+            }
             Self::Other => f.pad("failed to load package"),
         }
     }
@@ -629,6 +629,10 @@ pub enum Meta {
     Elem(Content),
     /// The numbering of the current page.
     PageNumbering(Value),
+    /// Ties the content within the area this metadata is attached to to an
+    /// element of the document's logical structure. In contrast to `Elem`,
+    /// this is invisible to introspection and only read by exporters.
+    Tag(Tag),
     /// Indicates that content should be hidden. This variant doesn't appear
     /// in the final frames as it is removed alongside the content that should
     /// be hidden.
@@ -645,11 +649,42 @@ impl Debug for Meta {
             Self::Link(dest) => write!(f, "Link({dest:?})"),
             Self::Elem(content) => write!(f, "Elem({:?})", content.func()),
             Self::PageNumbering(value) => write!(f, "PageNumbering({value:?})"),
+            Self::Tag(tag) => write!(f, "Tag({:?})", tag.role),
             Self::Hide => f.pad("Hide"),
         }
     }
 }
 
+/// Marks content as part of an element of the document's logical structure.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Tag {
+    /// The role the element plays in the structure.
+    pub role: Role,
+    /// Identifies the element across all the frames it produces.
+    pub location: Location,
+}
+
+/// The role of an element in the logical structure of a document.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Role {
+    /// A heading of the given level.
+    Heading(NonZeroUsize),
+    /// A paragraph.
+    Paragraph,
+    /// A bullet, numbered or term list.
+    List,
+    /// An item of a list.
+    ListItem,
+    /// A table.
+    Table,
+    /// A cell in the given row of a table, starting at zero.
+    TableCell(usize),
+    /// A figure.
+    Figure,
+    /// A link.
+    Link,
+}
+
 /// A link destination.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Destination {
@@ -93,6 +93,29 @@ pub fn call(
             _ => return missing(),
         },
 
+        Value::Bytes(bytes) => match method {
+            "len" => bytes.len().into_value(),
+            "at" => {
+                let index = args.expect("index")?;
+                let default = args.named::<Value>("default")?;
+                match bytes.at(index) {
+                    Ok(byte) => byte.into_value(),
+                    Err(err) => default.ok_or(err).at(span)?,
+                }
+            }
+            "slice" => {
+                let start = args.expect("start")?;
+                let mut end = args.eat()?;
+                if end.is_none() {
+                    end = args.named("count")?.map(|c: i64| start + c);
+                }
+                bytes.slice(start, end).at(span)?.into_value()
+            }
+            "base64" => bytes.to_base64().into_value(),
+            "hex" => bytes.to_hex().into_value(),
+            _ => return missing(),
+        },
+
         Value::Content(content) => match method {
             "func" => content.func().into_value(),
             "has" => content.has(&args.expect::<EcoString>("field")?).into_value(),
@@ -392,6 +415,13 @@ pub fn methods_on(type_name: &str) -> &[(&'static str, bool)] {
             ("starts-with", true),
             ("trim", true),
         ],
+        "bytes" => &[
+            ("len", false),
+            ("at", true),
+            ("slice", true),
+            ("base64", false),
+            ("hex", false),
+        ],
         "content" => &[
             ("func", false),
             ("has", true),
@@ -65,7 +65,7 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use self::func::{CapturesVisitor, Closure};
 use crate::diag::{
-    bail, error, At, SourceError, SourceResult, StrResult, Trace, Tracepoint,
+    bail, error, warning, At, SourceError, SourceResult, StrResult, Trace, Tracepoint,
 };
 use crate::file::{FileId, PackageManifest, PackageSpec};
 use crate::model::{
@@ -74,6 +74,7 @@ use crate::model::{
 };
 use crate::syntax::ast::{self, AstNode};
 use crate::syntax::{parse_code, Source, Span, Spanned, SyntaxKind, SyntaxNode};
+use crate::util::{hash128, Bytes};
 use crate::World;
 
 const MAX_ITERATIONS: usize = 10_000;
@@ -127,11 +128,60 @@ pub fn eval(
         bail!(flow.forbidden());
     }
 
+    // Other modules may import items just to make them available to their
+    // importers, so only the main file's imports must be used.
+    if result.is_ok() && id == world.main().id() {
+        warn_unused_imports(&mut vm, source.root());
+    }
+
     // Assemble the module.
     let name = id.path().file_stem().unwrap_or_default().to_string_lossy();
     Ok(Module::new(name).with_scope(vm.scopes.top).with_content(result?))
 }
 
+/// Warn about the items of imports that are not used anywhere in the source.
+///
+/// This is a purely syntactical check: Any identifier with the same name
+/// counts as a use, even if it refers to something else.
+fn warn_unused_imports(vm: &mut Vm, root: &SyntaxNode) {
+    fn visit(
+        node: &SyntaxNode,
+        imported: &mut Vec<SyntaxNode>,
+        used: &mut HashSet<EcoString>,
+    ) {
+        match node.kind() {
+            SyntaxKind::ImportItems => {
+                imported.extend(
+                    node.children()
+                        .filter(|child| child.kind() == SyntaxKind::Ident)
+                        .cloned(),
+                );
+                return;
+            }
+            SyntaxKind::Ident | SyntaxKind::MathIdent => {
+                used.insert(node.text().clone());
+            }
+            _ => {}
+        }
+
+        for child in node.children() {
+            visit(child, imported, used);
+        }
+    }
+
+    let mut imported = vec![];
+    let mut used = HashSet::new();
+    visit(root, &mut imported, &mut used);
+
+    for item in imported {
+        if !used.contains(item.text()) {
+            vm.vt
+                .tracer
+                .warn(warning!(item.span(), "unused import: {}", item.text()));
+        }
+    }
+}
+
 /// Evaluate a string as code and return the resulting value.
 ///
 /// Everything in the output is associated with the given `span`.
@@ -319,11 +369,22 @@ impl<'a> Route<'a> {
     }
 }
 
-/// Traces which values existed for an expression at a span.
+/// Traces which values existed for an expression at a span and collects the
+/// warnings and file writes emitted during compilation.
 #[derive(Default, Clone)]
 pub struct Tracer {
     span: Option<Span>,
     values: Vec<Value>,
+    warnings: Vec<SourceError>,
+    /// Hashes of the collected warnings, to skip warnings that are emitted
+    /// again, e.g. in a later layout iteration.
+    warnings_set: HashSet<u128>,
+    /// Files that should be written once compilation is done, together with
+    /// the span of the call that requested the write.
+    writes: Vec<(FileId, Bytes, Span)>,
+    /// How many of the writes happened before layout. Only the writes of the
+    /// final layout iteration follow them.
+    layout_start: Option<usize>,
 }
 
 impl Tracer {
@@ -332,13 +393,56 @@ impl Tracer {
 
     /// Create a new tracer, possibly with a span under inspection.
     pub fn new(span: Option<Span>) -> Self {
-        Self { span, values: vec![] }
+        Self { span, ..Self::default() }
     }
 
     /// Get the traced values.
     pub fn finish(self) -> Vec<Value> {
         self.values
     }
+
+    /// Get the collected warnings.
+    pub fn warnings(&self) -> &[SourceError] {
+        &self.warnings
+    }
+
+    /// The files that were requested to be written and their data.
+    pub fn writes(&self) -> impl Iterator<Item = (FileId, &Bytes)> + '_ {
+        self.writes.iter().map(|(id, data, _)| (*id, data))
+    }
+
+    /// Check that no file was written with different data more than once.
+    pub fn check_writes(&self) -> SourceResult<()> {
+        let mut errors = vec![];
+        for (i, (id, _, span)) in self.writes.iter().enumerate() {
+            if self.writes[..i].iter().any(|(prev, _, _)| prev == id) {
+                errors.push(error!(*span, "conflicting writes to {id}").with_hints([
+                    "the file was already written to with different data".into(),
+                ]));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Box::new(errors));
+        }
+
+        Ok(())
+    }
+
+    /// Perform the collected writes.
+    ///
+    /// Writes are buffered during compilation because evaluation and layout
+    /// are memoized and may run several times. Only the writes of the final
+    /// layout iteration are performed. If different data was written to the
+    /// same file, nothing is written and an error is returned instead.
+    pub fn flush_writes(&self, world: &dyn World) -> SourceResult<()> {
+        self.check_writes()?;
+        for (id, data, span) in &self.writes {
+            world.write(*id, data).at(*span)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[comemo::track]
@@ -358,6 +462,40 @@ impl Tracer {
             self.values.push(v);
         }
     }
+
+    /// Emit a warning.
+    pub fn warn(&mut self, warning: SourceError) {
+        // Only the span and message identify a warning, not its trace.
+        let hash = hash128(&(&warning.span, &warning.message));
+        if self.warnings_set.insert(hash) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Discard the writes of the previous layout iteration, as their data may
+    /// stem from introspections that were not resolved yet.
+    ///
+    /// Writes that happened before layout are kept.
+    pub fn start_layout_iteration(&mut self) {
+        match self.layout_start {
+            Some(start) => self.writes.truncate(start),
+            None => self.layout_start = Some(self.writes.len()),
+        }
+    }
+
+    /// Request that data is written to a file after compilation.
+    ///
+    /// Writing the same data to the same file again, e.g. in a later layout
+    /// iteration, has no effect.
+    pub fn write(&mut self, id: FileId, data: Bytes, span: Span) {
+        let repeated = self
+            .writes
+            .iter()
+            .any(|(prev, prev_data, _)| *prev == id && *prev_data == data);
+        if !repeated {
+            self.writes.push((id, data, span));
+        }
+    }
 }
 
 /// Evaluate an expression.
@@ -31,6 +31,7 @@ pub fn join(lhs: Value, rhs: Value) -> StrResult<Value> {
         (Content(a), Str(b)) => Content(a + item!(text)(b.into())),
         (Str(a), Content(b)) => Content(item!(text)(a.into()) + b),
         (Symbol(a), Content(b)) => Content(item!(text)(a.get().into()) + b),
+        (Bytes(a), Bytes(b)) => Bytes(a + b),
         (Array(a), Array(b)) => Array(a + b),
         (Dict(a), Dict(b)) => Dict(a + b),
         (a, b) => mismatch!("cannot join {} with {}", a, b),
@@ -102,6 +103,7 @@ pub fn add(lhs: Value, rhs: Value) -> StrResult<Value> {
         (Str(a), Content(b)) => Content(item!(text)(a.into()) + b),
         (Symbol(a), Content(b)) => Content(item!(text)(a.get().into()) + b),
 
+        (Bytes(a), Bytes(b)) => Bytes(a + b),
         (Array(a), Array(b)) => Array(a + b),
         (Dict(a), Dict(b)) => Dict(a + b),
 
@@ -347,6 +349,7 @@ pub fn equal(lhs: &Value, rhs: &Value) -> bool {
         (Color(a), Color(b)) => a == b,
         (Symbol(a), Symbol(b)) => a == b,
         (Str(a), Str(b)) => a == b,
+        (Bytes(a), Bytes(b)) => a == b,
         (Label(a), Label(b)) => a == b,
         (Content(a), Content(b)) => a == b,
         (Array(a), Array(b)) => a == b,
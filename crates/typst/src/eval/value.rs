@@ -5,6 +5,7 @@ use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use ecow::eco_format;
+use serde::ser::{Error as _, Serialize, SerializeMap, SerializeSeq, Serializer};
 use siphasher::sip128::{Hasher128, SipHasher13};
 
 use super::{
@@ -247,6 +248,44 @@ impl Hash for Value {
     }
 }
 
+impl Serialize for Value {
+    /// Serialize the value into a data format like JSON or YAML.
+    ///
+    /// Values without a natural equivalent in these formats, like lengths, are
+    /// serialized as their code representation. Content is serialized through
+    /// its fields. Functions, arguments, modules and styles can't be serialized
+    /// and result in an error.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::None => serializer.serialize_none(),
+            Self::Bool(v) => serializer.serialize_bool(*v),
+            Self::Int(v) => serializer.serialize_i64(*v),
+            Self::Float(v) => serializer.serialize_f64(*v),
+            Self::Symbol(v) => serializer.serialize_char(v.get()),
+            Self::Str(v) => serializer.serialize_str(v),
+            Self::Content(v) => v.serialize(serializer),
+            Self::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v.iter() {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Dict(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v.iter() {
+                    map.serialize_entry(key.as_str(), value)?;
+                }
+                map.end()
+            }
+            Self::Styles(_) | Self::Func(_) | Self::Args(_) | Self::Module(_) => Err(
+                S::Error::custom(format_args!("cannot serialize {}", self.type_name())),
+            ),
+            other => serializer.serialize_str(&other.repr()),
+        }
+    }
+}
+
 /// A dynamic value.
 #[derive(Clone, Hash)]
 #[allow(clippy::derived_hash_with_manual_eq)]
@@ -2,6 +2,101 @@
 
 mod pdf;
 mod render;
+mod svg;
 
-pub use self::pdf::pdf;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+
+pub use self::pdf::{pdf, pdf_into, PdfOptions, PdfStandard};
 pub use self::render::render;
+pub use self::svg::svg;
+
+/// A selection of pages to export.
+///
+/// Each range is inclusive and one-indexed. A missing start or end means that
+/// the range extends to the first or last page.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PageRanges(Vec<RangeInclusive<Option<NonZeroUsize>>>);
+
+impl PageRanges {
+    /// Create a selection from a list of ranges.
+    pub fn new(ranges: Vec<RangeInclusive<Option<NonZeroUsize>>>) -> Self {
+        Self(ranges)
+    }
+
+    /// Create a range from optional one-indexed start and end page numbers.
+    ///
+    /// Panics if a page number is zero.
+    pub fn range(
+        start: Option<usize>,
+        end: Option<usize>,
+    ) -> RangeInclusive<Option<NonZeroUsize>> {
+        let page = |number: Option<usize>| {
+            number.map(|n| NonZeroUsize::new(n).expect("page numbers start at 1"))
+        };
+        page(start)..=page(end)
+    }
+
+    /// Whether the page with the given one-indexed number is selected.
+    pub fn includes_page(&self, page: NonZeroUsize) -> bool {
+        self.0.iter().any(|range| {
+            range.start().map_or(true, |start| start <= page)
+                && range.end().map_or(true, |end| page <= end)
+        })
+    }
+
+    /// Whether the page with the given zero-based index is selected.
+    pub fn includes_page_index(&self, index: usize) -> bool {
+        self.includes_page(NonZeroUsize::new(index + 1).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Select pages with ranges of optional start and end page numbers.
+    fn ranges(ranges: &[(Option<usize>, Option<usize>)]) -> PageRanges {
+        PageRanges::new(
+            ranges
+                .iter()
+                .map(|&(start, end)| PageRanges::range(start, end))
+                .collect(),
+        )
+    }
+
+    /// The zero-based indices of the selected pages among the first twenty.
+    fn selected(ranges: &PageRanges) -> Vec<usize> {
+        (0..20).filter(|&i| ranges.includes_page_index(i)).collect()
+    }
+
+    #[test]
+    fn test_page_ranges_includes_page_index() {
+        assert_eq!(selected(&ranges(&[(Some(3), Some(3))])), [2]);
+        assert_eq!(selected(&ranges(&[(Some(3), Some(5))])), [2, 3, 4]);
+        assert_eq!(selected(&ranges(&[(None, Some(2))])), [0, 1]);
+        assert_eq!(selected(&ranges(&[(Some(18), None)])), [17, 18, 19]);
+        assert_eq!(selected(&ranges(&[(None, None)])), (0..20).collect::<Vec<_>>());
+        assert!(selected(&ranges(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_page_ranges_open_end() {
+        // `10-` includes every page from the tenth on, however many there are.
+        let ranges = ranges(&[(Some(10), None)]);
+        assert!(!ranges.includes_page_index(8));
+        assert!(ranges.includes_page_index(9));
+        assert!(ranges.includes_page_index(10_000));
+    }
+
+    #[test]
+    fn test_page_ranges_overlapping() {
+        let overlapping =
+            ranges(&[(Some(1), Some(3)), (Some(2), Some(5)), (Some(4), Some(4))]);
+        assert_eq!(selected(&overlapping), [0, 1, 2, 3, 4]);
+
+        let unordered =
+            ranges(&[(Some(7), None), (Some(1), Some(1)), (Some(8), Some(9))]);
+        assert_eq!(selected(&unordered)[..3], [0, 6, 7]);
+    }
+}
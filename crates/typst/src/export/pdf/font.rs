@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::io;
 
 use ecow::{eco_format, EcoString};
 use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
@@ -8,7 +9,7 @@ use unicode_general_category::GeneralCategory;
 
 use super::{deflate, EmExt, PdfContext, RefExt};
 use crate::font::Font;
-use crate::util::{Bytes, SliceExt};
+use crate::util::{hash128, Bytes, SliceExt};
 
 const CMAP_NAME: Name = Name(b"Custom");
 const SYSTEM_INFO: SystemInfo = SystemInfo {
@@ -19,7 +20,7 @@ const SYSTEM_INFO: SystemInfo = SystemInfo {
 
 /// Embed all used fonts into the PDF.
 #[tracing::instrument(skip_all)]
-pub fn write_fonts(ctx: &mut PdfContext) {
+pub fn write_fonts(ctx: &mut PdfContext) -> io::Result<()> {
     for font in ctx.font_map.items() {
         let type0_ref = ctx.alloc.bump();
         let cid_ref = ctx.alloc.bump();
@@ -36,11 +37,13 @@ pub fn write_fonts(ctx: &mut PdfContext) {
             .find_name(name_id::POST_SCRIPT_NAME)
             .unwrap_or_else(|| "unknown".to_string());
 
-        let base_font = eco_format!("ABCDEF+{}", postscript_name);
+        let tag = subset_tag(font, glyph_set);
+        let base_font = eco_format!("{tag}+{postscript_name}");
         let base_font = Name(base_font.as_bytes());
 
         // Write the base font object referencing the CID font.
         ctx.writer
+            .object(type0_ref)
             .type0_font(type0_ref)
             .base_font(base_font)
             .encoding_predefined(Name(b"Identity-H"))
@@ -59,7 +62,7 @@ pub fn write_fonts(ctx: &mut PdfContext) {
         };
 
         // Write the CID font referencing the font descriptor.
-        let mut cid = ctx.writer.cid_font(cid_ref);
+        let mut cid = ctx.writer.object(cid_ref).cid_font(cid_ref);
         cid.subtype(subtype);
         cid.base_font(base_font);
         cid.system_info(SYSTEM_INFO);
@@ -115,7 +118,8 @@ pub fn write_fonts(ctx: &mut PdfContext) {
         let stem_v = 10.0 + 0.244 * (f32::from(ttf.weight().to_number()) - 50.0);
 
         // Write the font descriptor (contains metrics about the font).
-        let mut font_descriptor = ctx.writer.font_descriptor(descriptor_ref);
+        let mut font_descriptor =
+            ctx.writer.object(descriptor_ref).font_descriptor(descriptor_ref);
         font_descriptor
             .name(base_font)
             .flags(flags)
@@ -136,12 +140,12 @@ pub fn write_fonts(ctx: &mut PdfContext) {
         // Write the /ToUnicode character map, which maps glyph ids back to
         // unicode codepoints to enable copying out of the PDF.
         let cmap = create_cmap(ttf, glyph_set);
-        ctx.writer.cmap(cmap_ref, &cmap.finish());
+        ctx.writer.object(cmap_ref).cmap(cmap_ref, &cmap.finish());
 
         // Subset and write the font's bytes.
         let glyphs: Vec<_> = glyph_set.keys().copied().collect();
         let data = subset_font(font, &glyphs);
-        let mut stream = ctx.writer.stream(data_ref, &data);
+        let mut stream = ctx.writer.object(data_ref).stream(data_ref, &data);
         stream.filter(Filter::FlateDecode);
 
         if subtype == CidFontType::Type0 {
@@ -149,7 +153,26 @@ pub fn write_fonts(ctx: &mut PdfContext) {
         }
 
         stream.finish();
+        ctx.writer.flush()?;
     }
+
+    Ok(())
+}
+
+/// Produce a six letter tag that identifies the subset of a font.
+///
+/// The tag is derived from the font and the used glyphs, so that it is the
+/// same each time the same document is exported.
+fn subset_tag(font: &Font, glyph_set: &BTreeMap<u16, EcoString>) -> EcoString {
+    const LEN: usize = 6;
+    const BASE: u128 = 26;
+    let mut hash = hash128(&(font, glyph_set));
+    let mut letters = [b'A'; LEN];
+    for letter in letters.iter_mut() {
+        *letter = b'A' + (hash % BASE) as u8;
+        hash /= BASE;
+    }
+    std::str::from_utf8(&letters).unwrap().into()
 }
 
 /// Subset a font to the given glyphs.
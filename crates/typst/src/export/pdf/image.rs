@@ -1,7 +1,7 @@
-use std::io::Cursor;
+use std::io::{self, Cursor};
 
 use image::{DynamicImage, GenericImageView, Rgba};
-use pdf_writer::{Filter, Finish};
+use pdf_writer::{Filter, Finish, PdfWriter};
 
 use super::{deflate, PdfContext, RefExt};
 use crate::image::{DecodedImage, Image, RasterFormat};
@@ -9,7 +9,7 @@ use crate::util::Bytes;
 
 /// Embed all used images into the PDF.
 #[tracing::instrument(skip_all)]
-pub fn write_images(ctx: &mut PdfContext) {
+pub fn write_images(ctx: &mut PdfContext) -> io::Result<()> {
     for image in ctx.image_map.items() {
         let image_ref = ctx.alloc.bump();
         let icc_ref = ctx.alloc.bump();
@@ -21,10 +21,12 @@ pub fn write_images(ctx: &mut PdfContext) {
         // Add the primary image.
         // TODO: Error if image could not be encoded.
         match image.decoded().as_ref() {
-            DecodedImage::Raster(dynamic, icc, _) => {
+            DecodedImage::Raster(dynamic, icc, format) => {
                 // TODO: Error if image could not be encoded.
-                let (data, filter, has_color) = encode_image(image);
-                let mut image = ctx.writer.image_xobject(image_ref, &data);
+                let (data, filter) = encode_image(image);
+                let has_color = has_color(dynamic, *format);
+                let mut image =
+                    ctx.writer.object(image_ref).image_xobject(image_ref, &data);
                 image.filter(filter);
                 image.width(width as i32);
                 image.height(height as i32);
@@ -47,7 +49,8 @@ pub fn write_images(ctx: &mut PdfContext) {
                     image.s_mask(mask_ref);
                     image.finish();
 
-                    let mut mask = ctx.writer.image_xobject(mask_ref, &alpha_data);
+                    let mut mask =
+                        ctx.writer.object(mask_ref).image_xobject(mask_ref, &alpha_data);
                     mask.filter(alpha_filter);
                     mask.width(width as i32);
                     mask.height(height as i32);
@@ -59,7 +62,8 @@ pub fn write_images(ctx: &mut PdfContext) {
 
                 if let Some(icc) = icc {
                     let compressed = deflate(&icc.0);
-                    let mut stream = ctx.writer.icc_profile(icc_ref, &compressed);
+                    let mut stream =
+                        ctx.writer.object(icc_ref).icc_profile(icc_ref, &compressed);
                     stream.filter(Filter::FlateDecode);
                     if has_color {
                         stream.n(3);
@@ -71,25 +75,40 @@ pub fn write_images(ctx: &mut PdfContext) {
                 }
             }
             DecodedImage::Svg(svg) => {
+                let mut chunk = PdfWriter::new();
                 let next_ref = svg2pdf::convert_tree_into(
                     svg,
                     svg2pdf::Options::default(),
-                    &mut ctx.writer,
+                    &mut chunk,
                     image_ref,
                 );
+                ctx.writer.append(chunk, image_ref.get()..next_ref.get())?;
                 ctx.alloc = next_ref;
             }
         }
+
+        ctx.writer.flush()?;
     }
+
+    Ok(())
+}
+
+/// Whether a raster image is embedded with color. Otherwise, it is embedded
+/// in gray-scale.
+pub fn has_color(dynamic: &DynamicImage, format: RasterFormat) -> bool {
+    !matches!(
+        (format, dynamic),
+        (RasterFormat::Jpg | RasterFormat::Png, DynamicImage::ImageLuma8(_))
+    )
 }
 
-/// Encode an image with a suitable filter and return the data, filter and
-/// whether the image has color.
+/// Encode an image with a suitable filter and return the data and filter.
 ///
-/// Skips the alpha channel as that's encoded separately.
+/// Skips the alpha channel as that's encoded separately. Whether the data has
+/// color is determined by [`has_color`].
 #[comemo::memoize]
 #[tracing::instrument(skip_all)]
-fn encode_image(image: &Image) -> (Bytes, Filter, bool) {
+fn encode_image(image: &Image) -> (Bytes, Filter) {
     let decoded = image.decoded();
     let (dynamic, format) = match decoded.as_ref() {
         DecodedImage::Raster(dynamic, _, format) => (dynamic, *format),
@@ -101,14 +120,14 @@ fn encode_image(image: &Image) -> (Bytes, Filter, bool) {
         (RasterFormat::Jpg, DynamicImage::ImageLuma8(_)) => {
             let mut data = Cursor::new(vec![]);
             dynamic.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
-            (data.into_inner().into(), Filter::DctDecode, false)
+            (data.into_inner().into(), Filter::DctDecode)
         }
 
         // 8-bit RGB JPEG (CMYK JPEGs get converted to RGB earlier).
         (RasterFormat::Jpg, DynamicImage::ImageRgb8(_)) => {
             let mut data = Cursor::new(vec![]);
             dynamic.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
-            (data.into_inner().into(), Filter::DctDecode, true)
+            (data.into_inner().into(), Filter::DctDecode)
         }
 
         // TODO: Encode flate streams with PNG-predictor?
@@ -116,7 +135,7 @@ fn encode_image(image: &Image) -> (Bytes, Filter, bool) {
         // 8-bit gray PNG.
         (RasterFormat::Png, DynamicImage::ImageLuma8(luma)) => {
             let data = deflate(luma.as_raw());
-            (data.into(), Filter::FlateDecode, false)
+            (data.into(), Filter::FlateDecode)
         }
 
         // Anything else (including Rgb(a) PNGs).
@@ -130,7 +149,7 @@ fn encode_image(image: &Image) -> (Bytes, Filter, bool) {
             }
 
             let data = deflate(&pixels);
-            (data.into(), Filter::FlateDecode, true)
+            (data.into(), Filter::FlateDecode)
         }
     }
 }
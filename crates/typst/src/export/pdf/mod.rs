@@ -4,35 +4,106 @@ mod font;
 mod image;
 mod outline;
 mod page;
+mod pdfa;
+mod structure;
+mod writer;
 
 use std::cmp::Eq;
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::io::{self, Write};
 
 use ecow::EcoString;
 use pdf_writer::types::Direction;
-use pdf_writer::{Finish, Name, PdfWriter, Ref, TextStr};
+use pdf_writer::{Finish, Name, Ref, TextStr};
 use xmp_writer::{LangId, RenditionClass, XmpWriter};
 
 use self::page::Page;
+use self::structure::StructTree;
+use self::writer::StreamingWriter;
+use crate::diag::SourceResult;
 use crate::doc::{Document, Lang};
+use crate::eval::Datetime;
+use crate::export::PageRanges;
 use crate::font::Font;
 use crate::geom::{Abs, Dir, Em};
 use crate::image::Image;
 use crate::model::Introspector;
+use crate::util::hash128;
 
 /// Export a document into a PDF file.
 ///
-/// Returns the raw bytes making up the PDF file.
+/// Returns the raw bytes making up the PDF file. Fails if the document uses
+/// features that the requested standard forbids.
 #[tracing::instrument(skip_all)]
-pub fn pdf(document: &Document) -> Vec<u8> {
-    let mut ctx = PdfContext::new(document);
-    page::construct_pages(&mut ctx, &document.pages);
-    font::write_fonts(&mut ctx);
-    image::write_images(&mut ctx);
-    page::write_page_tree(&mut ctx);
-    write_catalog(&mut ctx);
-    ctx.writer.finish()
+pub fn pdf(document: &Document, options: &PdfOptions) -> SourceResult<Vec<u8>> {
+    let mut buf = vec![];
+    pdf_into(document, options, &mut buf)?.expect("writing into a vector cannot fail");
+    Ok(buf)
+}
+
+/// Export a document into a PDF file that is written into the given output.
+///
+/// Each page, font and image is written into the output as soon as it is
+/// done, so that the file never has to be held in memory as a whole. For
+/// large documents, the output should be buffered.
+///
+/// The outer result reports violations of the requested standard, which are
+/// detected before anything is written. The inner one reports failures of the
+/// output.
+#[tracing::instrument(skip_all)]
+pub fn pdf_into(
+    document: &Document,
+    options: &PdfOptions,
+    mut output: impl Write,
+) -> SourceResult<io::Result<()>> {
+    if options.standard == PdfStandard::A2b {
+        pdfa::validate(document, options.page_ranges.as_ref())?;
+    }
+
+    let mut ctx = PdfContext::new(document, options, &mut output);
+    let result = write_document(&mut ctx, document, options);
+    Ok(result.and_then(|()| ctx.writer.finish()))
+}
+
+/// Write all objects of the document, flushing them into the output as they
+/// are done.
+fn write_document(
+    ctx: &mut PdfContext,
+    document: &Document,
+    options: &PdfOptions,
+) -> io::Result<()> {
+    page::construct_pages(ctx, &document.pages, options.page_ranges.as_ref())?;
+    font::write_fonts(ctx)?;
+    image::write_images(ctx)?;
+    page::write_page_tree(ctx);
+    write_catalog(ctx);
+    Ok(())
+}
+
+/// Settings for PDF export.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct PdfOptions {
+    /// The standard that the PDF must conform to.
+    pub standard: PdfStandard,
+    /// Which pages to export. `None` exports all pages.
+    pub page_ranges: Option<PageRanges>,
+    /// The creation date written into the document's metadata, in UTC.
+    ///
+    /// No date is written if this is `None`. Since nothing else in the PDF
+    /// depends on the time of export, the same document and options always
+    /// yield the same bytes.
+    pub timestamp: Option<Datetime>,
+}
+
+/// A standard that an exported PDF conforms to.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PdfStandard {
+    /// Plain PDF 1.7.
+    #[default]
+    V1_7,
+    /// PDF/A-2b, for long-term archival.
+    A2b,
 }
 
 /// Identifies the color space definitions.
@@ -42,15 +113,23 @@ const D65_GRAY: Name<'static> = Name(b"d65gray");
 /// Context for exporting a whole PDF document.
 pub struct PdfContext<'a> {
     document: &'a Document,
+    standard: PdfStandard,
+    timestamp: Option<Datetime>,
+    /// Identifies the file, derived from the document and the export options.
+    file_id: u128,
     introspector: Introspector,
-    writer: PdfWriter,
+    writer: StreamingWriter<'a>,
     pages: Vec<Page>,
+    /// The height of each page in the document, including the pages that
+    /// are not exported.
     page_heights: Vec<f32>,
     alloc: Ref,
     page_tree_ref: Ref,
     font_refs: Vec<Ref>,
     image_refs: Vec<Ref>,
-    page_refs: Vec<Ref>,
+    /// The reference of each page in the document. `None` for pages that are
+    /// not exported.
+    page_refs: Vec<Option<Ref>>,
     font_map: Remapper<Font>,
     image_map: Remapper<Image>,
     /// For each font a mapping from used glyphs to their text representation.
@@ -60,17 +139,28 @@ pub struct PdfContext<'a> {
     /// PDF's /ToUnicode map for glyphs that don't have an entry in the font's
     /// cmap. This is important for copy-paste and searching.
     glyph_sets: HashMap<Font, BTreeMap<u16, EcoString>>,
-    languages: HashMap<Lang, usize>,
+    /// How often each language is used. Ordered so that the languages are
+    /// always written in the same order.
+    languages: BTreeMap<Lang, usize>,
+    /// The logical structure of the document.
+    structure: StructTree,
 }
 
 impl<'a> PdfContext<'a> {
-    fn new(document: &'a Document) -> Self {
+    fn new(
+        document: &'a Document,
+        options: &PdfOptions,
+        output: &'a mut dyn Write,
+    ) -> Self {
         let mut alloc = Ref::new(1);
         let page_tree_ref = alloc.bump();
         Self {
             document,
+            standard: options.standard,
+            timestamp: options.timestamp,
+            file_id: hash128(&(document, options)),
             introspector: Introspector::new(&document.pages),
-            writer: PdfWriter::new(),
+            writer: StreamingWriter::new(output),
             pages: vec![],
             page_heights: vec![],
             alloc,
@@ -81,9 +171,18 @@ impl<'a> PdfContext<'a> {
             font_map: Remapper::new(),
             image_map: Remapper::new(),
             glyph_sets: HashMap::new(),
-            languages: HashMap::new(),
+            languages: BTreeMap::new(),
+            structure: StructTree::default(),
         }
     }
+
+    /// The reference and height of a page, given its index in the document.
+    ///
+    /// Returns `None` if the page is not exported.
+    fn page_target(&self, index: usize) -> Option<(Ref, f32)> {
+        let page_ref = self.page_refs.get(index).copied().flatten()?;
+        Some((page_ref, self.page_heights[index]))
+    }
 }
 
 /// Write the document catalog.
@@ -104,6 +203,9 @@ fn write_catalog(ctx: &mut PdfContext) {
     // Write the outline tree.
     let outline_root_id = outline::write_outline(ctx);
 
+    // Write the structure tree for accessibility.
+    let struct_tree_ref = structure::write_structure(ctx);
+
     // Write the document information.
     let mut info = ctx.writer.document_info(ctx.alloc.bump());
     let mut xmp = XmpWriter::new();
@@ -118,26 +220,62 @@ fn write_catalog(ctx: &mut PdfContext) {
         xmp.creator(authors.iter().map(|s| s.as_str()));
     }
     info.creator(TextStr("Typst"));
-    info.finish();
     xmp.creator_tool("Typst");
-    xmp.num_pages(ctx.document.pages.len() as u32);
+
+    if let Some(timestamp) = ctx.timestamp {
+        if let Some(date) = pdf_date(timestamp) {
+            info.creation_date(date);
+            info.modified_date(date);
+        }
+        if let Some(date) = xmp_date(timestamp) {
+            xmp.create_date(date);
+            xmp.modify_date(date);
+        }
+    }
+
+    info.finish();
+    xmp.num_pages(ctx.page_refs.iter().flatten().count() as u32);
     xmp.format("application/pdf");
     xmp.language(ctx.languages.keys().map(|lang| LangId(lang.as_str())));
     xmp.rendition_class(RenditionClass::Proof);
     xmp.pdf_version("1.7");
 
+    // Declare the PDF/A conformance level in the metadata.
+    if ctx.standard == PdfStandard::A2b {
+        xmp.pdfa_part(2);
+        xmp.pdfa_conformance("B");
+    }
+
     let xmp_buf = xmp.finish(None);
     let meta_ref = ctx.alloc.bump();
-    let mut meta_stream = ctx.writer.stream(meta_ref, xmp_buf.as_bytes());
+    let mut meta_stream =
+        ctx.writer.object(meta_ref).stream(meta_ref, xmp_buf.as_bytes());
     meta_stream.pair(Name(b"Type"), Name(b"Metadata"));
     meta_stream.pair(Name(b"Subtype"), Name(b"XML"));
     meta_stream.finish();
 
+    // PDF/A requires an output intent to interpret device colors.
+    let output_intents_ref = match ctx.standard {
+        PdfStandard::V1_7 => None,
+        PdfStandard::A2b => Some(pdfa::write_output_intents(ctx)),
+    };
+
+    // Identify the file. This is required by PDF/A and derived from the
+    // document so that exporting the same document yields the same file.
+    let file_id = ctx.file_id.to_be_bytes().to_vec();
+    ctx.writer.set_file_id((file_id.clone(), file_id));
+
     // Write the document catalog.
     let mut catalog = ctx.writer.catalog(ctx.alloc.bump());
     catalog.pages(ctx.page_tree_ref);
     catalog.viewer_preferences().direction(dir);
     catalog.pair(Name(b"Metadata"), meta_ref);
+    catalog.pair(Name(b"StructTreeRoot"), struct_tree_ref);
+    catalog.insert(Name(b"MarkInfo")).dict().pair(Name(b"Marked"), true);
+
+    if let Some(output_intents_ref) = output_intents_ref {
+        catalog.pair(Name(b"OutputIntents"), output_intents_ref);
+    }
 
     if let Some(outline_root_id) = outline_root_id {
         catalog.outlines(outline_root_id);
@@ -148,6 +286,42 @@ fn write_catalog(ctx: &mut PdfContext) {
     }
 }
 
+/// Convert a datetime into a PDF date in UTC.
+fn pdf_date(datetime: Datetime) -> Option<pdf_writer::Date> {
+    let year = datetime.year().filter(|&y| y >= 0)? as u16;
+    let mut date = pdf_writer::Date::new(year);
+    if let Some(month) = datetime.month() {
+        date = date.month(month);
+    }
+    if let Some(day) = datetime.day() {
+        date = date.day(day);
+    }
+    if let Some(hour) = datetime.hour() {
+        date = date.hour(hour);
+    }
+    if let Some(minute) = datetime.minute() {
+        date = date.minute(minute);
+    }
+    if let Some(second) = datetime.second() {
+        date = date.second(second);
+    }
+    Some(date.utc_offset_hour(0).utc_offset_minute(0))
+}
+
+/// Convert a datetime into an XMP date in UTC.
+fn xmp_date(datetime: Datetime) -> Option<xmp_writer::DateTime> {
+    let year = datetime.year().filter(|&y| y >= 0)? as u16;
+    Some(xmp_writer::DateTime {
+        year,
+        month: datetime.month(),
+        day: datetime.day(),
+        hour: datetime.hour(),
+        minute: datetime.minute(),
+        second: datetime.second(),
+        timezone: Some(xmp_writer::Timezone::Utc),
+    })
+}
+
 /// Compress data with the DEFLATE algorithm.
 #[tracing::instrument(skip_all)]
 fn deflate(data: &[u8]) -> Vec<u8> {
@@ -93,6 +93,7 @@ pub fn write_outline(ctx: &mut PdfContext) -> Option<Ref> {
     }
 
     ctx.writer
+        .object(root_id)
         .outline(root_id)
         .first(start_ref)
         .last(Ref::new(ctx.alloc.get() - 1))
@@ -140,7 +141,7 @@ fn write_outline_item(
     let id = ctx.alloc.bump();
     let next_ref = Ref::new(id.get() + node.len() as i32);
 
-    let mut outline = ctx.writer.outline_item(id);
+    let mut outline = ctx.writer.object(id).outline_item(id);
     outline.parent(parent_ref);
 
     if !is_last {
@@ -164,9 +165,9 @@ fn write_outline_item(
     let loc = node.element.location().unwrap();
     let pos = ctx.introspector.position(loc);
     let index = pos.page.get() - 1;
-    if let Some(&height) = ctx.page_heights.get(index) {
+    if let Some((page_ref, height)) = ctx.page_target(index) {
         let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
-        outline.dest().page(ctx.page_refs[index]).xyz(
+        outline.dest().page(page_ref).xyz(
             pos.point.x.to_f32(),
             height - y.to_f32(),
             None,
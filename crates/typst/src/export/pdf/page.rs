@@ -1,12 +1,17 @@
+use std::io;
+
 use ecow::eco_format;
 use pdf_writer::types::{
-    ActionType, AnnotationType, ColorSpaceOperand, LineCapStyle, LineJoinStyle,
+    ActionType, AnnotationFlags, AnnotationType, ColorSpaceOperand, LineCapStyle,
+    LineJoinStyle,
 };
 use pdf_writer::writers::ColorSpace;
 use pdf_writer::{Content, Filter, Finish, Name, Rect, Ref, Str};
 
+use super::structure::{StructTree, StructType};
 use super::{deflate, AbsExt, EmExt, PdfContext, RefExt, D65_GRAY, SRGB};
-use crate::doc::{Destination, Frame, FrameItem, GroupItem, Meta, TextItem};
+use crate::doc::{Destination, Frame, FrameItem, GroupItem, Meta, Tag, TextItem};
+use crate::export::PageRanges;
 use crate::font::Font;
 use crate::geom::{
     self, Abs, Color, Em, Geometry, LineCap, LineJoin, Numeric, Paint, Point, Ratio,
@@ -14,20 +19,34 @@ use crate::geom::{
 };
 use crate::image::Image;
 
-/// Construct page objects.
+/// Construct page objects for the pages that are in the given ranges.
 #[tracing::instrument(skip_all)]
-pub fn construct_pages(ctx: &mut PdfContext, frames: &[Frame]) {
-    for frame in frames {
-        construct_page(ctx, frame);
+pub fn construct_pages(
+    ctx: &mut PdfContext,
+    frames: &[Frame],
+    ranges: Option<&PageRanges>,
+) -> io::Result<()> {
+    for (i, frame) in frames.iter().enumerate() {
+        ctx.page_heights.push(frame.height().to_f32());
+        if ranges.map_or(true, |ranges| ranges.includes_page_index(i)) {
+            construct_page(ctx, frame)?;
+        } else {
+            ctx.page_refs.push(None);
+        }
     }
+
+    Ok(())
 }
 
 /// Construct a page object.
+///
+/// The page's content stream is written right away, so that it doesn't have
+/// to be kept around until the page tree is written.
 #[tracing::instrument(skip_all)]
-pub fn construct_page(ctx: &mut PdfContext, frame: &Frame) {
+pub fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> io::Result<()> {
     let page_ref = ctx.alloc.bump();
-    ctx.page_refs.push(page_ref);
-    ctx.page_heights.push(frame.height().to_f32());
+    ctx.page_refs.push(Some(page_ref));
+    ctx.structure.start_page(page_ref);
 
     let mut ctx = PageContext {
         parent: ctx,
@@ -37,6 +56,9 @@ pub fn construct_page(ctx: &mut PdfContext, frame: &Frame) {
         saves: vec![],
         bottom: 0.0,
         links: vec![],
+        tags: vec![],
+        tagged_links: vec![],
+        marked: None,
     };
 
     let size = frame.size();
@@ -54,28 +76,38 @@ pub fn construct_page(ctx: &mut PdfContext, frame: &Frame) {
 
     // Encode the page into the content stream.
     write_frame(&mut ctx, frame);
+    end_marked(&mut ctx);
+
+    let content_id = ctx.parent.alloc.bump();
+    let data = deflate(&ctx.content.finish());
+    let parent = ctx.parent;
+    parent
+        .writer
+        .object(content_id)
+        .stream(content_id, &data)
+        .filter(Filter::FlateDecode);
 
     let page = Page {
         size,
-        content: ctx.content,
+        content_id,
         id: ctx.page_ref,
         links: ctx.links,
     };
 
-    ctx.parent.pages.push(page);
+    parent.pages.push(page);
+    parent.writer.flush()
 }
 
 /// Write the page tree.
 #[tracing::instrument(skip_all)]
 pub fn write_page_tree(ctx: &mut PdfContext) {
-    for page in std::mem::take(&mut ctx.pages).into_iter() {
-        write_page(ctx, page);
+    for (i, page) in std::mem::take(&mut ctx.pages).into_iter().enumerate() {
+        write_page(ctx, i, page);
     }
 
-    let mut pages = ctx.writer.pages(ctx.page_tree_ref);
-    pages
-        .count(ctx.page_refs.len() as i32)
-        .kids(ctx.page_refs.iter().copied());
+    let mut pages = ctx.writer.object(ctx.page_tree_ref).pages(ctx.page_tree_ref);
+    let page_refs = ctx.page_refs.iter().flatten().copied();
+    pages.count(page_refs.clone().count() as i32).kids(page_refs);
 
     let mut resources = pages.resources();
     let mut spaces = resources.color_spaces();
@@ -104,22 +136,43 @@ pub fn write_page_tree(ctx: &mut PdfContext) {
 
 /// Write a page tree node.
 #[tracing::instrument(skip_all)]
-fn write_page(ctx: &mut PdfContext, page: Page) {
-    let content_id = ctx.alloc.bump();
+fn write_page(ctx: &mut PdfContext, i: usize, page: Page) {
+    let annotation_refs: Vec<Ref> = page.links.iter().map(|_| ctx.alloc.bump()).collect();
 
-    let mut page_writer = ctx.writer.page(page.id);
+    let mut page_writer = ctx.writer.object(page.id).page(page.id);
     page_writer.parent(ctx.page_tree_ref);
 
     let w = page.size.x.to_f32();
     let h = page.size.y.to_f32();
     page_writer.media_box(Rect::new(0.0, 0.0, w, h));
-    page_writer.contents(content_id);
+    page_writer.contents(page.content_id);
+
+    // Link the page's marked content to the structure tree.
+    page_writer.pair(Name(b"StructParents"), i as i32);
+    if !annotation_refs.is_empty() {
+        page_writer.pair(Name(b"Tabs"), Name(b"S"));
+        page_writer
+            .insert(Name(b"Annots"))
+            .array()
+            .items(annotation_refs.iter().copied());
+    }
+
+    page_writer.finish();
+
+    for ((dest, rect, elem), annotation_ref) in
+        page.links.into_iter().zip(annotation_refs)
+    {
+        let struct_parent =
+            elem.map(|elem| ctx.structure.annotate(elem, page.id, annotation_ref));
+
+        let mut annotation = ctx.writer.object(annotation_ref).annotation(annotation_ref);
+        if let Some(struct_parent) = struct_parent {
+            annotation.pair(Name(b"StructParent"), struct_parent);
+        }
 
-    let mut annotations = page_writer.annotations();
-    for (dest, rect) in page.links {
-        let mut annotation = annotations.push();
         annotation.subtype(AnnotationType::Link).rect(rect);
         annotation.border(0.0, 0.0, 0.0, None);
+        annotation.flags(AnnotationFlags::PRINT);
 
         let pos = match dest {
             Destination::Url(uri) => {
@@ -135,22 +188,15 @@ fn write_page(ctx: &mut PdfContext, page: Page) {
 
         let index = pos.page.get() - 1;
         let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
-        if let Some(&height) = ctx.page_heights.get(index) {
+        if let Some((page_ref, height)) = ctx.page_target(index) {
             annotation
                 .action()
                 .action_type(ActionType::GoTo)
                 .destination()
-                .page(ctx.page_refs[index])
+                .page(page_ref)
                 .xyz(pos.point.x.to_f32(), height - y.to_f32(), None);
         }
     }
-
-    annotations.finish();
-    page_writer.finish();
-
-    let data = page.content.finish();
-    let data = deflate(&data);
-    ctx.writer.stream(content_id, &data).filter(Filter::FlateDecode);
 }
 
 /// Data for an exported page.
@@ -159,10 +205,11 @@ pub struct Page {
     pub id: Ref,
     /// The page's dimensions.
     pub size: Size,
-    /// The page's content stream.
-    pub content: Content,
-    /// Links in the PDF coordinate system.
-    pub links: Vec<(Destination, Rect)>,
+    /// The indirect object id of the page's content stream, which is written
+    /// when the page is constructed.
+    pub content_id: Ref,
+    /// Links in the PDF coordinate system and their structure elements.
+    pub links: Vec<(Destination, Rect, Option<usize>)>,
 }
 
 /// An exporter for the contents of a single PDF page.
@@ -173,7 +220,15 @@ struct PageContext<'a, 'b> {
     state: State,
     saves: Vec<State>,
     bottom: f32,
-    links: Vec<(Destination, Rect)>,
+    links: Vec<(Destination, Rect, Option<usize>)>,
+    /// The tags of the elements that enclose the content that is currently
+    /// written, from the outermost to the innermost one.
+    tags: Vec<Tag>,
+    /// Indices of links that belong to the next piece of content.
+    tagged_links: Vec<usize>,
+    /// The open marked content sequence: its structure element, or `None` for
+    /// an artifact, and the number of saved states when it was opened.
+    marked: Option<(Option<usize>, usize)>,
 }
 
 /// A simulated graphics state used to deduplicate graphics state changes and
@@ -320,22 +375,186 @@ impl PageContext<'_, '_> {
 
 /// Encode a frame into the content stream.
 fn write_frame(ctx: &mut PageContext, frame: &Frame) {
+    // The elements of this frame and the areas they cover. Elements are tagged
+    // with the size of their content and enclose everything in that area,
+    // while zero-sized items just mark where an element starts.
+    let mut elems: Vec<(Tag, Point, Size)> = vec![];
+
     for &(pos, ref item) in frame.items() {
-        let x = pos.x.to_f32();
-        let y = pos.y.to_f32();
-        match item {
-            FrameItem::Group(group) => write_group(ctx, pos, group),
-            FrameItem::Text(text) => write_text(ctx, x, y, text),
-            FrameItem::Shape(shape, _) => write_shape(ctx, x, y, shape),
-            FrameItem::Image(image, size, _) => write_image(ctx, x, y, image, *size),
-            FrameItem::Meta(meta, size) => match meta {
+        if let FrameItem::Meta(meta, size) = item {
+            match meta {
                 Meta::Link(dest) => write_link(ctx, pos, dest, *size),
+                Meta::Tag(tag) if !size.is_zero() => elems.push((*tag, pos, *size)),
+                Meta::Tag(_) => {}
                 Meta::Elem(_) => {}
                 Meta::Hide => {}
                 Meta::PageNumbering(_) => {}
+            }
+            continue;
+        }
+
+        let outer = ctx.tags.len();
+        ctx.tags.extend(covering(&elems, pos));
+
+        let x = pos.x.to_f32();
+        let y = pos.y.to_f32();
+        match item {
+            FrameItem::Group(group) => {
+                enter_group(ctx, group);
+                write_group(ctx, pos, group);
+            }
+            FrameItem::Text(text) => {
+                mark_content(ctx, None);
+                write_text(ctx, x, y, text);
+            }
+            FrameItem::Shape(shape, _) => {
+                mark_content(ctx, None);
+                write_shape(ctx, x, y, shape);
+            }
+            FrameItem::Image(image, size, _) => {
+                mark_content(ctx, Some(image));
+                write_image(ctx, x, y, image, *size);
+            }
+            FrameItem::Meta(..) => unreachable!(),
+        }
+
+        ctx.tags.truncate(outer);
+    }
+}
+
+/// The tags of the elements that cover an item at the given position.
+fn covering<'a>(
+    elems: &'a [(Tag, Point, Size)],
+    pos: Point,
+) -> impl Iterator<Item = Tag> + 'a {
+    elems
+        .iter()
+        .filter(move |&&(_, origin, size)| {
+            let end = origin + size.to_point();
+            (origin.x..=end.x).contains(&pos.x) && (origin.y..=end.y).contains(&pos.y)
+        })
+        .map(|&(tag, _, _)| tag)
+}
+
+/// Tie the next piece of content to its structure element, or mark it as an
+/// artifact.
+fn mark_content(ctx: &mut PageContext, image: Option<&Image>) {
+    let links = std::mem::take(&mut ctx.tagged_links);
+    let structure = &mut ctx.parent.structure;
+
+    let mut elem = structure.resolve(&ctx.tags);
+    if let Some(image) = image {
+        elem = Some(structure.figure(elem, image.alt()));
+    }
+
+    if let Some(link) = elem.and_then(|elem| structure.ancestor(elem, StructType::Link)) {
+        for i in links {
+            ctx.links[i].2 = Some(link);
+        }
+    }
+
+    mark(ctx, elem);
+}
+
+/// Make sure that a marked content sequence for the given structure element,
+/// or for artifacts, is open.
+///
+/// The open sequence is continued if it belongs to the same element. Thus,
+/// there is one sequence per element unless the content of other elements or
+/// graphics states get in between.
+fn mark(ctx: &mut PageContext, elem: Option<usize>) {
+    if matches!(ctx.marked, Some((open, _)) if open == elem) {
+        return;
+    }
+
+    end_marked(ctx);
+    ctx.marked = Some((elem, ctx.saves.len()));
+
+    let Some(elem) = elem else {
+        ctx.content.begin_marked_content(Name(b"Artifact"));
+        return;
+    };
+
+    let structure = &mut ctx.parent.structure;
+    let mcid = structure.mark(elem);
+    let mut marked = ctx
+        .content
+        .begin_marked_content_with_properties(structure.kind(elem).name());
+    marked.properties().pair(Name(b"MCID"), mcid);
+    marked.finish();
+}
+
+/// Close the open marked content sequence, if any.
+fn end_marked(ctx: &mut PageContext) {
+    if ctx.marked.take().is_some() {
+        ctx.content.end_marked_content();
+    }
+}
+
+/// Prepare the marked content for a group that is about to be written.
+///
+/// Marked content sequences and graphics states must nest. A sequence that is
+/// open before the group can thus only continue into it if all of the group's
+/// content belongs to the same element. In that case, the sequence for that
+/// element is opened right away, so that it can also continue after the
+/// group. Otherwise, the sequence is closed and the group's content gets its
+/// own sequences, which are closed when the group ends.
+fn enter_group(ctx: &mut PageContext, group: &GroupItem) {
+    // We are within a group whose content all belongs to the open sequence.
+    if matches!(ctx.marked, Some((_, depth)) if depth < ctx.saves.len()) {
+        return;
+    }
+
+    let mut tags = ctx.tags.clone();
+    let mut first = None;
+    if !is_uniform(&group.frame, &mut tags, &mut first) {
+        end_marked(ctx);
+        return;
+    }
+
+    if let Some(first) = first {
+        let elem = ctx.parent.structure.resolve(&first);
+        mark(ctx, elem);
+    }
+}
+
+/// Whether all content in a frame belongs to the same structure element.
+///
+/// The tags of the first piece of content are stored in `first`. Images always
+/// make a frame non-uniform, as each of them may become its own figure.
+fn is_uniform(frame: &Frame, tags: &mut Vec<Tag>, first: &mut Option<Vec<Tag>>) -> bool {
+    let mut elems = vec![];
+    for &(pos, ref item) in frame.items() {
+        match item {
+            FrameItem::Meta(Meta::Tag(tag), size) if !size.is_zero() => {
+                elems.push((*tag, pos, *size));
+                continue;
+            }
+            FrameItem::Meta(..) => continue,
+            FrameItem::Image(..) => return false,
+            FrameItem::Group(_) | FrameItem::Text(_) | FrameItem::Shape(..) => {}
+        }
+
+        let outer = tags.len();
+        tags.extend(covering(&elems, pos));
+        let uniform = match item {
+            FrameItem::Group(group) => is_uniform(&group.frame, tags, first),
+            _ => match first {
+                Some(first) => StructTree::identify(first) == StructTree::identify(tags),
+                None => {
+                    *first = Some(tags.clone());
+                    true
+                }
             },
+        };
+        tags.truncate(outer);
+
+        if !uniform {
+            return false;
         }
     }
+
+    true
 }
 
 /// Encode a group into the content stream.
@@ -358,6 +577,12 @@ fn write_group(ctx: &mut PageContext, pos: Point, group: &GroupItem) {
     }
 
     write_frame(ctx, &group.frame);
+
+    // Sequences that were opened within the group end with it.
+    if matches!(ctx.marked, Some((_, depth)) if depth >= ctx.saves.len()) {
+        end_marked(ctx);
+    }
+
     ctx.restore_state();
 }
 
@@ -496,21 +721,7 @@ fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size)
     let h = size.y.to_f32();
     ctx.content.save_state();
     ctx.content.transform([w, 0.0, 0.0, -h, x, y + h]);
-
-    if let Some(alt) = image.alt() {
-        let mut image_span =
-            ctx.content.begin_marked_content_with_properties(Name(b"Span"));
-        let mut image_alt = image_span.properties();
-        image_alt.pair(Name(b"Alt"), pdf_writer::Str(alt.as_bytes()));
-        image_alt.finish();
-        image_span.finish();
-
-        ctx.content.x_object(Name(name.as_bytes()));
-        ctx.content.end_marked_content();
-    } else {
-        ctx.content.x_object(Name(name.as_bytes()));
-    }
-
+    ctx.content.x_object(Name(name.as_bytes()));
     ctx.content.restore_state();
 }
 
@@ -541,7 +752,8 @@ fn write_link(ctx: &mut PageContext, pos: Point, dest: &Destination, size: Size)
     let y2 = min_y.to_f32();
     let rect = Rect::new(x1, y1, x2, y2);
 
-    ctx.links.push((dest.clone(), rect));
+    ctx.tagged_links.push(ctx.links.len());
+    ctx.links.push((dest.clone(), rect, None));
 }
 
 impl From<&LineCap> for LineCapStyle {
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+
+use ecow::eco_format;
+use pdf_writer::{Filter, Finish, Name, Ref, Str, TextStr};
+use ttf_parser::Permissions;
+
+use super::image::has_color;
+use super::{deflate, AbsExt, PdfContext, RefExt};
+use crate::diag::{SourceError, SourceResult};
+use crate::doc::{Document, Frame, FrameItem, TextItem};
+use crate::export::PageRanges;
+use crate::font::Font;
+use crate::geom::{Color, Paint, Shape};
+use crate::image::{DecodedImage, Image};
+use crate::syntax::Span;
+
+/// The sRGB color profile that is embedded as the output intent.
+const SRGB_ICC: &[u8] = include_bytes!("../../../assets/sRGB-v2.icc");
+
+/// The smallest and largest page dimensions PDF/A permits, in points.
+const MIN_PAGE_SIZE: f32 = 3.0;
+const MAX_PAGE_SIZE: f32 = 14400.0;
+
+/// Check that a document can be exported in conformance with PDF/A-2b.
+///
+/// Only the pages in the given ranges are checked. Reports all violations at
+/// once so that they can be fixed in one go.
+///
+/// The checks cover page sizes, fonts, CMYK colors and the color profiles of
+/// raster images. SVG images are embedded as they are converted and their
+/// content is not checked.
+#[tracing::instrument(skip_all)]
+pub fn validate(document: &Document, ranges: Option<&PageRanges>) -> SourceResult<()> {
+    let mut validator = Validator { errors: vec![], fonts: HashSet::new() };
+
+    for (i, frame) in document.pages.iter().enumerate() {
+        if ranges.map_or(false, |ranges| !ranges.includes_page_index(i)) {
+            continue;
+        }
+
+        let size = frame.size();
+        let (w, h) = (size.x.to_f32(), size.y.to_f32());
+        if !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&w)
+            || !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&h)
+        {
+            validator.errors.push(
+                SourceError::new(
+                    first_span(frame),
+                    eco_format!("page {} is too small or too large for PDF/A", i + 1),
+                )
+                .with_hints([
+                    "PDF/A requires page dimensions between 3pt and 14400pt".into(),
+                ]),
+            );
+        }
+
+        validator.frame(frame);
+    }
+
+    if !validator.errors.is_empty() {
+        return Err(Box::new(validator.errors));
+    }
+
+    Ok(())
+}
+
+/// Collects PDF/A violations in the frames of a document.
+struct Validator {
+    errors: Vec<SourceError>,
+    /// Fonts that were already reported, to only report each font once.
+    fonts: HashSet<Font>,
+}
+
+impl Validator {
+    fn frame(&mut self, frame: &Frame) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame),
+                FrameItem::Text(text) => self.text(text),
+                FrameItem::Shape(shape, span) => self.shape(shape, *span),
+                FrameItem::Image(image, _, span) => self.image(image, *span),
+                FrameItem::Meta(_, _) => {}
+            }
+        }
+    }
+
+    fn shape(&mut self, shape: &Shape, span: Span) {
+        let stroke = shape.stroke.as_ref().map(|stroke| &stroke.paint);
+        for paint in shape.fill.iter().chain(stroke) {
+            self.paint(paint, span);
+        }
+    }
+
+    fn image(&mut self, image: &Image, span: Span) {
+        let DecodedImage::Raster(dynamic, Some(icc), format) = image.decoded().as_ref()
+        else {
+            return;
+        };
+
+        // The embedded profile must be one that PDF/A supports and it must
+        // fit the color components the image is written with.
+        let space: &[u8] = if has_color(dynamic, *format) { b"RGB " } else { b"GRAY" };
+        let supported = icc
+            .0
+            .get(..20)
+            .map_or(false, |header| header[8] <= 4 && &header[16..20] == space);
+        if !supported {
+            self.errors.push(
+                SourceError::new(
+                    span,
+                    "the color profile of this image is not supported by PDF/A",
+                )
+                .with_hints([
+                    "PDF/A requires ICC profiles up to version 4 that match the \
+                     colors of the image"
+                        .into(),
+                ]),
+            );
+        }
+    }
+
+    fn paint(&mut self, paint: &Paint, span: Span) {
+        // CMYK colors are written in a device color space, which PDF/A only
+        // permits if it matches the output intent. That one is RGB.
+        let Paint::Solid(color) = paint;
+        if matches!(color, Color::Cmyk(_)) {
+            self.errors.push(
+                SourceError::new(span, "PDF/A does not permit CMYK colors")
+                    .with_hints(["try using an RGB or gray color instead".into()]),
+            );
+        }
+    }
+
+    fn text(&mut self, text: &TextItem) {
+        let span = text.glyphs.first().map_or(Span::detached(), |g| g.span.0);
+        self.paint(&text.fill, span);
+
+        // PDF/A requires all fonts to be embedded, which the font's license
+        // must allow.
+        let restricted = text.font.ttf().permissions() == Some(Permissions::Restricted);
+        if restricted && self.fonts.insert(text.font.clone()) {
+            let family = &text.font.info().family;
+            self.errors.push(
+                SourceError::new(
+                    span,
+                    eco_format!("the license of font {family} does not allow embedding"),
+                )
+                .with_hints(["PDF/A requires all fonts to be embedded".into()]),
+            );
+        }
+
+        // PDF/A forbids references to the `.notdef` glyph, which is used
+        // when a font does not cover a character.
+        for glyph in &text.glyphs {
+            if glyph.id == 0 {
+                let c = &text.text[glyph.range()];
+                self.errors.push(
+                    SourceError::new(
+                        glyph.span.0,
+                        eco_format!(
+                            "the font {} has no glyph for {c:?}",
+                            text.font.info().family
+                        ),
+                    )
+                    .with_hints([
+                        "PDF/A forbids the use of the .notdef glyph".into(),
+                        "try using a font that covers this character".into(),
+                    ]),
+                );
+            }
+        }
+    }
+}
+
+/// The span of the first item in a frame that has one.
+fn first_span(frame: &Frame) -> Span {
+    frame
+        .items()
+        .map(|(_, item)| match item {
+            FrameItem::Group(group) => first_span(&group.frame),
+            FrameItem::Text(text) => {
+                text.glyphs.first().map_or(Span::detached(), |g| g.span.0)
+            }
+            FrameItem::Shape(_, span) => *span,
+            FrameItem::Image(_, _, span) => *span,
+            FrameItem::Meta(_, _) => Span::detached(),
+        })
+        .find(|span| !span.is_detached())
+        .unwrap_or_else(Span::detached)
+}
+
+/// Write the output intent that PDF/A requires for device-dependent colors.
+///
+/// Returns the reference to the array of output intents.
+#[tracing::instrument(skip_all)]
+pub fn write_output_intents(ctx: &mut PdfContext) -> Ref {
+    let profile_ref = ctx.alloc.bump();
+    let compressed = deflate(SRGB_ICC);
+    let mut profile =
+        ctx.writer.object(profile_ref).icc_profile(profile_ref, &compressed);
+    profile.filter(Filter::FlateDecode);
+    profile.n(3);
+    profile.finish();
+
+    let intents_ref = ctx.alloc.bump();
+    let mut intents = ctx.writer.object(intents_ref).indirect(intents_ref).array();
+    let mut intent = intents.push().dict();
+    intent.pair(Name(b"Type"), Name(b"OutputIntent"));
+    intent.pair(Name(b"S"), Name(b"GTS_PDFA1"));
+    intent.pair(Name(b"OutputConditionIdentifier"), Str(b"sRGB"));
+    intent.pair(Name(b"RegistryName"), Str(b"http://www.color.org"));
+    intent.pair(Name(b"Info"), TextStr("sRGB IEC61966-2.1"));
+    intent.pair(Name(b"DestOutputProfile"), profile_ref);
+    intent.finish();
+    intents.finish();
+
+    intents_ref
+}
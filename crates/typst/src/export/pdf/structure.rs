@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use ecow::EcoString;
+use pdf_writer::{Finish, Name, Ref, TextStr};
+
+use super::{PdfContext, RefExt};
+use crate::doc::{Role, Tag};
+use crate::model::Location;
+
+/// The logical structure of a document, which is written into the PDF's
+/// structure tree so that assistive technology can make sense of the pages.
+///
+/// The structure is recovered from the `Meta::Tag` items in the frames. Such
+/// an item covers the area of the frame it was attached to, and all content in
+/// that area, including nested frames, is part of the element. Enclosing
+/// elements come before the ones they contain.
+pub struct StructTree {
+    /// All structure elements. The first one is the document's root.
+    elems: Vec<StructElem>,
+    /// Maps from tag locations to their structure elements.
+    located: HashMap<Location, usize>,
+    /// For each page, its reference and the structure element of each marked
+    /// content sequence, indexed by MCID.
+    pages: Vec<(Ref, Vec<usize>)>,
+    /// The structure elements of link annotations.
+    annotations: Vec<usize>,
+}
+
+/// A node in the structure tree.
+struct StructElem {
+    /// The structure type.
+    kind: StructType,
+    /// The parent element. `None` for the root.
+    parent: Option<usize>,
+    /// The marked content, annotations and elements below this element.
+    kids: Vec<StructKid>,
+    /// An alternate description for figures.
+    alt: Option<EcoString>,
+    /// For tables, the row elements by their index in the table.
+    rows: Vec<(usize, usize)>,
+}
+
+/// A child of a structure element.
+enum StructKid {
+    /// Another structure element.
+    Elem(usize),
+    /// A marked content sequence on a page.
+    Marked(Ref, i32),
+    /// An annotation on a page.
+    Annotation(Ref, Ref),
+}
+
+/// The standard structure types we use.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StructType {
+    Document,
+    Heading(usize),
+    Paragraph,
+    List,
+    ListItem,
+    Table,
+    TableRow,
+    TableCell,
+    Figure,
+    Link,
+}
+
+impl StructType {
+    /// The structure type's name in the PDF.
+    pub fn name(self) -> Name<'static> {
+        Name(match self {
+            Self::Document => b"Document",
+            Self::Heading(1) => b"H1",
+            Self::Heading(2) => b"H2",
+            Self::Heading(3) => b"H3",
+            Self::Heading(4) => b"H4",
+            Self::Heading(5) => b"H5",
+            Self::Heading(_) => b"H6",
+            Self::Paragraph => b"P",
+            Self::List => b"L",
+            Self::ListItem => b"LI",
+            Self::Table => b"Table",
+            Self::TableRow => b"TR",
+            Self::TableCell => b"TD",
+            Self::Figure => b"Figure",
+            Self::Link => b"Link",
+        })
+    }
+}
+
+impl From<Role> for StructType {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Heading(level) => Self::Heading(level.get()),
+            Role::Paragraph => Self::Paragraph,
+            Role::List => Self::List,
+            Role::ListItem => Self::ListItem,
+            Role::Table => Self::Table,
+            Role::TableCell(_) => Self::TableCell,
+            Role::Figure => Self::Figure,
+            Role::Link => Self::Link,
+        }
+    }
+}
+
+impl Default for StructTree {
+    fn default() -> Self {
+        Self {
+            elems: vec![StructElem::new(StructType::Document, None)],
+            located: HashMap::new(),
+            pages: vec![],
+            annotations: vec![],
+        }
+    }
+}
+
+impl StructTree {
+    /// Start a new page.
+    pub fn start_page(&mut self, page_ref: Ref) {
+        self.pages.push((page_ref, vec![]));
+    }
+
+    /// Find the structure element that content belongs to, given the tags of
+    /// the elements it is part of, from the outermost to the innermost one.
+    ///
+    /// Returns `None` if the content is an artifact, e.g. a page header or the
+    /// marker of a list item.
+    pub fn resolve(&mut self, tags: &[Tag]) -> Option<usize> {
+        let mut parent = 0;
+        for tag in enclosing(tags) {
+            parent = match self.located.get(&tag.location) {
+                Some(&elem) => elem,
+                None => {
+                    let parent = match tag.role {
+                        Role::TableCell(y) => self.row(parent, y),
+                        _ => parent,
+                    };
+                    let elem = self.push(tag.role.into(), parent);
+                    self.located.insert(tag.location, elem);
+                    elem
+                }
+            };
+        }
+
+        // Content directly within a list or table, like the markers of list
+        // items or the lines of tables, is not part of the logical structure.
+        match self.elems[parent].kind {
+            StructType::Document
+            | StructType::List
+            | StructType::Table
+            | StructType::TableRow => None,
+            _ => Some(parent),
+        }
+    }
+
+    /// The location of the element that [`resolve`](Self::resolve) finds for
+    /// the given tags, without creating it. `None` stands for the root.
+    ///
+    /// Content with equal locations belongs to the same element.
+    pub fn identify(tags: &[Tag]) -> Option<Location> {
+        enclosing(tags).last().map(|tag| tag.location)
+    }
+
+    /// Find or create the figure element for an image within the given
+    /// element and give it an alternate description.
+    pub fn figure(&mut self, elem: Option<usize>, alt: Option<&str>) -> usize {
+        let elem = match elem {
+            Some(elem) if self.elems[elem].kind == StructType::Figure => elem,
+            _ => self.push(StructType::Figure, elem.unwrap_or(0)),
+        };
+
+        if let Some(alt) = alt {
+            self.elems[elem].alt.get_or_insert_with(|| alt.into());
+        }
+
+        elem
+    }
+
+    /// The structure type of an element.
+    pub fn kind(&self, elem: usize) -> StructType {
+        self.elems[elem].kind
+    }
+
+    /// Find the innermost element of a structure type that contains the given
+    /// one.
+    pub fn ancestor(&self, mut elem: usize, kind: StructType) -> Option<usize> {
+        loop {
+            if self.elems[elem].kind == kind {
+                return Some(elem);
+            }
+            elem = self.elems[elem].parent?;
+        }
+    }
+
+    /// Add a marked content sequence on the current page to an element.
+    ///
+    /// Returns the sequence's MCID.
+    pub fn mark(&mut self, elem: usize) -> i32 {
+        let (page_ref, marked) = self.pages.last_mut().expect("no page was started");
+        let mcid = marked.len() as i32;
+        marked.push(elem);
+        self.elems[elem].kids.push(StructKid::Marked(*page_ref, mcid));
+        mcid
+    }
+
+    /// Add a link annotation to an element.
+    ///
+    /// Returns the annotation's key in the parent tree.
+    pub fn annotate(&mut self, elem: usize, page_ref: Ref, annot_ref: Ref) -> i32 {
+        let key = self.pages.len() + self.annotations.len();
+        self.annotations.push(elem);
+        self.elems[elem].kids.push(StructKid::Annotation(page_ref, annot_ref));
+        key as i32
+    }
+
+    /// Add a new element below a parent.
+    fn push(&mut self, kind: StructType, parent: usize) -> usize {
+        let elem = self.elems.len();
+        self.elems.push(StructElem::new(kind, Some(parent)));
+        self.elems[parent].kids.push(StructKid::Elem(elem));
+        elem
+    }
+
+    /// Find or create the row element with the given index in a table.
+    fn row(&mut self, table: usize, y: usize) -> usize {
+        if self.elems[table].kind != StructType::Table {
+            return table;
+        }
+
+        if let Some(&(_, row)) = self.elems[table].rows.iter().find(|&&(i, _)| i == y) {
+            return row;
+        }
+
+        let row = self.push(StructType::TableRow, table);
+        self.elems[table].rows.push((y, row));
+        row
+    }
+}
+
+impl StructElem {
+    fn new(kind: StructType, parent: Option<usize>) -> Self {
+        Self {
+            kind,
+            parent,
+            kids: vec![],
+            alt: None,
+            rows: vec![],
+        }
+    }
+}
+
+/// The tags of the elements that content belongs to, without duplicates and
+/// redundant paragraphs.
+fn enclosing(tags: &[Tag]) -> Vec<Tag> {
+    // The same element may be tagged multiple times in one run.
+    let mut unique: Vec<Tag> = vec![];
+    for tag in tags {
+        if unique.iter().all(|other| other.location != tag.location) {
+            unique.push(*tag);
+        }
+    }
+
+    // List items and table cells are tagged within the paragraphs that contain
+    // them, but paragraphs belong into items and cells. Headings contain a
+    // paragraph that is redundant.
+    let item = unique
+        .iter()
+        .rposition(|tag| matches!(tag.role, Role::ListItem | Role::TableCell(_)));
+    let mut i = 0;
+    let mut prev = None;
+    unique.retain(|tag| {
+        let keep = tag.role != Role::Paragraph
+            || (item.map_or(true, |item| i > item)
+                && !matches!(prev, Some(Role::Heading(_))));
+        i += 1;
+        prev = Some(tag.role);
+        keep
+    });
+
+    unique
+}
+
+/// Write the structure tree.
+///
+/// Returns the reference to the structure tree root.
+#[tracing::instrument(skip_all)]
+pub fn write_structure(ctx: &mut PdfContext) -> Ref {
+    let root_ref = ctx.alloc.bump();
+    let parent_tree_ref = ctx.alloc.bump();
+    let refs: Vec<Ref> = ctx.structure.elems.iter().map(|_| ctx.alloc.bump()).collect();
+
+    for (elem, &elem_ref) in ctx.structure.elems.iter().zip(&refs) {
+        let mut dict = ctx.writer.object(elem_ref).indirect(elem_ref).dict();
+        dict.pair(Name(b"Type"), Name(b"StructElem"));
+        dict.pair(Name(b"S"), elem.kind.name());
+        dict.pair(Name(b"P"), elem.parent.map_or(root_ref, |parent| refs[parent]));
+        if let Some(alt) = &elem.alt {
+            dict.pair(Name(b"Alt"), TextStr(alt));
+        }
+
+        let mut kids = dict.insert(Name(b"K")).array();
+        for kid in &elem.kids {
+            match *kid {
+                StructKid::Elem(kid) => {
+                    kids.item(refs[kid]);
+                }
+                StructKid::Marked(page_ref, mcid) => {
+                    let mut marked = kids.push().dict();
+                    marked.pair(Name(b"Type"), Name(b"MCR"));
+                    marked.pair(Name(b"Pg"), page_ref);
+                    marked.pair(Name(b"MCID"), mcid);
+                }
+                StructKid::Annotation(page_ref, annot_ref) => {
+                    let mut object = kids.push().dict();
+                    object.pair(Name(b"Type"), Name(b"OBJR"));
+                    object.pair(Name(b"Pg"), page_ref);
+                    object.pair(Name(b"Obj"), annot_ref);
+                }
+            }
+        }
+    }
+
+    // The parent tree maps from the marked content of pages and from
+    // annotations back to their structure elements.
+    let mut parent_tree =
+        ctx.writer.object(parent_tree_ref).indirect(parent_tree_ref).dict();
+    let mut nums = parent_tree.insert(Name(b"Nums")).array();
+    for (i, (_, marked)) in ctx.structure.pages.iter().enumerate() {
+        nums.item(i as i32);
+        nums.push().array().items(marked.iter().map(|&elem| refs[elem]));
+    }
+
+    let offset = ctx.structure.pages.len();
+    for (i, &elem) in ctx.structure.annotations.iter().enumerate() {
+        nums.item((offset + i) as i32);
+        nums.item(refs[elem]);
+    }
+
+    nums.finish();
+    parent_tree.finish();
+
+    let next_key = offset + ctx.structure.annotations.len();
+    let mut root = ctx.writer.object(root_ref).indirect(root_ref).dict();
+    root.pair(Name(b"Type"), Name(b"StructTreeRoot"));
+    root.pair(Name(b"K"), refs[0]);
+    root.pair(Name(b"ParentTree"), parent_tree_ref);
+    root.pair(Name(b"ParentTreeNextKey"), next_key as i32);
+    root.finish();
+
+    root_ref
+}
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use pdf_writer::writers::{Catalog, DocumentInfo};
+use pdf_writer::{PdfWriter, Ref};
+
+/// Writes a PDF file into an [`io::Write`] piece by piece.
+///
+/// Objects are first written into an in-memory [`PdfWriter`], which is handed
+/// out by [`object`](Self::object) together with the object's offset in the
+/// output. Each [`flush`](Self::flush) moves the objects written so far into
+/// the output, so that only the objects of a single page, font or image are
+/// held in memory at once. The cross-reference table and trailer are written
+/// by [`finish`](Self::finish).
+pub struct StreamingWriter<'a> {
+    /// The objects that were not flushed yet.
+    pending: PdfWriter,
+    /// The length of the file header at the start of each pending writer.
+    header_len: usize,
+    /// Where the file is written to.
+    output: &'a mut dyn Write,
+    /// How many bytes were written into the output so far.
+    written: usize,
+    /// The offset of each flushed object in the output.
+    offsets: Vec<(Ref, usize)>,
+    /// The document catalog for the trailer.
+    catalog_id: Option<Ref>,
+    /// The document information dictionary for the trailer.
+    info_id: Option<Ref>,
+    /// The file identifier for the trailer.
+    file_id: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<'a> StreamingWriter<'a> {
+    /// Create a new writer that writes into the given output.
+    pub fn new(output: &'a mut dyn Write) -> Self {
+        Self {
+            pending: PdfWriter::new(),
+            header_len: PdfWriter::new().len(),
+            output,
+            written: 0,
+            offsets: vec![],
+            catalog_id: None,
+            info_id: None,
+            file_id: None,
+        }
+    }
+
+    /// Get the writer for the object with the given id.
+    ///
+    /// Remembers where the object starts in the output, so the object must be
+    /// the next thing written. Objects that are not written through this
+    /// method are missing from the cross-reference table.
+    pub fn object(&mut self, id: Ref) -> &mut PdfWriter {
+        // Only the first flush keeps the pending writer's file header.
+        let skip = if self.written == 0 { 0 } else { self.header_len };
+        self.offsets.push((id, self.written + self.pending.len() - skip));
+        &mut self.pending
+    }
+
+    /// Write objects that were produced by another library into the output.
+    ///
+    /// Some libraries, like the SVG converter, write a range of objects into
+    /// a [`PdfWriter`] of their own. The offsets of these objects are found
+    /// by looking for their headers, which start a line. Ids without an
+    /// object are left out of the cross-reference table.
+    pub fn append(&mut self, chunk: PdfWriter, ids: Range<i32>) -> io::Result<()> {
+        self.flush()?;
+
+        let end = chunk.len();
+        let bytes = chunk.finish();
+        let body = &bytes[self.header_len..end];
+
+        let mut seen = HashSet::new();
+        let mut offset = 0;
+        for line in body.split(|&b| b == b'\n') {
+            if let Some(id) = parse_header(line) {
+                if ids.contains(&id) && seen.insert(id) {
+                    self.offsets.push((Ref::new(id), self.written + offset));
+                }
+            }
+            offset += line.len() + 1;
+        }
+
+        self.output.write_all(body)?;
+        self.written += body.len();
+        Ok(())
+    }
+
+    /// Start writing the document catalog.
+    pub fn catalog(&mut self, id: Ref) -> Catalog<'_> {
+        self.catalog_id = Some(id);
+        self.object(id).catalog(id)
+    }
+
+    /// Start writing the document information dictionary.
+    pub fn document_info(&mut self, id: Ref) -> DocumentInfo<'_> {
+        self.info_id = Some(id);
+        self.object(id).document_info(id)
+    }
+
+    /// Set the file identifier for the document.
+    pub fn set_file_id(&mut self, id: (Vec<u8>, Vec<u8>)) {
+        self.file_id = Some(id);
+    }
+
+    /// Move all objects written so far into the output.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let end = self.pending.len();
+        let bytes = std::mem::replace(&mut self.pending, PdfWriter::new()).finish();
+
+        // The first flush also writes the file header. The pending writer's
+        // own cross-reference table and trailer are not needed.
+        let start = if self.written == 0 { 0 } else { self.header_len };
+        self.output.write_all(&bytes[start..end])?;
+        self.written += end - start;
+        Ok(())
+    }
+
+    /// Flush the remaining objects and write the cross-reference table and
+    /// trailer.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()?;
+        self.offsets.sort();
+
+        let xref_offset = self.written;
+        let len = 1 + self.offsets.last().map_or(0, |(id, _)| id.get());
+        let mut offsets = self.offsets.iter().peekable();
+        let mut entries = Vec::with_capacity(len as usize);
+        for id in 0..len {
+            match offsets.next_if(|(used, _)| used.get() == id) {
+                Some(&(_, offset)) => entries.push(Some(offset)),
+                None => entries.push(None),
+            }
+        }
+
+        let w = &mut self.output;
+        writeln!(w, "xref\n0 {len}")?;
+        for (id, entry) in entries.iter().enumerate() {
+            match entry {
+                Some(offset) => write!(w, "{offset:010} 00000 n\r\n")?,
+                None => {
+                    // Free entries form a linked list that ends at zero.
+                    let next = entries[id + 1..]
+                        .iter()
+                        .position(Option::is_none)
+                        .map_or(0, |i| id + 1 + i);
+                    write!(w, "{next:010} 65535 f\r\n")?;
+                }
+            }
+        }
+
+        writeln!(w, "trailer\n<<\n  /Size {len}")?;
+        if let Some(catalog_id) = self.catalog_id {
+            writeln!(w, "  /Root {} 0 R", catalog_id.get())?;
+        }
+        if let Some(info_id) = self.info_id {
+            writeln!(w, "  /Info {} 0 R", info_id.get())?;
+        }
+        if let Some((a, b)) = &self.file_id {
+            writeln!(w, "  /ID [<{}> <{}>]", hex(a), hex(b))?;
+        }
+        write!(w, ">>\nstartxref\n{xref_offset}\n%%EOF")?;
+        w.flush()
+    }
+}
+
+/// Parse the id of an object from its header line, e.g. `12 0 obj`.
+fn parse_header(line: &[u8]) -> Option<i32> {
+    let line = std::str::from_utf8(line).ok()?;
+    line.trim_end().strip_suffix(" 0 obj")?.parse().ok()
+}
+
+/// Encode bytes as uppercase hexadecimal digits.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_writer_offsets() {
+        let mut buf = vec![];
+        let mut writer = StreamingWriter::new(&mut buf);
+        writer.object(Ref::new(1)).stream(Ref::new(1), b"first");
+        writer.flush().unwrap();
+        writer.object(Ref::new(3)).stream(Ref::new(3), b"second");
+        writer.object(Ref::new(4)).indirect(Ref::new(4)).primitive(4);
+        writer.flush().unwrap();
+
+        // Data that looks like a header doesn't confuse appending.
+        let mut chunk = PdfWriter::new();
+        chunk.stream(Ref::new(5), b"5 0 obj");
+        chunk.indirect(Ref::new(6)).primitive(6);
+        writer.append(chunk, 5..7).unwrap();
+        writer.catalog(Ref::new(2));
+        writer.finish().unwrap();
+
+        let text = String::from_utf8_lossy(&buf);
+        assert!(text.starts_with("%PDF-1.7"));
+        assert_eq!(text.matches("%PDF").count(), 1);
+
+        let xref_offset: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .trim_end_matches("\n%%EOF")
+            .parse()
+            .unwrap();
+        let xref = &text[xref_offset..];
+        assert!(xref.starts_with("xref\n0 7\n"));
+
+        let entries: Vec<_> = xref.lines().skip(2).take(7).collect();
+        assert_eq!(entries[0], "0000000000 65535 f");
+        for (id, entry) in entries.iter().enumerate().skip(1) {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(entry.ends_with(" 00000 n"));
+            assert!(text[offset..].starts_with(&format!("{id} 0 obj")));
+        }
+    }
+}
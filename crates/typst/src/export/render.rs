@@ -66,6 +66,7 @@ fn render_frame(
                 Meta::Link(_) => {}
                 Meta::Elem(_) => {}
                 Meta::PageNumbering(_) => {}
+                Meta::Tag(_) => {}
                 Meta::Hide => {}
             },
         }
@@ -0,0 +1,631 @@
+//! Exporting into SVG images.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use base64::Engine;
+use ecow::{eco_format, EcoString};
+use ttf_parser::{GlyphId, OutlineBuilder};
+use xmlwriter::XmlWriter;
+
+use crate::doc::{Destination, Frame, FrameItem, GroupItem, Meta, TextItem};
+use crate::font::Font;
+use crate::geom::{
+    self, Abs, Color, Geometry, LineCap, LineJoin, Paint, PathItem, Shape, Size, Stroke,
+    Transform,
+};
+use crate::image::{Image, ImageFormat, RasterFormat, VectorFormat};
+use crate::util::hash128;
+
+/// Export a frame into an SVG image.
+///
+/// Returns the SVG source for the frame. Text is converted into glyph
+/// outlines that are defined once per document and referenced with `<use>`,
+/// so that the result does not depend on any fonts installed on the viewer's
+/// system.
+///
+/// All ids in the output are derived from hashes, so that multiple SVGs can be
+/// inlined into the same HTML page without conflicts.
+#[tracing::instrument(skip_all)]
+pub fn svg(frame: &Frame) -> String {
+    let mut renderer = SvgRenderer::new(hash128(frame) as u64);
+    renderer.write_header(frame.size());
+    renderer.render_frame(frame);
+    renderer.write_glyph_defs();
+    renderer.xml.end_document()
+}
+
+/// Renders frames into an SVG document.
+struct SvgRenderer {
+    /// The XML writer the document is written into.
+    xml: XmlWriter,
+    /// Glyph outlines that are referenced from text runs, keyed by font and
+    /// glyph id. The values are the SVG path data in font units.
+    glyphs: Deduplicator<(Font, u16), String>,
+    /// A hash of the rendered frame, which makes clip path ids unique.
+    salt: u64,
+    /// The number of clip paths that were defined so far.
+    clips: usize,
+}
+
+impl SvgRenderer {
+    /// Create a new renderer with an empty document.
+    fn new(salt: u64) -> Self {
+        Self {
+            xml: XmlWriter::new(xmlwriter::Options::default()),
+            glyphs: Deduplicator::new('g'),
+            salt,
+            clips: 0,
+        }
+    }
+
+    /// Open the root element for an image of the given size.
+    fn write_header(&mut self, size: Size) {
+        let w = size.x.to_f32();
+        let h = size.y.to_f32();
+        self.xml.start_element("svg");
+        self.xml.write_attribute("class", "typst-doc");
+        self.xml.write_attribute_fmt("viewBox", format_args!("0 0 {w} {h}"));
+        self.xml.write_attribute_fmt("width", format_args!("{w}pt"));
+        self.xml.write_attribute_fmt("height", format_args!("{h}pt"));
+        self.xml.write_attribute("xmlns", "http://www.w3.org/2000/svg");
+        self.xml
+            .write_attribute("xmlns:xlink", "http://www.w3.org/1999/xlink");
+    }
+
+    /// Render a frame into the current group.
+    fn render_frame(&mut self, frame: &Frame) {
+        for (pos, item) in frame.items() {
+            let x = pos.x.to_f32();
+            let y = pos.y.to_f32();
+            match item {
+                FrameItem::Group(group) => self.render_group(x, y, group),
+                FrameItem::Text(text) => self.render_text(x, y, text),
+                FrameItem::Shape(shape, _) => self.render_shape(x, y, shape),
+                FrameItem::Image(image, size, _) => self.render_image(x, y, image, *size),
+                FrameItem::Meta(meta, size) => match meta {
+                    Meta::Link(dest) => self.render_link(x, y, dest, *size),
+                    Meta::Elem(_) => {}
+                    Meta::PageNumbering(_) => {}
+                    Meta::Tag(_) => {}
+                    Meta::Hide => {}
+                },
+            }
+        }
+    }
+
+    /// Render a group with optional transform and clipping.
+    fn render_group(&mut self, x: f32, y: f32, group: &GroupItem) {
+        let ts = Transform::translate(Abs::pt(x as f64), Abs::pt(y as f64))
+            .pre_concat(group.transform);
+
+        self.xml.start_element("g");
+        self.xml.write_attribute("class", "typst-group");
+        if !ts.is_identity() {
+            self.xml.write_attribute("transform", &SvgMatrix(ts));
+        }
+
+        if group.clips {
+            let id = eco_format!("c{:x}-{}", self.salt, self.clips);
+            self.clips += 1;
+
+            // The attribute must be written before the clip path element,
+            // which closes the group's start tag.
+            self.xml.write_attribute_fmt("clip-path", format_args!("url(#{id})"));
+
+            let size = group.frame.size();
+            self.xml.start_element("clipPath");
+            self.xml.write_attribute("id", &id);
+            self.xml.start_element("rect");
+            self.xml.write_attribute("width", &size.x.to_f32());
+            self.xml.write_attribute("height", &size.y.to_f32());
+            self.xml.end_element();
+            self.xml.end_element();
+        }
+
+        self.render_frame(&group.frame);
+        self.xml.end_element();
+    }
+
+    /// Render a text run.
+    fn render_text(&mut self, x: f32, y: f32, text: &TextItem) {
+        let size = text.size.to_f32();
+        let scale = size / text.font.units_per_em() as f32;
+
+        self.xml.start_element("g");
+        self.xml.write_attribute("class", "typst-text");
+        self.xml.write_attribute_fmt(
+            "transform",
+            format_args!("translate({x} {y}) scale({scale} {})", -scale),
+        );
+        write_fill(&mut self.xml, &text.fill);
+
+        let mut offset = 0.0;
+        for glyph in &text.glyphs {
+            let dx = offset + glyph.x_offset.at(text.size).to_f32();
+            if let Some(id) = self.glyph(&text.font, glyph.id) {
+                self.xml.start_element("use");
+                self.xml.write_attribute_fmt("xlink:href", format_args!("#{id}"));
+                self.xml.write_attribute("x", &(dx / scale));
+                self.xml.end_element();
+            } else {
+                self.render_bitmap_glyph(dx / scale, text, glyph.id);
+            }
+            offset += glyph.x_advance.at(text.size).to_f32();
+        }
+
+        self.xml.end_element();
+    }
+
+    /// Look up or build the definition of an outline glyph.
+    fn glyph(&mut self, font: &Font, id: u16) -> Option<EcoString> {
+        self.glyphs.insert_with((font.clone(), id), || {
+            let mut builder = SvgPathBuilder::default();
+            font.ttf().outline_glyph(GlyphId(id), &mut builder)?;
+            Some(builder.0)
+        })
+    }
+
+    /// Render a bitmap glyph (for instance, a color emoji) as an image in
+    /// font units.
+    fn render_bitmap_glyph(&mut self, x: f32, text: &TextItem, id: u16) {
+        let upem = text.font.units_per_em() as f32;
+        let Some(raster) = text.font.ttf().glyph_raster_image(GlyphId(id), upem as u16)
+        else {
+            return;
+        };
+
+        let format = ImageFormat::Raster(raster.format.into());
+        let w = raster.width as f32 / raster.pixels_per_em as f32 * upem;
+        let h = raster.height as f32 / raster.pixels_per_em as f32 * upem;
+        let dx = raster.x as f32 / raster.pixels_per_em as f32 * upem;
+        let dy = raster.y as f32 / raster.pixels_per_em as f32 * upem;
+
+        // Undo the vertical flip of the surrounding text group because images
+        // are drawn Y-down.
+        self.xml.start_element("image");
+        self.xml.write_attribute_fmt(
+            "transform",
+            format_args!("translate({} {}) scale(1 -1)", x + dx, dy + h),
+        );
+        self.xml.write_attribute("width", &w);
+        self.xml.write_attribute("height", &h);
+        self.xml.write_attribute("xlink:href", &data_url(format, raster.data));
+        self.xml.end_element();
+    }
+
+    /// Render a geometrical shape.
+    fn render_shape(&mut self, x: f32, y: f32, shape: &Shape) {
+        self.xml.start_element("path");
+        self.xml.write_attribute("class", "typst-shape");
+        if x != 0.0 || y != 0.0 {
+            self.xml
+                .write_attribute_fmt("transform", format_args!("translate({x} {y})"));
+        }
+
+        match &shape.fill {
+            Some(fill) => write_fill(&mut self.xml, fill),
+            None => self.xml.write_attribute("fill", "none"),
+        }
+
+        if let Some(stroke) = &shape.stroke {
+            write_stroke(&mut self.xml, stroke);
+        }
+
+        self.xml.write_attribute("d", &convert_geometry(&shape.geometry));
+        self.xml.end_element();
+    }
+
+    /// Render a raster or vector image.
+    fn render_image(&mut self, x: f32, y: f32, image: &Image, size: Size) {
+        self.xml.start_element("image");
+        self.xml.write_attribute("x", &x);
+        self.xml.write_attribute("y", &y);
+        self.xml.write_attribute("width", &size.x.to_f32());
+        self.xml.write_attribute("height", &size.y.to_f32());
+        self.xml.write_attribute("preserveAspectRatio", "none");
+        self.xml
+            .write_attribute("xlink:href", &data_url(image.format(), image.data()));
+        if let Some(alt) = image.alt() {
+            self.xml.write_attribute("aria-label", alt);
+        }
+        self.xml.end_element();
+    }
+
+    /// Render a link as a transparent, clickable rectangle.
+    fn render_link(&mut self, x: f32, y: f32, dest: &Destination, size: Size) {
+        // Internal links point into other pages, which are exported as
+        // separate files, so only external links can be represented.
+        let Destination::Url(url) = dest else { return };
+
+        self.xml.start_element("a");
+        self.xml.write_attribute("xlink:href", url);
+        self.xml.start_element("rect");
+        self.xml.write_attribute("x", &x);
+        self.xml.write_attribute("y", &y);
+        self.xml.write_attribute("width", &size.x.to_f32());
+        self.xml.write_attribute("height", &size.y.to_f32());
+        self.xml.write_attribute("fill", "transparent");
+        self.xml.end_element();
+        self.xml.end_element();
+    }
+
+    /// Write the definitions of all glyphs that were used.
+    fn write_glyph_defs(&mut self) {
+        if self.glyphs.is_empty() {
+            return;
+        }
+
+        self.xml.start_element("defs");
+        self.xml.write_attribute("id", "glyph");
+        for (id, path) in self.glyphs.iter() {
+            self.xml.start_element("path");
+            self.xml.write_attribute("id", id);
+            self.xml.write_attribute("d", path);
+            self.xml.end_element();
+        }
+        self.xml.end_element();
+    }
+}
+
+/// Assigns hash-based ids to items that should only be defined once.
+struct Deduplicator<K, V> {
+    /// The prefix of the generated ids.
+    prefix: char,
+    /// Maps from items to their ids. `None` if the item could not be built.
+    ids: HashMap<K, Option<EcoString>>,
+    /// The definitions in insertion order.
+    defs: Vec<(EcoString, V)>,
+}
+
+impl<K, V> Deduplicator<K, V>
+where
+    K: Eq + std::hash::Hash,
+{
+    fn new(prefix: char) -> Self {
+        Self { prefix, ids: HashMap::new(), defs: vec![] }
+    }
+
+    /// Get the id of an item, building its definition if it is new.
+    fn insert_with<F>(&mut self, key: K, f: F) -> Option<EcoString>
+    where
+        F: FnOnce() -> Option<V>,
+    {
+        let defs = &mut self.defs;
+        let id = eco_format!("{}{:x}", self.prefix, hash128(&key) as u64);
+        self.ids
+            .entry(key)
+            .or_insert_with(|| {
+                let value = f()?;
+                defs.push((id.clone(), value));
+                Some(id)
+            })
+            .clone()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&EcoString, &V)> {
+        self.defs.iter().map(|(id, value)| (id, value))
+    }
+}
+
+/// Write the fill attributes for a paint.
+fn write_fill(xml: &mut XmlWriter, paint: &Paint) {
+    let Paint::Solid(color) = paint;
+    let (hex, opacity) = convert_color(*color);
+    xml.write_attribute("fill", &hex);
+    if let Some(opacity) = opacity {
+        xml.write_attribute("fill-opacity", &opacity);
+    }
+}
+
+/// Write the stroke attributes for a stroke.
+fn write_stroke(xml: &mut XmlWriter, stroke: &Stroke) {
+    let Stroke {
+        paint,
+        thickness,
+        line_cap,
+        line_join,
+        dash_pattern,
+        miter_limit,
+    } = stroke;
+
+    let Paint::Solid(color) = paint;
+    let (hex, opacity) = convert_color(*color);
+    xml.write_attribute("stroke", &hex);
+    if let Some(opacity) = opacity {
+        xml.write_attribute("stroke-opacity", &opacity);
+    }
+
+    xml.write_attribute("stroke-width", &thickness.to_f32());
+    xml.write_attribute(
+        "stroke-linecap",
+        match line_cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        },
+    );
+    xml.write_attribute(
+        "stroke-linejoin",
+        match line_join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        },
+    );
+    xml.write_attribute("stroke-miterlimit", &miter_limit.0);
+
+    if let Some(pattern) = dash_pattern {
+        let mut array = String::new();
+        for (i, length) in pattern.array.iter().enumerate() {
+            if i > 0 {
+                array.push(' ');
+            }
+            write!(array, "{}", length.to_f32()).unwrap();
+        }
+        xml.write_attribute("stroke-dasharray", &array);
+        xml.write_attribute("stroke-dashoffset", &pattern.phase.to_f32());
+    }
+}
+
+/// Convert a color into a hex code and an optional opacity.
+fn convert_color(color: Color) -> (EcoString, Option<f32>) {
+    let c = color.to_rgba();
+    let hex = eco_format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+    let opacity = (c.a != 255).then(|| c.a as f32 / 255.0);
+    (hex, opacity)
+}
+
+/// Convert a geometry into SVG path data.
+fn convert_geometry(geometry: &Geometry) -> String {
+    let mut builder = SvgPathBuilder::default();
+    match geometry {
+        Geometry::Line(target) => {
+            builder.move_to(0.0, 0.0);
+            builder.line_to(target.x.to_f32(), target.y.to_f32());
+        }
+        Geometry::Rect(size) => {
+            let w = size.x.to_f32();
+            let h = size.y.to_f32();
+            builder.move_to(0.0, 0.0);
+            builder.line_to(w, 0.0);
+            builder.line_to(w, h);
+            builder.line_to(0.0, h);
+            builder.close();
+        }
+        Geometry::Path(path) => convert_path(&mut builder, path),
+    }
+    builder.0
+}
+
+/// Convert a Typst path into SVG path data.
+fn convert_path(builder: &mut SvgPathBuilder, path: &geom::Path) {
+    for elem in &path.0 {
+        match elem {
+            PathItem::MoveTo(p) => builder.move_to(p.x.to_f32(), p.y.to_f32()),
+            PathItem::LineTo(p) => builder.line_to(p.x.to_f32(), p.y.to_f32()),
+            PathItem::CubicTo(p1, p2, p3) => builder.curve_to(
+                p1.x.to_f32(),
+                p1.y.to_f32(),
+                p2.x.to_f32(),
+                p2.y.to_f32(),
+                p3.x.to_f32(),
+                p3.y.to_f32(),
+            ),
+            PathItem::ClosePath => builder.close(),
+        }
+    }
+}
+
+/// Encode raw image data as a data URL.
+fn data_url(format: ImageFormat, data: &[u8]) -> String {
+    let mime = match format {
+        ImageFormat::Raster(RasterFormat::Png) => "image/png",
+        ImageFormat::Raster(RasterFormat::Jpg) => "image/jpeg",
+        ImageFormat::Raster(RasterFormat::Gif) => "image/gif",
+        ImageFormat::Vector(VectorFormat::Svg) => "image/svg+xml",
+    };
+
+    let mut url = format!("data:{mime};base64,");
+    base64::engine::general_purpose::STANDARD.encode_string(data, &mut url);
+    url
+}
+
+/// Builds SVG path data from glyph outlines and Typst paths.
+#[derive(Default)]
+struct SvgPathBuilder(String);
+
+impl OutlineBuilder for SvgPathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        write!(self.0, "M {x} {y} ").unwrap();
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        write!(self.0, "L {x} {y} ").unwrap();
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        write!(self.0, "Q {x1} {y1} {x} {y} ").unwrap();
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        write!(self.0, "C {x1} {y1} {x2} {y2} {x} {y} ").unwrap();
+    }
+
+    fn close(&mut self) {
+        self.0.push_str("Z ");
+    }
+}
+
+/// Displays a transform as an SVG `matrix(..)` transform.
+struct SvgMatrix(Transform);
+
+impl fmt::Display for SvgMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Transform { sx, ky, kx, sy, tx, ty } = self.0;
+        write!(
+            f,
+            "matrix({} {} {} {} {} {})",
+            sx.get(),
+            ky.get(),
+            kx.get(),
+            sy.get(),
+            tx.to_f32(),
+            ty.to_f32()
+        )
+    }
+}
+
+/// Additional methods for [`Abs`].
+trait AbsExt {
+    /// Convert to a number of points as f32.
+    fn to_f32(self) -> f32;
+}
+
+impl AbsExt for Abs {
+    fn to_f32(self) -> f32 {
+        self.to_pt() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+    use crate::doc::{Glyph, Lang, Position};
+    use crate::geom::{Em, Point};
+    use crate::syntax::Span;
+    use crate::util::Bytes;
+
+    /// Export a frame and check that the result is well-formed XML.
+    fn export(frame: &Frame) -> String {
+        let svg = svg(frame);
+        roxmltree::Document::parse(&svg).expect("exported svg is malformed");
+        svg
+    }
+
+    fn size() -> Size {
+        Size::new(Abs::pt(100.0), Abs::pt(50.0))
+    }
+
+    fn text(s: &str) -> TextItem {
+        let data = include_bytes!("../../../../assets/fonts/IBMPlexSans-Regular.ttf");
+        let font = Font::new(Bytes::from_static(data), 0).unwrap();
+        let glyphs = s
+            .char_indices()
+            .map(|(i, c)| Glyph {
+                id: font.ttf().glyph_index(c).unwrap().0,
+                x_advance: Em::new(0.5),
+                x_offset: Em::zero(),
+                range: i as u16..i as u16 + 1,
+                span: (Span::detached(), 0),
+            })
+            .collect();
+
+        TextItem {
+            font,
+            size: Abs::pt(10.0),
+            fill: Color::BLACK.into(),
+            lang: Lang::ENGLISH,
+            text: s.into(),
+            glyphs,
+        }
+    }
+
+    #[test]
+    fn test_svg_clipping_group() {
+        let mut inner = Frame::new(size());
+        let rect = Geometry::Rect(size()).filled(Color::RED.into());
+        inner.push(Point::zero(), FrameItem::Shape(rect, Span::detached()));
+
+        let mut group = GroupItem::new(inner);
+        group.clips = true;
+
+        let mut frame = Frame::new(size());
+        frame.push(Point::with_x(Abs::pt(5.0)), FrameItem::Group(group.clone()));
+        frame.push(Point::zero(), FrameItem::Group(group));
+
+        let svg = export(&frame);
+        let doc = roxmltree::Document::parse(&svg).unwrap();
+        let groups: Vec<_> = doc
+            .descendants()
+            .filter(|node| node.attribute("class") == Some("typst-group"))
+            .collect();
+
+        assert_eq!(groups.len(), 2);
+        for group in &groups {
+            let clip = group.attribute("clip-path").unwrap();
+            let id = clip.strip_prefix("url(#").unwrap().strip_suffix(')').unwrap();
+            let path = group.first_element_child().unwrap();
+            assert_eq!(path.tag_name().name(), "clipPath");
+            assert_eq!(path.attribute("id"), Some(id));
+        }
+
+        // Each clip path has its own id.
+        assert_ne!(groups[0].attribute("clip-path"), groups[1].attribute("clip-path"));
+    }
+
+    #[test]
+    fn test_svg_text_uses_glyph_defs() {
+        let mut frame = Frame::new(size());
+        frame.push(Point::with_y(Abs::pt(20.0)), FrameItem::Text(text("abba")));
+
+        let svg = export(&frame);
+        let doc = roxmltree::Document::parse(&svg).unwrap();
+        let defs: Vec<_> = doc
+            .descendants()
+            .filter(|node| node.has_tag_name("defs"))
+            .flat_map(|node| node.children().filter(|child| child.is_element()))
+            .collect();
+        let uses: Vec<_> =
+            doc.descendants().filter(|node| node.has_tag_name("use")).collect();
+
+        // Repeated glyphs are only defined once.
+        assert_eq!(defs.len(), 2);
+        assert_eq!(uses.len(), 4);
+        for node in uses {
+            let href = node
+                .attribute(("http://www.w3.org/1999/xlink", "href"))
+                .unwrap()
+                .strip_prefix('#')
+                .unwrap();
+            assert!(defs.iter().any(|def| def.attribute("id") == Some(href)));
+        }
+    }
+
+    #[test]
+    fn test_svg_image() {
+        let data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+        let format = ImageFormat::Vector(VectorFormat::Svg);
+        let image = Image::new(Bytes::from_static(data), format, Some("A square".into()))
+            .unwrap();
+
+        let mut frame = Frame::new(size());
+        frame.push(Point::zero(), FrameItem::Image(image, size(), Span::detached()));
+
+        let svg = export(&frame);
+        assert!(svg.contains("xlink:href=\"data:image/svg+xml;base64,"));
+        assert!(svg.contains("aria-label=\"A square\""));
+    }
+
+    #[test]
+    fn test_svg_links() {
+        let mut frame = Frame::new(size());
+        let url = Destination::Url("https://typst.app".into());
+        frame.push(Point::zero(), FrameItem::Meta(Meta::Link(url), size()));
+        let pos = Destination::Position(Position {
+            page: NonZeroUsize::new(1).unwrap(),
+            point: Point::zero(),
+        });
+        frame.push(Point::zero(), FrameItem::Meta(Meta::Link(pos), size()));
+
+        let svg = export(&frame);
+        assert_eq!(svg.matches("<a ").count(), 1);
+        assert!(svg.contains("xlink:href=\"https://typst.app\""));
+    }
+}
@@ -1,5 +1,6 @@
 //! File and package management.
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::path::{Path, PathBuf};
@@ -202,6 +203,19 @@ impl Display for PackageSpec {
     }
 }
 
+impl Serialize for PackageSpec {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PackageSpec {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let string = EcoString::deserialize(d)?;
+        string.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// A package's version.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Version {
@@ -213,6 +227,17 @@ pub struct Version {
     pub patch: u32,
 }
 
+impl Version {
+    /// The version of the running compiler.
+    pub fn compiler() -> Self {
+        Self {
+            major: env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap(),
+            minor: env!("CARGO_PKG_VERSION_MINOR").parse().unwrap(),
+            patch: env!("CARGO_PKG_VERSION_PATCH").parse().unwrap(),
+        }
+    }
+}
+
 impl FromStr for Version {
     type Err = EcoString;
 
@@ -256,11 +281,160 @@ impl<'de> Deserialize<'de> for Version {
     }
 }
 
+/// A requirement on a version, e.g. `>=0.6` or `>=0.6.1, <0.8`.
+///
+/// Consists of comma-separated comparisons with version bounds whose minor and
+/// patch components may be omitted. Omitted components are not compared, so
+/// `<0.8` excludes `0.8.1` and `=0.6` allows `0.6.2`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct VersionRequirement(Vec<(VersionOp, VersionBound)>);
+
+/// A comparison operator in a [`VersionRequirement`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum VersionOp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A version whose minor and patch components may be omitted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct VersionBound {
+    major: u32,
+    minor: Option<u32>,
+    patch: Option<u32>,
+}
+
+impl VersionRequirement {
+    /// Whether the given version satisfies the requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.0.iter().all(|(op, bound)| {
+            let ordering = bound.compare(version);
+            match op {
+                VersionOp::Eq => ordering.is_eq(),
+                VersionOp::Lt => ordering.is_lt(),
+                VersionOp::Le => ordering.is_le(),
+                VersionOp::Gt => ordering.is_gt(),
+                VersionOp::Ge => ordering.is_ge(),
+            }
+        })
+    }
+}
+
+impl VersionBound {
+    /// Compare a version to the bound, ignoring omitted components.
+    fn compare(&self, version: &Version) -> Ordering {
+        version
+            .major
+            .cmp(&self.major)
+            .then_with(|| self.minor.map_or(Ordering::Equal, |m| version.minor.cmp(&m)))
+            .then_with(|| self.patch.map_or(Ordering::Equal, |p| version.patch.cmp(&p)))
+    }
+}
+
+impl FromStr for VersionRequirement {
+    type Err = EcoString;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparisons = vec![];
+        for part in s.split(',') {
+            let part = part.trim();
+            let (op, rest) = if let Some(rest) = part.strip_prefix(">=") {
+                (VersionOp::Ge, rest)
+            } else if let Some(rest) = part.strip_prefix("<=") {
+                (VersionOp::Le, rest)
+            } else if let Some(rest) = part.strip_prefix('>') {
+                (VersionOp::Gt, rest)
+            } else if let Some(rest) = part.strip_prefix('<') {
+                (VersionOp::Lt, rest)
+            } else if let Some(rest) = part.strip_prefix('=') {
+                (VersionOp::Eq, rest)
+            } else {
+                bail!("version requirement `{part}` must start with a comparison");
+            };
+
+            let rest = rest.trim();
+            if rest.is_empty() {
+                bail!("version requirement is missing major version");
+            }
+
+            let mut parts = rest.split('.');
+            let mut next = |kind| {
+                parts
+                    .next()
+                    .map(|part| {
+                        part.parse::<u32>().map_err(|_| {
+                            eco_format!("`{part}` is not a valid {kind} version")
+                        })
+                    })
+                    .transpose()
+            };
+
+            // Splitting a non-empty string yields at least one part.
+            let major = next("major")?.unwrap_or_default();
+            let minor = next("minor")?;
+            let patch = if minor.is_some() { next("patch")? } else { None };
+            if let Some(rest) = parts.next() {
+                bail!("version requirement has unexpected fourth component: `{rest}`");
+            }
+
+            comparisons.push((op, VersionBound { major, minor, patch }));
+        }
+
+        Ok(Self(comparisons))
+    }
+}
+
+impl Display for VersionRequirement {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, (op, bound)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            let op = match op {
+                VersionOp::Eq => "=",
+                VersionOp::Lt => "<",
+                VersionOp::Le => "<=",
+                VersionOp::Gt => ">",
+                VersionOp::Ge => ">=",
+            };
+
+            write!(f, "{op}{}", bound.major)?;
+            if let Some(minor) = bound.minor {
+                write!(f, ".{minor}")?;
+            }
+            if let Some(patch) = bound.patch {
+                write!(f, ".{patch}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for VersionRequirement {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionRequirement {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let string = EcoString::deserialize(d)?;
+        string.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// A parsed package manifest.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct PackageManifest {
     /// Details about the package itself.
     pub package: PackageInfo,
+    /// Details about the template, if the package provides one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateInfo>,
 }
 
 impl PackageManifest {
@@ -272,7 +446,8 @@ impl PackageManifest {
         })
     }
 
-    /// Ensure that this manifest is indeed for the specified package.
+    /// Ensure that this manifest is indeed for the specified package and that
+    /// the package supports the running compiler.
     pub fn validate(&self, spec: &PackageSpec) -> StrResult<()> {
         if self.package.name != spec.name {
             bail!("package manifest contains mismatched name `{}`", self.package.name);
@@ -285,13 +460,18 @@ impl PackageManifest {
             );
         }
 
+        if let Some(required) = &self.package.compiler {
+            let compiler = Version::compiler();
+            if !required.matches(&compiler) {
+                bail!("package requires typst {required}, but this is typst {compiler}");
+            }
+        }
+
         Ok(())
     }
 }
 
 /// The `package` key in the manifest.
-///
-/// More fields are specified, but they are not relevant to the compiler.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct PackageInfo {
     /// The name of the package within its namespace.
@@ -300,4 +480,124 @@ pub struct PackageInfo {
     pub version: Version,
     /// The path of the entrypoint into the package.
     pub entrypoint: EcoString,
+    /// The compiler versions the package works with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<VersionRequirement>,
+    /// The package's authors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<EcoString>,
+    /// The package's license as an SPDX expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<EcoString>,
+    /// A short description of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<EcoString>,
+    /// Globs of files that are excluded when the package is bundled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<EcoString>,
+    /// Other packages this package imports.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PackageSpec>,
+}
+
+/// The `template` key in the manifest.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    /// The directory within the package that is copied into new projects.
+    pub path: EcoString,
+    /// The path of the main file of new projects within the template
+    /// directory.
+    pub entrypoint: EcoString,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a version requirement.
+    fn requirement(s: &str) -> VersionRequirement {
+        s.parse().unwrap()
+    }
+
+    /// Parse a version.
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_version_requirement_parse() {
+        assert_eq!(requirement(">=0.6").to_string(), ">=0.6");
+        assert_eq!(requirement(" >= 0.6.1 ,<0.8 ").to_string(), ">=0.6.1, <0.8");
+        assert_eq!(requirement("=1").to_string(), "=1");
+        assert_eq!(requirement("<=2.0.3, >1").to_string(), "<=2.0.3, >1");
+    }
+
+    #[test]
+    fn test_version_requirement_parse_invalid() {
+        let error = |s: &str| s.parse::<VersionRequirement>().unwrap_err();
+        assert_eq!(error(""), "version requirement `` must start with a comparison");
+        assert_eq!(
+            error("0.6"),
+            "version requirement `0.6` must start with a comparison"
+        );
+        assert_eq!(
+            error(">=0.6,"),
+            "version requirement `` must start with a comparison"
+        );
+        assert_eq!(error(">="), "version requirement is missing major version");
+        assert_eq!(error(">=x"), "`x` is not a valid major version");
+        assert_eq!(error("<0.y"), "`y` is not a valid minor version");
+        assert_eq!(error("<0.6."), "`` is not a valid patch version");
+        assert_eq!(
+            error("=1.2.3.4"),
+            "version requirement has unexpected fourth component: `4`",
+        );
+    }
+
+    #[test]
+    fn test_version_requirement_matches() {
+        let matches = |req: &str, v: &str| requirement(req).matches(&version(v));
+
+        assert!(matches(">=0.6", "0.6.0"));
+        assert!(matches(">=0.6", "0.6.5"));
+        assert!(matches(">=0.6", "1.0.0"));
+        assert!(!matches(">=0.6", "0.5.9"));
+        assert!(matches(">=0.6.1", "0.6.1"));
+        assert!(!matches(">=0.6.1", "0.6.0"));
+
+        // Omitted components are not compared.
+        assert!(matches("=0.6", "0.6.2"));
+        assert!(!matches("=0.6", "0.7.0"));
+        assert!(!matches("<0.8", "0.8.1"));
+        assert!(matches("<=0.8", "0.8.1"));
+        assert!(matches(">0", "1.0.0"));
+        assert!(!matches(">0", "0.9.0"));
+
+        // All comparisons must hold.
+        assert!(matches(">=0.6.1, <0.8", "0.7.3"));
+        assert!(!matches(">=0.6.1, <0.8", "0.6.0"));
+        assert!(!matches(">=0.6.1, <0.8", "0.8.0"));
+    }
+
+    #[test]
+    fn test_package_requires_compiler() {
+        let spec: PackageSpec = "@preview/example:0.1.0".parse().unwrap();
+        let manifest = |compiler: &str| {
+            PackageManifest::parse(
+                format!(
+                    "[package]\nname = \"example\"\nversion = \"0.1.0\"\n\
+                     entrypoint = \"lib.typ\"\ncompiler = \"{compiler}\"\n"
+                )
+                .as_bytes(),
+            )
+            .unwrap()
+        };
+
+        let compiler = Version::compiler();
+        assert!(manifest(&format!("={compiler}")).validate(&spec).is_ok());
+        assert_eq!(
+            manifest(">=99.0").validate(&spec).unwrap_err(),
+            eco_format!("package requires typst >=99.0, but this is typst {compiler}"),
+        );
+    }
 }
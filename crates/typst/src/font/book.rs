@@ -1,5 +1,6 @@
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::ops::Range;
 
 use serde::{Deserialize, Serialize};
 use ttf_parser::{name_id, PlatformId, Tag};
@@ -477,6 +478,16 @@ impl Coverage {
         false
     }
 
+    /// Iterate over the ranges of covered codepoints.
+    pub fn ranges(&self) -> impl Iterator<Item = Range<u32>> + '_ {
+        let mut cursor = 0;
+        self.0.chunks_exact(2).map(move |pair| {
+            let start = cursor + pair[0];
+            cursor = start + pair[1];
+            start..cursor
+        })
+    }
+
     /// Iterate over all covered codepoints.
     pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
         let mut inside = false;
@@ -543,4 +554,13 @@ mod tests {
         let coverage = Coverage::from_vec(codepoints.clone());
         assert_eq!(coverage.iter().collect::<Vec<_>>(), codepoints);
     }
+
+    #[test]
+    fn test_coverage_ranges() {
+        let coverage = Coverage::from_vec(vec![2, 3, 7, 8, 9, 14, 15, 19, 21]);
+        assert_eq!(
+            coverage.ranges().collect::<Vec<_>>(),
+            [2..4, 7..10, 14..16, 19..20, 21..22]
+        );
+    }
 }
@@ -16,6 +16,7 @@ use image::{ImageDecoder, ImageResult};
 use usvg::{TreeParsing, TreeTextToPath};
 
 use crate::diag::{format_xml_like_error, StrResult};
+use crate::eval::{cast, Cast, IntoValue};
 use crate::font::Font;
 use crate::geom::Axes;
 use crate::util::Bytes;
@@ -156,8 +157,81 @@ pub enum ImageFormat {
     Vector(VectorFormat),
 }
 
+impl ImageFormat {
+    /// Try to detect the format of an image from its data.
+    ///
+    /// Gzip-compressed SVGs are not detected, since the compression could
+    /// hide any format. Their format must be known from elsewhere, e.g. from
+    /// an `.svgz` extension.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Some(Self::Raster(RasterFormat::Png));
+        }
+
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            return Some(Self::Raster(RasterFormat::Jpg));
+        }
+
+        if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            return Some(Self::Raster(RasterFormat::Gif));
+        }
+
+        if is_svg(data) {
+            return Some(Self::Vector(VectorFormat::Svg));
+        }
+
+        None
+    }
+}
+
+/// Whether the data is an SVG document, i.e. whether its root element is
+/// `svg` after an optional byte order mark, XML declaration, comments,
+/// processing instructions and document type declaration.
+fn is_svg(data: &[u8]) -> bool {
+    let mut rest = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    loop {
+        let start = rest.iter().position(|b| !b.is_ascii_whitespace());
+        rest = &rest[start.unwrap_or(rest.len())..];
+        if rest.starts_with(b"<svg") {
+            return true;
+        }
+
+        // A document type declaration may contain an internal subset in
+        // brackets, which in turn contains `>`.
+        let end: &[u8] = if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<!") {
+            let close = rest.iter().position(|&b| b == b'>');
+            let open = rest.iter().position(|&b| b == b'[');
+            match (open, close) {
+                (Some(open), Some(close)) if open < close => b"]>",
+                _ => b">",
+            }
+        } else {
+            return false;
+        };
+
+        match rest.windows(end.len()).position(|window| window == end) {
+            Some(i) => rest = &rest[i + end.len()..],
+            None => return false,
+        }
+    }
+}
+
+cast! {
+    ImageFormat,
+    self => match self {
+        Self::Raster(v) => v.into_value(),
+        Self::Vector(v) => v.into_value(),
+    },
+    v: RasterFormat => Self::Raster(v),
+    v: VectorFormat => Self::Vector(v),
+}
+
 /// A raster graphics format.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Cast)]
 pub enum RasterFormat {
     /// Raster format for illustrations and transparent graphics.
     Png,
@@ -168,7 +242,7 @@ pub enum RasterFormat {
 }
 
 /// A vector graphics format.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Cast)]
 pub enum VectorFormat {
     /// The vector graphics format of the web.
     Svg,
@@ -447,3 +521,32 @@ fn format_usvg_error(error: usvg::Error) -> EcoString {
         usvg::Error::ParsingFailed(error) => format_xml_like_error("svg", error),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_svg() {
+        let svg = Some(ImageFormat::Vector(VectorFormat::Svg));
+        assert_eq!(ImageFormat::detect(b"<svg></svg>"), svg);
+        assert_eq!(
+            ImageFormat::detect(
+                b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n\
+                  <!-- <html> -->\n\
+                  <!DOCTYPE svg [<!ENTITY a \"b\">]>\n\
+                  <svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+            ),
+            svg,
+        );
+    }
+
+    #[test]
+    fn test_detect_not_svg() {
+        assert_eq!(ImageFormat::detect(b"<html><svg></svg></html>"), None);
+        assert_eq!(ImageFormat::detect(b"<!-- <svg> -->"), None);
+        assert_eq!(ImageFormat::detect(b"<!-- unterminated <svg>"), None);
+        assert_eq!(ImageFormat::detect(b"plain text mentioning <svg>"), None);
+        assert_eq!(ImageFormat::detect(&[0x1F, 0x8B, 0x08, 0x00]), None);
+    }
+}
@@ -17,7 +17,7 @@
 //!   per page with items at fixed positions.
 //! - **Exporting:**
 //!   These frames can finally be exported into an output format (currently
-//!   supported are [PDF] and [raster images]).
+//!   supported are [PDF], [SVG], and [raster images]).
 //!
 //! [tokens]: syntax::SyntaxKind
 //! [parsed]: syntax::parse
@@ -30,6 +30,7 @@
 //! [document]: doc::Document
 //! [frame]: doc::Frame
 //! [PDF]: export::pdf
+//! [SVG]: export::svg
 //! [raster images]: export::render
 
 #![recursion_limit = "1000"]
@@ -56,7 +57,7 @@ pub mod syntax;
 use comemo::{Prehashed, Track, TrackedMut};
 use ecow::EcoString;
 
-use crate::diag::{FileResult, SourceResult};
+use crate::diag::{FileError, FileResult, SourceResult};
 use crate::doc::Document;
 use crate::eval::{Datetime, Library, Route, Tracer};
 use crate::file::{FileId, PackageSpec};
@@ -65,10 +66,21 @@ use crate::syntax::Source;
 use crate::util::Bytes;
 
 /// Compile a source file into a fully layouted document.
-#[tracing::instrument(skip(world))]
-pub fn compile(world: &dyn World) -> SourceResult<Document> {
+///
+/// Warnings are collected in the `tracer`, whether compilation succeeds or
+/// not. Files written by the document are not written yet, but remain pending
+/// in the `tracer`. Once the caller accepted the document, it can perform them
+/// with [`Tracer::flush_writes`].
+#[tracing::instrument(skip(world, tracer))]
+pub fn compile(world: &dyn World, tracer: &mut Tracer) -> SourceResult<Document> {
+    let document = typeset(world, tracer)?;
+    tracer.check_writes()?;
+    Ok(document)
+}
+
+/// Evaluate and typeset the main source file.
+fn typeset(world: &dyn World, tracer: &mut Tracer) -> SourceResult<Document> {
     let route = Route::default();
-    let mut tracer = Tracer::default();
 
     // Call `track` just once to keep comemo's ID stable.
     let world = world.track();
@@ -123,7 +135,15 @@ pub trait World {
     /// Try to access the specified file.
     fn file(&self, id: FileId) -> FileResult<Bytes>;
 
-    fn write(&self, id: FileId, data: &[u8]) -> FileResult<()>;
+    /// Try to write data to the specified file.
+    ///
+    /// This is called once compilation has succeeded, for each file the
+    /// document wrote to. Implementors decide where writing is allowed and
+    /// should deny it with a [`FileError::AccessDenied`] that names the rule.
+    /// Writing is not supported by default.
+    fn write(&self, _id: FileId, _data: &[u8]) -> FileResult<()> {
+        Err(FileError::AccessDenied(Some("writing files is not supported".into())))
+    }
 
     /// Try to access the font with the given index in the font book.
     fn font(&self, index: usize) -> Option<Font>;
@@ -5,6 +5,7 @@ use std::ops::{Add, AddAssign};
 
 use comemo::Prehashed;
 use ecow::{eco_format, EcoString, EcoVec};
+use serde::ser::{Serialize, SerializeMap, Serializer};
 
 use super::{
     element, Behave, Behaviour, ElemFunc, Element, Guard, Label, Locatable, Location,
@@ -463,6 +464,19 @@ impl Debug for Content {
     }
 }
 
+impl Serialize for Content {
+    /// Serialize the content as a map of its fields, with the element's name
+    /// under the `func` key.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("func", self.func.name())?;
+        for (name, value) in self.fields() {
+            map.serialize_entry(name.as_str(), &value)?;
+        }
+        map.end()
+    }
+}
+
 impl Default for Content {
     fn default() -> Self {
         Self::empty()
@@ -122,19 +122,24 @@ impl<'a> Locator<'a> {
             match item {
                 FrameItem::Group(group) => self.visit_frame(&group.frame),
                 FrameItem::Meta(Meta::Elem(elem), _) => {
-                    let mut hashes = self.hashes.borrow_mut();
-                    let loc = elem.location().unwrap();
-                    let entry = hashes.entry(loc.hash).or_default();
-
-                    // Next disambiguator needs to be at least one larger than
-                    // the maximum we've seen so far.
-                    *entry = (*entry).max(loc.disambiguator + 1);
+                    self.visit_location(elem.location().unwrap());
                 }
+                FrameItem::Meta(Meta::Tag(tag), _) => self.visit_location(tag.location),
                 _ => {}
             }
         }
     }
 
+    /// Advance past a location that was assigned within a frame.
+    fn visit_location(&mut self, loc: Location) {
+        let mut hashes = self.hashes.borrow_mut();
+        let entry = hashes.entry(loc.hash).or_default();
+
+        // Next disambiguator needs to be at least one larger than the maximum
+        // we've seen so far.
+        *entry = (*entry).max(loc.disambiguator + 1);
+    }
+
     /// Advance past a number of frames.
     pub fn visit_frames<'b>(&mut self, frames: impl IntoIterator<Item = &'b Frame>) {
         for frame in frames {
@@ -60,6 +60,7 @@ pub fn typeset(
         tracing::info!("Layout iteration {iter}");
 
         delayed = DelayedErrors::default();
+        tracer.start_layout_iteration();
 
         let constraint = <Introspector as Validate>::Constraint::new();
         let mut locator = Locator::new();
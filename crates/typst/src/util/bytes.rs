@@ -1,9 +1,13 @@
 use std::borrow::Cow;
-use std::fmt::{self, Debug, Formatter};
-use std::ops::Deref;
+use std::fmt::{self, Debug, Formatter, Write};
+use std::ops::{Add, Deref};
 use std::sync::Arc;
 
+use base64::Engine;
 use comemo::Prehashed;
+use ecow::{eco_format, EcoString};
+
+use crate::diag::StrResult;
 
 /// A shared byte buffer that is cheap to clone and hash.
 #[derive(Clone, Hash, Eq, PartialEq)]
@@ -24,6 +28,97 @@ impl Bytes {
     pub fn to_vec(&self) -> Vec<u8> {
         self.0.to_vec()
     }
+
+    /// Extract the byte at the given index. Negative indices count from the
+    /// back.
+    pub fn at(&self, index: i64) -> StrResult<u8> {
+        self.locate_opt(index)
+            .and_then(|i| self.get(i).copied())
+            .ok_or_else(|| out_of_bounds_no_default(index, self.len()))
+    }
+
+    /// Extract a contiguous subslice of the buffer.
+    pub fn slice(&self, start: i64, end: Option<i64>) -> StrResult<Self> {
+        let start = self.locate(start)?;
+        let end = self.locate(end.unwrap_or(self.len() as i64))?.max(start);
+        Ok(self[start..end].into())
+    }
+
+    /// Decode a base64 string, as it is used in e.g. data URLs.
+    pub fn from_base64(text: &str) -> StrResult<Self> {
+        base64::engine::general_purpose::STANDARD
+            .decode(text.trim())
+            .map(Self::from)
+            .map_err(|_| "invalid base64 string".into())
+    }
+
+    /// Encode the buffer as a base64 string.
+    pub fn to_base64(&self) -> EcoString {
+        base64::engine::general_purpose::STANDARD
+            .encode(self.as_slice())
+            .into()
+    }
+
+    /// Decode a string of hexadecimal digits, two per byte.
+    pub fn from_hex(text: &str) -> StrResult<Self> {
+        let digits = text.trim().as_bytes();
+        if digits.len() % 2 != 0 {
+            return Err("hex string must have an even number of digits".into());
+        }
+
+        digits
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = std::str::from_utf8(pair).ok()?;
+                u8::from_str_radix(pair, 16).ok()
+            })
+            .collect::<Option<Vec<u8>>>()
+            .map(Self::from)
+            .ok_or_else(|| "invalid hex string".into())
+    }
+
+    /// Encode the buffer as a string of lowercase hexadecimal digits.
+    pub fn to_hex(&self) -> EcoString {
+        let mut hex = EcoString::with_capacity(2 * self.len());
+        for byte in self.iter() {
+            write!(hex, "{byte:02x}").unwrap();
+        }
+        hex
+    }
+
+    /// Resolve an index, if it is within bounds. The length itself is a
+    /// valid index for slicing.
+    fn locate_opt(&self, index: i64) -> Option<usize> {
+        let wrapped =
+            if index >= 0 { Some(index) } else { (self.len() as i64).checked_add(index) };
+
+        wrapped
+            .and_then(|v| usize::try_from(v).ok())
+            .filter(|&v| v <= self.len())
+    }
+
+    /// Resolve an index or throw an out of bounds error.
+    fn locate(&self, index: i64) -> StrResult<usize> {
+        self.locate_opt(index).ok_or_else(|| out_of_bounds(index, self.len()))
+    }
+}
+
+impl Add for Bytes {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.is_empty() {
+            return rhs;
+        }
+        if rhs.is_empty() {
+            return self;
+        }
+
+        let mut vec = Vec::with_capacity(self.len() + rhs.len());
+        vec.extend_from_slice(&self);
+        vec.extend_from_slice(&rhs);
+        vec.into()
+    }
 }
 
 impl From<&[u8]> for Bytes {
@@ -57,3 +152,18 @@ impl Debug for Bytes {
         write!(f, "bytes({})", self.len())
     }
 }
+
+/// The out of bounds access error message.
+#[cold]
+fn out_of_bounds(index: i64, len: usize) -> EcoString {
+    eco_format!("byte index out of bounds (index: {index}, len: {len})")
+}
+
+/// The out of bounds access error message when no default value was given.
+#[cold]
+fn out_of_bounds_no_default(index: i64, len: usize) -> EcoString {
+    eco_format!(
+        "byte index out of bounds (index: {index}, len: {len}) \
+         and no default value was specified",
+    )
+}
@@ -1,7 +1,7 @@
 use comemo::{Prehashed, Track, Tracked};
 use iai::{black_box, main, Iai};
 use typst::diag::FileResult;
-use typst::eval::{Datetime, Library};
+use typst::eval::{Datetime, Library, Tracer};
 use typst::file::FileId;
 use typst::font::{Font, FontBook};
 use typst::geom::Color;
@@ -60,7 +60,7 @@ fn bench_edit(iai: &mut Iai) {
 fn bench_eval(iai: &mut Iai) {
     let world = BenchWorld::new();
     let route = typst::eval::Route::default();
-    let mut tracer = typst::eval::Tracer::default();
+    let mut tracer = Tracer::default();
     iai.run(|| {
         typst::eval::eval(world.track(), route.track(), tracer.track_mut(), &world.source)
             .unwrap()
@@ -70,7 +70,7 @@ fn bench_eval(iai: &mut Iai) {
 fn bench_typeset(iai: &mut Iai) {
     let world = BenchWorld::new();
     let route = typst::eval::Route::default();
-    let mut tracer = typst::eval::Tracer::default();
+    let mut tracer = Tracer::default();
     let module = typst::eval::eval(
         world.track(),
         route.track(),
@@ -84,12 +84,14 @@ fn bench_typeset(iai: &mut Iai) {
 
 fn bench_compile(iai: &mut Iai) {
     let world = BenchWorld::new();
-    iai.run(|| typst::compile(&world));
+    let mut tracer = Tracer::default();
+    iai.run(|| typst::compile(&world, &mut tracer));
 }
 
 fn bench_render(iai: &mut Iai) {
     let world = BenchWorld::new();
-    let document = typst::compile(&world).unwrap();
+    let mut tracer = Tracer::default();
+    let document = typst::compile(&world, &mut tracer).unwrap();
     iai.run(|| typst::export::render(&document.pages[0], 1.0, Color::WHITE))
 }
 
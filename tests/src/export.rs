@@ -0,0 +1,412 @@
+//! Tests that check the structure of exported documents.
+
+use comemo::Prehashed;
+use once_cell::sync::Lazy;
+use typst::diag::{FileError, FileResult, SourceResult};
+use typst::doc::{Document, Frame, FrameItem};
+use typst::eval::{Datetime, Library, Tracer};
+use typst::export::{PageRanges, PdfOptions, PdfStandard};
+use typst::file::FileId;
+use typst::font::{Font, FontBook};
+use typst::syntax::Source;
+use typst::util::Bytes;
+use typst::World;
+
+/// The fonts that are available to the tested documents.
+static FONTS: Lazy<Vec<Font>> = Lazy::new(|| {
+    [
+        include_bytes!("../../assets/fonts/LinLibertine_R.ttf").as_slice(),
+        include_bytes!("../../assets/fonts/NewCMMath-Regular.otf").as_slice(),
+    ]
+    .into_iter()
+    .flat_map(|data| Font::iter(Bytes::from_static(data)))
+    .collect()
+});
+
+/// A world with a single source file.
+struct ExportWorld {
+    library: Prehashed<Library>,
+    book: Prehashed<FontBook>,
+    source: Source,
+}
+
+impl ExportWorld {
+    fn new(text: &str) -> Self {
+        Self {
+            library: Prehashed::new(typst_library::build()),
+            book: Prehashed::new(FontBook::from_fonts(FONTS.iter())),
+            source: Source::detached(text),
+        }
+    }
+}
+
+impl World for ExportWorld {
+    fn library(&self) -> &Prehashed<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &Prehashed<FontBook> {
+        &self.book
+    }
+
+    fn main(&self) -> Source {
+        self.source.clone()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        Err(FileError::NotFound(id.path().into()))
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        Err(FileError::NotFound(id.path().into()))
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        FONTS.get(index).cloned()
+    }
+
+    fn today(&self, _: Option<i64>) -> Option<Datetime> {
+        Datetime::from_ymd(1970, 1, 1)
+    }
+}
+
+/// Compile a document that must not have errors.
+fn compile(text: &str) -> Document {
+    compile_world(&ExportWorld::new(text))
+}
+
+/// Compile the source file of a world, which must not have errors.
+fn compile_world(world: &ExportWorld) -> Document {
+    let mut tracer = Tracer::default();
+    match typst::compile(world, &mut tracer) {
+        Ok(document) => document,
+        Err(errors) => panic!("failed to compile: {:?}", errors[0].message),
+    }
+}
+
+/// Export a document that must not have errors to HTML.
+fn html(text: &str) -> String {
+    let world = ExportWorld::new(text);
+    let mut tracer = Tracer::default();
+    let document = match typst::compile(&world, &mut tracer) {
+        Ok(document) => document,
+        Err(errors) => panic!("failed to compile: {:?}", errors[0].message),
+    };
+    match typst_library::html::html(&world, &mut tracer, &document) {
+        Ok(html) => html,
+        Err(errors) => panic!("failed to export: {:?}", errors[0].message),
+    }
+}
+
+/// The values of all attributes that start with the given text, e.g. all
+/// `id="…"` attributes.
+fn attrs<'a>(html: &'a str, start: &str) -> Vec<&'a str> {
+    html.split(start)
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .collect()
+}
+
+/// Export a document to PDF/A-2b.
+fn pdfa(document: &Document, page_ranges: Option<PageRanges>) -> SourceResult<Vec<u8>> {
+    let options = PdfOptions {
+        standard: PdfStandard::A2b,
+        page_ranges,
+        ..PdfOptions::default()
+    };
+    typst::export::pdf(document, &options)
+}
+
+/// Count the text runs in a frame, including nested ones.
+fn count_text(frame: &Frame) -> usize {
+    frame
+        .items()
+        .map(|(_, item)| match item {
+            FrameItem::Group(group) => count_text(&group.frame),
+            FrameItem::Text(_) => 1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The indirect objects of a PDF file, as pairs of their object number and
+/// their textual body.
+///
+/// Streams are binary, so their contents end up garbled, but the dictionaries
+/// that we are interested in are plain text.
+fn objects(pdf: &[u8]) -> Vec<(i32, String)> {
+    let text = String::from_utf8_lossy(pdf);
+    text.split("endobj")
+        .filter_map(|chunk| {
+            let (head, body) = chunk.split_once(" 0 obj")?;
+            let id = head.rsplit(|c: char| c.is_ascii_whitespace()).next()?;
+            Some((id.parse().ok()?, body.to_string()))
+        })
+        .collect()
+}
+
+/// The numbers that directly follow a key, e.g. all MCIDs of an object.
+fn numbers(body: &str, key: &str) -> Vec<i32> {
+    body.split(key)
+        .skip(1)
+        .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+        .collect()
+}
+
+/// The object numbers of all indirect references in an object.
+fn refs(body: &str) -> Vec<i32> {
+    let tokens: Vec<&str> = body
+        .split(|c: char| c.is_ascii_whitespace() || matches!(c, '[' | ']'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens
+        .windows(3)
+        .filter(|window| window[1] == "0" && window[2] == "R")
+        .filter_map(|window| window[0].parse().ok())
+        .collect()
+}
+
+/// The body of the single object that contains all of the needles.
+fn object<'a>(objects: &'a [(i32, String)], needles: &[&str]) -> (i32, &'a str) {
+    let mut found = objects
+        .iter()
+        .filter(|(_, body)| needles.iter().all(|needle| body.contains(needle)));
+    let (id, body) = found.next().expect("object not found");
+    assert!(found.next().is_none(), "object is ambiguous");
+    (*id, body)
+}
+
+#[test]
+fn test_pdf_structure_of_multi_line_paragraph() {
+    let document = compile(
+        "#set page(width: 100pt, height: auto)\n\
+         This paragraph is long enough to be broken into several lines, \
+         all of which belong to the same structure element.",
+    );
+
+    let runs = count_text(&document.pages[0]);
+    assert!(runs > 3, "paragraph should span multiple lines");
+
+    let pdf = typst::export::pdf(&document, &PdfOptions::default()).unwrap();
+    let objects = objects(&pdf);
+
+    // The paragraph is a single element below the document element.
+    let (doc_id, _) = object(&objects, &["/Type /StructElem", "/S /Document"]);
+    let (par_id, par) = object(&objects, &["/Type /StructElem", "/S /P\n"]);
+    assert_eq!(numbers(par, "/P "), [doc_id]);
+
+    // All text runs of all lines are part of one marked content sequence of
+    // the paragraph, instead of being artifacts or sequences of their own.
+    assert_eq!(par.matches("/Type /MCR").count(), 1);
+    assert_eq!(numbers(par, "/MCID "), [0]);
+
+    // The parent tree maps the marked content of the page back to the
+    // paragraph.
+    let (tree_id, tree) = object(&objects, &["/Nums"]);
+    assert_eq!(refs(tree), [par_id]);
+
+    let (_, root) = object(&objects, &["/Type /StructTreeRoot"]);
+    assert_eq!(numbers(root, "/K "), [doc_id]);
+    assert_eq!(numbers(root, "/ParentTree "), [tree_id]);
+}
+
+#[test]
+fn test_pdf_structure_of_table() {
+    let document = compile("#table(columns: 2, [A], [B], [C], [D])");
+    let pdf = typst::export::pdf(&document, &PdfOptions::default()).unwrap();
+    let objects = objects(&pdf);
+    let elems = |kind: &str| -> Vec<(i32, &str)> {
+        let needle = format!("/S /{kind}\n");
+        objects
+            .iter()
+            .filter(|(_, body)| body.contains("/Type /StructElem"))
+            .filter(|(_, body)| body.contains(&needle))
+            .map(|(id, body)| (*id, body.as_str()))
+            .collect()
+    };
+
+    // The table is directly below the document and has one row element per
+    // row of the grid.
+    let (doc_id, _) = object(&objects, &["/Type /StructElem", "/S /Document"]);
+    let tables = elems("Table");
+    assert_eq!(tables.len(), 1);
+    let (table_id, table) = tables[0];
+    assert_eq!(numbers(table, "/P "), [doc_id]);
+
+    let rows = elems("TR");
+    assert_eq!(rows.len(), 2);
+    let row_ids: Vec<i32> = rows.iter().map(|&(id, _)| id).collect();
+    assert_eq!(refs(table), [doc_id, row_ids[0], row_ids[1]]);
+
+    // Each row holds two cells, whose content is marked directly, without the
+    // paragraphs in between.
+    let cells = elems("TD");
+    assert_eq!(cells.len(), 4);
+    for (i, (_, cell)) in cells.iter().enumerate() {
+        assert_eq!(numbers(cell, "/P "), [row_ids[i / 2]]);
+        assert_eq!(cell.matches("/Type /MCR").count(), 1);
+    }
+
+    assert!(elems("P").is_empty());
+}
+
+#[test]
+fn test_html_heading_and_internal_link() {
+    let html = html(
+        "#set heading(numbering: \"1.\")\n\
+         = Intro <intro>\n\
+         See #link(<intro>)[the introduction].",
+    );
+
+    // The heading is numbered from the counter state of the layout pass.
+    assert!(html.contains(">1. Intro</h1>"), "{html}");
+
+    // The link points to the heading's anchor.
+    let ids = attrs(&html, "<h1 id=\"");
+    assert_eq!(ids.len(), 1, "{html}");
+    assert!(ids[0].starts_with("loc-"));
+    assert_eq!(attrs(&html, "<a href=\"#"), ids);
+    assert!(html.contains(">the introduction</a>"), "{html}");
+}
+
+#[test]
+fn test_html_lists() {
+    let html = html("- a\n- b\n\n3. c\n+ d");
+    assert!(html.contains("<ul>\n<li>a</li>\n<li>b</li>\n</ul>\n"), "{html}");
+    assert!(html.contains("<ol>\n<li value=\"3\">c</li>\n<li>d</li>\n</ol>\n"), "{html}");
+}
+
+#[test]
+fn test_html_table() {
+    let html = html("#table(columns: 2, [a], [b], [c], [d])");
+    assert!(html.contains("<table>\n"), "{html}");
+    assert!(html.contains("<tr><td>a</td>\n<td>b</td>\n</tr>\n"), "{html}");
+    assert!(html.contains("<tr><td>c</td>\n<td>d</td>\n</tr>\n"), "{html}");
+    assert!(html.contains("</table>\n"), "{html}");
+}
+
+#[test]
+fn test_html_footnotes() {
+    let html = html("First#footnote[One] and second#footnote[Two].");
+
+    // The markers link to the notes, which are numbered like in the layout.
+    let hrefs = attrs(&html, "<sup><a href=\"#");
+    assert_eq!(hrefs.len(), 2, "{html}");
+    assert!(hrefs.iter().all(|href| href.starts_with("fn-loc-")));
+    assert_ne!(hrefs[0], hrefs[1]);
+    assert!(html.contains(&format!("<a href=\"#{}\">1</a>", hrefs[0])), "{html}");
+    assert!(html.contains(&format!("<a href=\"#{}\">2</a>", hrefs[1])), "{html}");
+
+    // The notes are collected at the end of the document.
+    let (_, notes) = html.split_once("<section class=\"footnotes\">").unwrap();
+    assert!(notes.contains(&format!("<li id=\"{}\">One</li>", hrefs[0])), "{html}");
+    assert!(notes.contains(&format!("<li id=\"{}\">Two</li>", hrefs[1])), "{html}");
+}
+
+#[test]
+fn test_html_smart_quotes() {
+    let html = html("\"Hi,\" she said. It's 'fine'.");
+    assert!(html.contains("“Hi,” she said. It’s ‘fine’."), "{html}");
+
+    let html = html("#set text(lang: \"de\")\n\"Hallo\" und 'so'.");
+    assert!(html.contains("„Hallo“ und ‚so‘."), "{html}");
+
+    let html = html("#set smartquote(enabled: false)\n\"Hi\"");
+    assert!(html.contains("&quot;Hi&quot;"), "{html}");
+}
+
+#[test]
+fn test_html_equations_are_svg() {
+    let html = html("Inline $x^2$ and display:\n$ a + b $");
+    assert!(html.contains("<span class=\"equation\"><svg"), "{html}");
+    assert!(html.contains("<div id=\"loc-"), "{html}");
+    assert_eq!(html.matches("class=\"equation\"><svg").count(), 2, "{html}");
+}
+
+#[test]
+fn test_pdfa_reports_all_violations() {
+    let document = compile(
+        "Missing glyph: 你\n\
+         #page(width: 2pt, height: 2pt, margin: 0pt)[#rect(width: 1pt, height: 1pt)]",
+    );
+
+    let errors = pdfa(&document, None).unwrap_err();
+    assert_eq!(errors.len(), 2);
+
+    let glyph = errors
+        .iter()
+        .find(|error| error.message.contains("has no glyph"))
+        .expect("missing glyph should be reported");
+    assert!(glyph.message.ends_with("has no glyph for \"你\""), "{}", glyph.message);
+    assert!(!glyph.span.is_detached());
+    assert!(glyph.hints.iter().any(|hint| hint.contains(".notdef")));
+
+    let page = errors
+        .iter()
+        .find(|error| error.message.starts_with("page "))
+        .expect("page size should be reported");
+    assert_eq!(page.message.as_str(), "page 2 is too small or too large for PDF/A");
+    assert!(!page.span.is_detached());
+    assert_eq!(page.hints.len(), 1);
+    assert_eq!(
+        page.hints[0].as_str(),
+        "PDF/A requires page dimensions between 3pt and 14400pt"
+    );
+}
+
+#[test]
+fn test_pdfa_forbids_cmyk_colors() {
+    let document = compile(
+        "#let ink = cmyk(0%, 0%, 0%, 100%)\n\
+         #text(fill: ink)[Ink]\n\
+         #rect(stroke: ink)\n\
+         #rect(fill: rgb(\"#000000\"))",
+    );
+
+    let errors = pdfa(&document, None).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    for error in errors.iter() {
+        assert_eq!(error.message.as_str(), "PDF/A does not permit CMYK colors");
+        assert!(!error.span.is_detached());
+    }
+}
+
+#[test]
+fn test_pdfa_only_validates_exported_pages() {
+    let document = compile("Fine\n#page(width: 2pt, height: 2pt, margin: 0pt)[]");
+    let first = PageRanges::range(Some(1), Some(1));
+    assert!(pdfa(&document, None).is_err());
+    assert!(pdfa(&document, Some(PageRanges::new(vec![first]))).is_ok());
+}
+
+#[test]
+fn test_pdfa_output_intent_and_metadata() {
+    let document = compile("Archived");
+    let pdf = pdfa(&document, None).unwrap();
+    let objects = objects(&pdf);
+
+    // The catalog references the sRGB output intent.
+    let (intents_id, intents) = object(&objects, &["/Type /OutputIntent"]);
+    assert!(intents.contains("/S /GTS_PDFA1"));
+    assert!(intents.contains("/OutputConditionIdentifier (sRGB)"));
+    let (_, catalog) = object(&objects, &["/Type /Catalog"]);
+    assert_eq!(numbers(catalog, "/OutputIntents "), [intents_id]);
+
+    let profile_id = numbers(intents, "/DestOutputProfile ")[0];
+    let (_, profile) = objects.iter().find(|(id, _)| *id == profile_id).unwrap();
+    assert!(profile.contains("/N 3"));
+
+    // The metadata declares the conformance level.
+    let (_, metadata) = object(&objects, &["/Type /Metadata"]);
+    assert!(metadata.contains("<pdfaid:part>2</pdfaid:part>"), "{metadata}");
+    assert!(
+        metadata.contains("<pdfaid:conformance>B</pdfaid:conformance>"),
+        "{metadata}"
+    );
+
+    // Plain PDFs have neither.
+    let plain = typst::export::pdf(&document, &PdfOptions::default()).unwrap();
+    let plain = String::from_utf8_lossy(&plain);
+    assert!(!plain.contains("/OutputIntents"));
+    assert!(!plain.contains("pdfaid:"));
+}
@@ -21,9 +21,10 @@ use typst::file::FileId;
 use unscanny::Scanner;
 use walkdir::WalkDir;
 
-use typst::diag::{bail, FileError, FileResult, StrResult};
+use typst::diag::{bail, FileError, FileResult, Severity, StrResult};
 use typst::doc::{Document, Frame, FrameItem, Meta};
 use typst::eval::{eco_format, func, Datetime, Library, NoneValue, Value};
+use typst::export::PdfOptions;
 use typst::font::{Font, FontBook};
 use typst::geom::{Abs, Color, RgbaColor, Smart};
 use typst::syntax::{Source, Span, SyntaxNode};
@@ -205,6 +206,8 @@ struct TestWorld {
     book: Prehashed<FontBook>,
     fonts: Vec<Font>,
     paths: RefCell<HashMap<PathBuf, PathSlot>>,
+    /// The files written by the current test, in order.
+    writes: RefCell<Vec<(FileId, Bytes)>>,
 }
 
 #[derive(Clone)]
@@ -235,6 +238,7 @@ impl TestWorld {
             book: Prehashed::new(FontBook::from_fonts(&fonts)),
             fonts,
             paths: RefCell::default(),
+            writes: RefCell::default(),
         }
     }
 }
@@ -270,6 +274,11 @@ impl World for TestWorld {
             .clone()
     }
 
+    fn write(&self, id: FileId, data: &[u8]) -> FileResult<()> {
+        self.writes.borrow_mut().push((id, Bytes::from(data.to_vec())));
+        Ok(())
+    }
+
     fn font(&self, id: usize) -> Option<Font> {
         Some(self.fonts[id].clone())
     }
@@ -281,6 +290,7 @@ impl World for TestWorld {
 
 impl TestWorld {
     fn set(&mut self, path: &Path, text: String) -> Source {
+        self.writes.borrow_mut().clear();
         self.main = FileId::new(None, &Path::new("/").join(path));
         let mut slot = self.slot(self.main).unwrap();
         let source = Source::new(self.main, text);
@@ -294,7 +304,8 @@ impl TestWorld {
             None => PathBuf::new(),
         };
 
-        let system_path = root.join_rooted(id.path()).ok_or(FileError::AccessDenied)?;
+        let system_path =
+            root.join_rooted(id.path()).ok_or(FileError::AccessDenied(None))?;
 
         Ok(RefMut::map(self.paths.borrow_mut(), |paths| {
             paths.entry(system_path.clone()).or_insert_with(|| PathSlot {
@@ -405,7 +416,7 @@ fn test(
     let document = Document { pages: frames, ..Default::default() };
     if compare_ever {
         if let Some(pdf_path) = pdf_path {
-            let pdf_data = typst::export::pdf(&document);
+            let pdf_data = typst::export::pdf(&document, &PdfOptions::default()).unwrap();
             fs::create_dir_all(pdf_path.parent().unwrap()).unwrap();
             fs::write(pdf_path, pdf_data).unwrap();
         }
@@ -520,30 +531,36 @@ fn test_part(
         writeln!(output, "Model:\n{:#?}\n", module.content()).unwrap();
     }
 
-    let (mut frames, errors) = match typst::compile(world) {
+    let mut tracer = typst::eval::Tracer::default();
+    let (mut frames, errors) = match typst::compile(world, &mut tracer)
+        .and_then(|document| tracer.flush_writes(world).map(|()| document))
+    {
         Ok(document) => (document.pages, vec![]),
         Err(errors) => (vec![], *errors),
     };
+    let diagnostics = tracer.warnings().iter().cloned().chain(errors);
 
     // Don't retain frames if we don't wanna compare with reference images.
     if !compare_ref {
         frames.clear();
     }
 
-    // Map errors to range and message format, discard traces and errors from
-    // other files, collect hints.
+    // Map errors and warnings to range and message format, discard traces and
+    // errors from other files, collect hints.
     //
     // This has one caveat: due to the format of the expected hints, we can not
     // verify if a hint belongs to a error or not. That should be irrelevant
     // however, as the line of the hint is still verified.
-    let actual_errors_and_hints: HashSet<UserOutput> = errors
-        .into_iter()
+    let actual_errors_and_hints: HashSet<UserOutput> = diagnostics
         .inspect(|error| assert!(!error.span.is_detached()))
         .filter(|error| error.span.id() == source.id())
         .flat_map(|error| {
             let range = error.span.range(world);
-            let output_error =
-                UserOutput::Error(range.clone(), error.message.replace('\\', "/"));
+            let message = error.message.replace('\\', "/");
+            let output_error = match error.severity {
+                Severity::Error => UserOutput::Error(range.clone(), message),
+                Severity::Warning => UserOutput::Warning(range.clone(), message),
+            };
             let hints = error
                 .hints
                 .iter()
@@ -551,6 +568,10 @@ fn test_part(
                 .map(|hint| UserOutput::Hint(range.clone(), hint.to_string()));
             iter::once(output_error).chain(hints).collect::<Vec<_>>()
         })
+        .chain(world.writes.borrow().iter().map(|(id, data)| {
+            let path = id.to_string().replace('\\', "/");
+            UserOutput::Write(path, format!("{:?}", String::from_utf8_lossy(data)))
+        }))
         .collect();
 
     // Basically symmetric_difference, but we need to know where an item is coming from.
@@ -593,7 +614,12 @@ fn print_user_output(
 ) {
     let (range, message) = match &user_output {
         UserOutput::Error(r, m) => (r, m),
+        UserOutput::Warning(r, m) => (r, m),
         UserOutput::Hint(r, m) => (r, m),
+        UserOutput::Write(path, data) => {
+            writeln!(output, "Write: {path} {data}").unwrap();
+            return;
+        }
     };
 
     let start_line = 1 + line + source.byte_to_line(range.start).unwrap();
@@ -602,7 +628,9 @@ fn print_user_output(
     let end_col = 1 + source.byte_to_column(range.end).unwrap();
     let kind = match user_output {
         UserOutput::Error(_, _) => "Error",
+        UserOutput::Warning(_, _) => "Warning",
         UserOutput::Hint(_, _) => "Hint",
+        UserOutput::Write(_, _) => unreachable!(),
     };
     writeln!(output, "{kind}: {start_line}:{start_col}-{end_line}:{end_col}: {message}")
         .unwrap();
@@ -621,14 +649,19 @@ struct TestPartMetadata {
 #[derive(PartialEq, Eq, Debug, Hash)]
 enum UserOutput {
     Error(Range<usize>, String),
+    Warning(Range<usize>, String),
     Hint(Range<usize>, String),
+    /// A written file with its path and its data as a debug string.
+    Write(String, String),
 }
 
 impl UserOutput {
     fn start(&self) -> usize {
         match self {
             UserOutput::Error(r, _) => r.start,
+            UserOutput::Warning(r, _) => r.start,
             UserOutput::Hint(r, _) => r.start,
+            UserOutput::Write(_, _) => usize::MAX,
         }
     }
 
@@ -636,6 +669,10 @@ impl UserOutput {
         UserOutput::Error(range, message)
     }
 
+    fn warning(range: Range<usize>, message: String) -> UserOutput {
+        UserOutput::Warning(range, message)
+    }
+
     fn hint(range: Range<usize>, message: String) -> UserOutput {
         UserOutput::Hint(range, message)
     }
@@ -667,12 +704,18 @@ fn parse_part_metadata(source: &Source) -> TestPartMetadata {
         };
 
         let error_factory: fn(Range<usize>, String) -> UserOutput = UserOutput::error;
+        let warning_factory: fn(Range<usize>, String) -> UserOutput = UserOutput::warning;
         let hint_factory: fn(Range<usize>, String) -> UserOutput = UserOutput::hint;
 
         let error_metadata = get_metadata(line, "Error").map(|s| (s, error_factory));
+        let get_warning_metadata =
+            || get_metadata(line, "Warning").map(|s| (s, warning_factory));
         let get_hint_metadata = || get_metadata(line, "Hint").map(|s| (s, hint_factory));
 
-        if let Some((expectation, factory)) = error_metadata.or_else(get_hint_metadata) {
+        if let Some((expectation, factory)) = error_metadata
+            .or_else(get_warning_metadata)
+            .or_else(get_hint_metadata)
+        {
             let mut s = Scanner::new(expectation);
             let start = pos(&mut s);
             let end = if s.eat_if('-') { pos(&mut s) } else { start };
@@ -680,6 +723,12 @@ fn parse_part_metadata(source: &Source) -> TestPartMetadata {
 
             expectations.insert(factory(range, s.after().trim().to_string()));
         };
+
+        if let Some((path, data)) =
+            get_metadata(line, "Write").and_then(|s| s.split_once(' '))
+        {
+            expectations.insert(UserOutput::Write(path.into(), data.into()));
+        }
     }
 
     TestPartMetadata {
@@ -15,6 +15,8 @@
 
 // Should output `bye`.
 // Stop at semicolon.
+// Warning: 23-24 unused import: a
+// Warning: 26-27 unused import: c
 #import "module.typ": a, c;bye
 
 ---
@@ -71,6 +73,8 @@
 #import"module.typ":*
 
 // Allow the trailing comma.
+// Warning: 23-24 unused import: a
+// Warning: 26-27 unused import: c
 #import "module.typ": a, c,
 
 ---
@@ -11,6 +11,10 @@
 #import "@test/adder:0.1.0": add
 #test(add(2, 8), 10)
 
+---
+// Error: 9-29 package requires typst >=99.0, but this is typst 0.6.0
+#import "@test/future:0.1.0": *
+
 ---
 // Error: 9-13 `@` is not a valid package namespace
 #import "@@": *
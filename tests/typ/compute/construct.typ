@@ -223,3 +223,42 @@
 ---
 // Error: 26-36 failed to format datetime in the requested format
 #datetime.today().display("[hour]")
+
+---
+// Test the bytes constructor and methods.
+#let data = bytes("Hello")
+#test(type(data), "bytes")
+#test(data.len(), 5)
+#test(data.at(0), 72)
+#test(data.at(-1), 111)
+#test(data.at(5, default: none), none)
+#test(str(data.slice(1, 3)), "el")
+#test(str(data.slice(1, count: 2)), "el")
+#test(bytes((72, 105)), bytes("Hi"))
+#test(bytes(data), data)
+#test(str(data + bytes("!")), "Hello!")
+#test(str(bytes("😃")), "😃")
+
+---
+// Test base64 and hex encoding.
+#let data = bytes("Hello")
+#test(data.base64(), "SGVsbG8=")
+#test(data.hex(), "48656c6c6f")
+#test(bytes.from-base64("SGVsbG8="), data)
+#test(bytes.from-hex("48656C6C6F"), data)
+
+---
+// Error: 8-14 number too large
+#bytes((256,))
+
+---
+// Error: 2-22 byte index out of bounds (index: 5, len: 5) and no default value was specified
+#bytes("Hello").at(5)
+
+---
+// Error: 16-22 invalid hex string
+#bytes.from-hex("zz")
+
+---
+// Error: 6-19 bytes are not valid utf-8
+#str(bytes((255,)))
@@ -6,6 +6,13 @@
 #let data = read("/files/hello.txt")
 #test(data, "Hello, world!")
 
+---
+// Test reading files as bytes.
+#let data = read("/files/hello.txt", encoding: none)
+#test(type(data), "bytes")
+#test(data.len(), 13)
+#test(str(data), "Hello, world!")
+
 ---
 // Error: 18-38 file not found (searched at files/missing.txt)
 #let data = read("/files/missing.txt")
@@ -14,6 +21,30 @@
 // Error: 18-34 file is not valid utf-8
 #let data = read("/files/bad.txt")
 
+---
+// Test writing files. Repeating a write has no effect.
+// Write: /typ/compute/hello.txt "Hello, world!"
+// Write: /nested/data.bin "\u{1}\u{2}\u{3}"
+#write("hello.txt", "Hello, world!")
+#write("hello.txt", bytes("Hello, world!"))
+#write("/nested/data.bin", bytes((1, 2, 3)))
+
+---
+// Test that only the writes of the final layout iteration count.
+// Write: /typ/compute/figures.txt "([First], [Second])"
+#figure([A], caption: [First])
+#figure([B], caption: [Second])
+#locate(loc => {
+  let figures = query(figure, loc)
+  write("figures.txt", repr(figures.map(fig => fig.caption)))
+})
+
+---
+#write("hello.txt", "Hello")
+// Error: 8-19 conflicting writes to /typ/compute/hello.txt
+// Hint: 8-19 the file was already written to with different data
+#write("hello.txt", "World")
+
 ---
 // Test reading CSV data.
 // Ref: true
@@ -30,6 +61,32 @@
 // Error: 6-22 failed to parse csv file: found 3 instead of 2 fields in line 3
 #csv("/files/bad.csv")
 
+---
+// Test decoding CSV data.
+#test(csv.decode("a,b\nc,d"), (("a", "b"), ("c", "d")))
+#test(csv.decode("a;b\nc;d", delimiter: ";"), (("a", "b"), ("c", "d")))
+
+---
+// Error: 13-21 failed to parse csv data: found 1 instead of 2 fields in line 2
+#csv.decode("a,b\nc")
+
+---
+// Test encoding CSV data.
+#test(csv.encode((("a", 1), ("b", none))), "a,1\nb,\n")
+#test(csv.encode((("a", true),), delimiter: ";"), "a;true\n")
+
+---
+// Error: 13-33 failed to encode csv: found 1 instead of 2 fields in row 2
+#csv.encode((("a", "b"), ("c",)))
+
+---
+// Error: 13-33 cannot encode length as csv in row 2, column 2
+#csv.encode((("a", 1), (2, 1pt)))
+
+---
+// Error: 13-24 expected array for row 2, found integer
+#csv.encode((("a",), 1))
+
 ---
 // Test reading JSON data.
 #let data = json("/files/zoo.json")
@@ -41,6 +98,34 @@
 // Error: 7-24 failed to parse json file: syntax error in line 3
 #json("/files/bad.json")
 
+---
+// Test decoding JSON data.
+#test(json.decode("{\"a\": [1, 2.5, null]}"), (a: (1, 2.5, none)))
+#let data = (name: "Debby", weight: 150, tags: ("zebra",))
+#test(json.decode(json.encode(data)), data)
+
+---
+// Error: 14-17 failed to parse json data: syntax error in line 1
+#json.decode("{")
+
+---
+// Error: 14-16 expected string or bytes, found integer
+#json.decode(12)
+
+---
+// Test encoding JSON data.
+#test(json.encode((a: 1, b: (2.5, "c", none, true)), pretty: false), "{\"a\":1,\"b\":[2.5,\"c\",null,true]}")
+#test(json.encode((x: 1)), "{\n  \"x\": 1\n}")
+#test(json.encode(datetime(year: 2023, month: 2, day: 1), pretty: false), "\"2023-02-01\"")
+
+---
+// Error: 14-18 cannot encode content as json
+#json.encode([hi])
+
+---
+// Error: 14-23 cannot encode function as json
+#json.encode((f: rect))
+
 ---
 // Test reading TOML data.
 #let data = toml("/files/toml-types.toml")
@@ -65,6 +150,28 @@
 // Error: 7-24 failed to parse toml file: expected `.`, `=`, index 15-15
 #toml("/files/bad.toml")
 
+---
+// Test decoding TOML data.
+#test(toml.decode("x = 1\n[y]\nz = true"), (x: 1, y: (z: true)))
+
+---
+// Error: 14-27 data is not valid utf-8
+#toml.decode(bytes((255,)))
+
+---
+// Test encoding TOML data.
+#test(toml.encode((title: "Zoo", count: 3)), "count = 3\ntitle = \"Zoo\"\n")
+#let data = toml("/files/toml-types.toml")
+#test(toml.encode(data.table, pretty: false), "element = 5\nothers = [false, \"indeed\", 7]\n")
+
+---
+// Error: 14-18 expected dictionary, found array
+#toml.encode((1,))
+
+---
+// Error: 14-23 cannot encode none as toml
+#toml.encode((a: none))
+
 ---
 // Test reading YAML data
 #let data = yaml("/files/yaml-types.yaml")
@@ -83,6 +190,26 @@
 // Error: 7-24 failed to parse yaml file: while parsing a flow sequence, expected ',' or ']' at line 2 column 1
 #yaml("/files/bad.yaml")
 
+---
+// Test decoding YAML data.
+#test(yaml.decode("- 1\n- two\n- ~"), (1, "two", none))
+
+---
+// Test encoding YAML data.
+#test(yaml.encode((a: 1)), "---\na: 1\n")
+
+---
+// Error: 14-17 cannot encode length as yaml
+#yaml.encode(1pt)
+
+---
+// Test decoding XML data.
+#test(xml.decode("<a x=\"1\">hi</a>"), ((tag: "a", attrs: (x: "1"), children: ("hi",)),))
+
+---
+// Error: 13-22 failed to parse xml data: found closing tag 'b' instead of 'a' in line 1
+#xml.decode("<a></b>")
+
 ---
 // Test reading XML data.
 #let data = xml("/files/data.xml")
@@ -120,3 +120,8 @@ _No relative giraffe!_
 ---
 // Error: 7-12 expected semicolon or line break
 #eval("1 2")
+
+---
+// Without inputs from the command line, the dictionary is empty.
+#test(type(sys.inputs), "dictionary")
+#test(sys.inputs.at("draft", default: "false"), "false")
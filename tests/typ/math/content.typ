@@ -15,5 +15,6 @@ $ x := #table(columns: 2)[x][y]/mat(1, 2, 3)
 
 ---
 // Test font switch.
+// Warning: 29-40 unknown font family: noto sans
 #let here = text.with(font: "Noto Sans")
 $#here[f] := #here[Hi there]$.
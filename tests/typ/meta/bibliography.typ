@@ -2,8 +2,10 @@
 
 ---
 // Test ambiguous reference.
+// Ref: false
 = Introduction <arrgh>
-// Error: 1-7 label occurs in the document and its bibliography
+// Warning: 1-7 label occurs in the document and its bibliography
+// Hint: 1-7 the reference cites the bibliography entry
 @arrgh
 #bibliography("/files/works.bib")
 
@@ -4,6 +4,7 @@
 #set page(width: 160pt)
 #set text(size: 8pt)
 
+// Warning: 2:19-2:34 unknown font family: ibm plex mono
 #let try(top, bottom) = rect(inset: 0pt, fill: conifer)[
   #set text(font: "IBM Plex Mono", top-edge: top, bottom-edge: bottom)
   From #top to #bottom
@@ -57,6 +57,10 @@ A #box(image("/files/tiger.jpg", height: 1cm, width: 80%)) B
 // Error: 2-22 unknown image format
 #image("./image.typ")
 
+---
+// Error: 2-23 unknown image format
+#image.decode("hello")
+
 ---
 // Error: 2-25 failed to parse svg: found closing tag 'g' instead of 'style' in line 4
 #image("/files/bad.svg")